@@ -411,7 +411,7 @@ pub mod tests {
         };
         use halo2_proofs::{
             circuit::{Layouter, SimpleFloorPlanner},
-            dev::{FailureLocation, MockProver, VerifyFailure},
+            dev::{format_field, FailureLocation, MockProver, VerifyFailure},
             plonk::{Circuit, ConstraintSystem, Error},
         };
 
@@ -497,25 +497,6 @@ pub mod tests {
             }
         }
 
-        // Copied from halo2_proofs::dev::util
-        fn format_value(v: pallas::Base) -> String {
-            use ff::Field;
-            if v.is_zero_vartime() {
-                "0".into()
-            } else if v == pallas::Base::one() {
-                "1".into()
-            } else if v == -pallas::Base::one() {
-                "-1".into()
-            } else {
-                // Format value as hex.
-                let s = format!("{:?}", v);
-                // Remove leading zeroes.
-                let s = s.strip_prefix("0x").unwrap();
-                let s = s.trim_start_matches('0');
-                format!("0x{}", s)
-            }
-        }
-
         // Magnitude larger than 64 bits should fail
         {
             let circuits = [
@@ -581,7 +562,7 @@ pub mod tests {
                                 },
                                 cell_values: vec![(
                                     ((Any::advice(), 5).into(), 0).into(),
-                                    format_value(*magnitude_error),
+                                    format_field(*magnitude_error),
                                 )],
                             },
                             VerifyFailure::Permutation {
@@ -647,11 +628,11 @@ pub mod tests {
                         cell_values: vec![
                             (
                                 ((Any::advice(), 1).into(), 0).into(),
-                                format_value(negation_check_y),
+                                format_field(negation_check_y),
                             ),
                             (
                                 ((Any::advice(), 3).into(), 0).into(),
-                                format_value(negation_check_y),
+                                format_field(negation_check_y),
                             ),
                             (((Any::advice(), 4).into(), 0).into(), "0".to_string()),
                         ],
@@ -352,6 +352,15 @@ impl<'r, F: Field> Region<'r, F> {
     pub fn constrain_equal(&mut self, left: Cell, right: Cell) -> Result<(), Error> {
         self.region.constrain_equal(left, right)
     }
+
+    /// Marks a cell as an output of the circuit.
+    ///
+    /// This doesn't constrain the cell. It makes the values that a chip intends to
+    /// expose explicit, so that they can be checked with
+    /// [`MockProver::outputs`](crate::dev::MockProver::outputs).
+    pub fn mark_output(&mut self, cell: Cell) -> Result<(), Error> {
+        self.region.mark_output(cell)
+    }
 }
 
 /// A lookup table in the circuit.
@@ -372,6 +372,13 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> RegionLayouter<F>
 
         Ok(())
     }
+
+    fn mark_output(&mut self, cell: Cell) -> Result<(), Error> {
+        self.layouter.cs.mark_output(
+            cell.column,
+            *self.layouter.regions[*cell.region_index] + cell.row_offset,
+        )
+    }
 }
 
 /// The default value to fill a table column with.
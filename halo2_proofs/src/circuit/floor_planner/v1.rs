@@ -491,6 +491,13 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> RegionLayouter<F> for V1Region<'r
 
         Ok(())
     }
+
+    fn mark_output(&mut self, cell: Cell) -> Result<(), Error> {
+        self.plan.cs.mark_output(
+            cell.column,
+            *self.plan.regions[*cell.region_index] + cell.row_offset,
+        )
+    }
 }
 
 #[cfg(test)]
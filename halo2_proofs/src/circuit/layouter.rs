@@ -102,6 +102,13 @@ pub trait RegionLayouter<F: Field>: fmt::Debug {
     ///
     /// Returns an error if either of the cells is not within the given permutation.
     fn constrain_equal(&mut self, left: Cell, right: Cell) -> Result<(), Error>;
+
+    /// Marks a cell as an output of the circuit.
+    ///
+    /// The default implementation ignores the mark, which doesn't affect the circuit.
+    fn mark_output(&mut self, _cell: Cell) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Helper trait for implementing a custom [`Layouter`].
@@ -1,14 +1,21 @@
 //! Tools for developing circuits.
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
 use std::iter;
-use std::ops::{Add, Mul, Neg, Range};
+use std::ops::{Add, Deref, Mul, Neg, Range};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
-use blake2b_simd::blake2b;
-use ff::Field;
+use blake2b_simd::{blake2b, Params as Blake2bParams};
+use ff::{Field, PrimeField};
+use rand_core::RngCore;
 
 use crate::{
     arithmetic::{FieldExt, Group},
@@ -30,8 +37,11 @@ use rayon::{
 pub mod metadata;
 mod util;
 
+mod error;
+pub use error::MockProverError;
+
 mod failure;
-pub use failure::{FailureLocation, VerifyFailure};
+pub use failure::{assert_contains_failure, FailureLocation, VerificationError, VerifyFailure};
 
 pub mod cost;
 pub use cost::CircuitCost;
@@ -39,14 +49,39 @@ pub use cost::CircuitCost;
 mod gates;
 pub use gates::CircuitGates;
 
+mod builder;
+pub use builder::MockProverBuilder;
+
+mod description;
+pub use description::{analyze_configuration, CircuitDescription};
+
+mod trace;
+pub use trace::CellDiff;
+
+mod formatter;
+pub use formatter::{format_field, FieldFormatter};
+
+mod cell_info;
+pub use cell_info::{AssignedCellInfo, CellTrace};
+
+mod explain;
+
+mod checked;
+
+mod replay;
+pub use replay::{replay_trace, AssignmentEvent};
+
+#[cfg(feature = "dev-serde")]
+mod serialization;
+
 #[cfg(feature = "dev-graph")]
 mod graph;
 
 #[cfg(feature = "dev-graph")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dev-graph")))]
-pub use graph::{circuit_dot_graph, layout::CircuitLayout};
+pub use graph::{circuit_dot_graph, layout::CircuitLayout, permutation_dot_graph};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Region {
     /// The name of the region. Not required to be unique.
     name: String,
@@ -98,17 +133,25 @@ enum Value<F: Group + Field> {
     Poison,
 }
 
-impl<F: Group + Field> From<CellValue<F>> for Value<F> {
-    fn from(value: CellValue<F>) -> Self {
-        match value {
-            // Cells that haven't been explicitly assigned to, default to zero.
-            CellValue::Unassigned => Value::Real(F::zero()),
+impl<F: Group + Field> CellValue<F> {
+    /// Converts this cell into a value within an expression, treating an unassigned
+    /// cell as holding `unassigned`.
+    fn with_default(self, unassigned: F) -> Value<F> {
+        match self {
+            CellValue::Unassigned => Value::Real(unassigned),
             CellValue::Assigned(v) => Value::Real(v),
             CellValue::Poison(_) => Value::Poison,
         }
     }
 }
 
+impl<F: Group + Field> From<CellValue<F>> for Value<F> {
+    fn from(value: CellValue<F>) -> Self {
+        // Cells that haven't been explicitly assigned to, default to zero.
+        value.with_default(F::zero())
+    }
+}
+
 impl<F: Group + Field> Neg for Value<F> {
     type Output = Self;
 
@@ -163,6 +206,641 @@ impl<F: Group + Field> Mul<F> for Value<F> {
     }
 }
 
+/// The seed from which [`MockProver::run`] derives the circuit's challenges.
+const DEFAULT_CHALLENGE_SEED: &[u8] = b"Halo2-MockProver";
+
+/// Converts the failures collected by a verification pass into its result.
+fn failures_to_result(mut errors: Vec<VerifyFailure>) -> Result<(), Vec<VerifyFailure>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        dedup_poisoned(&mut errors);
+        Err(errors)
+    }
+}
+
+/// Removes any duplicate `ConstraintPoisoned` errors (we check all unavailable rows in
+/// case the trigger is row-specific, but the error message only points at the
+/// constraint).
+fn dedup_poisoned(errors: &mut Vec<VerifyFailure>) {
+    errors.dedup_by(|a, b| match (a, b) {
+        (
+            a @ VerifyFailure::ConstraintPoisoned { .. },
+            b @ VerifyFailure::ConstraintPoisoned { .. },
+        ) => a == b,
+        _ => false,
+    });
+}
+
+/// Skips the `ConstraintPoisoned` failures that repeat the one before them as `failures`
+/// are found, as [`dedup_poisoned`] does for a collected list.
+fn skip_repeated_poisoned(
+    failures: impl Iterator<Item = VerifyFailure>,
+) -> impl Iterator<Item = VerifyFailure> {
+    let mut previous = None;
+    failures.filter(move |failure| {
+        let poisoned = match failure {
+            VerifyFailure::ConstraintPoisoned { constraint } => {
+                Some((constraint.gate.index, constraint.index))
+            }
+            _ => None,
+        };
+        let repeated = poisoned.is_some() && poisoned == previous;
+        previous = poisoned;
+        !repeated
+    })
+}
+
+/// Returns the selectors of `cs` whose usage contradicts their declared kind. This
+/// must be called before selectors are compressed.
+fn selector_kind_conflicts<F: Field>(cs: &ConstraintSystem<F>) -> Vec<SelectorKindConflict> {
+    let selectors_of = |expression: &Expression<F>| -> Vec<Selector> {
+        expression.evaluate(
+            &|_| vec![],
+            &|selector| vec![selector],
+            &|_| vec![],
+            &|_| vec![],
+            &|_| vec![],
+            &|_| vec![],
+            &|a| a,
+            &|mut a, mut b| {
+                a.append(&mut b);
+                a
+            },
+            &|mut a, mut b| {
+                a.append(&mut b);
+                a
+            },
+            &|a, _| a,
+        )
+    };
+    // Whether every term of `expression` is multiplied by `selector`.
+    let is_factor = |expression: &Expression<F>, selector: Selector| {
+        expression.evaluate(
+            &|_| false,
+            &|s| s == selector,
+            &|_| false,
+            &|_| false,
+            &|_| false,
+            &|_| false,
+            &|a| a,
+            &|a, b| a && b,
+            &|a, b| a || b,
+            &|a, _| a,
+        )
+    };
+
+    let mut conflicts = vec![];
+    // For each complex selector used by a gate, whether it could be simple.
+    let mut complex: BTreeMap<usize, (Selector, bool)> = BTreeMap::new();
+    for (gate_index, gate) in cs.gates.iter().enumerate() {
+        for (constraint_index, poly) in gate.polynomials().iter().enumerate() {
+            let selectors = selectors_of(poly);
+            let has_simple = selectors.iter().any(Selector::is_simple);
+            for selector in selectors {
+                let factor = is_factor(poly, selector);
+                if selector.is_simple() {
+                    if !factor {
+                        conflicts.push(SelectorKindConflict::NotAFactor {
+                            selector,
+                            gate_index,
+                            constraint_index,
+                        });
+                    }
+                } else {
+                    let could_be_simple =
+                        &mut complex.entry(selector.0).or_insert((selector, true)).1;
+                    *could_be_simple &= factor && !has_simple;
+                }
+            }
+        }
+    }
+    conflicts.dedup();
+
+    for lookup in cs.lookups.iter() {
+        for expression in lookup
+            .input_expressions
+            .iter()
+            .chain(lookup.table_expressions.iter())
+        {
+            for selector in selectors_of(expression) {
+                if let Some((_, could_be_simple)) = complex.get_mut(&selector.0) {
+                    *could_be_simple = false;
+                }
+            }
+        }
+    }
+    conflicts.extend(
+        complex
+            .into_values()
+            .filter(|(_, could_be_simple)| *could_be_simple)
+            .map(|(selector, _)| SelectorKindConflict::CouldBeSimple { selector }),
+    );
+    conflicts
+}
+
+/// Synthesizes `circuit` with [`MockProver::run`] `iterations` times, and returns the
+/// fixed and advice cells whose assigned values differ between any of the runs.
+///
+/// A circuit's witness generation should be deterministic. Non-determinism (for example
+/// from `HashMap` iteration order or an unseeded RNG) leaks into real proofs, and is
+/// hard to track down otherwise. An empty set means that no differences were found.
+pub fn check_determinism<F: FieldExt, ConcreteCircuit: Circuit<F>>(
+    k: u32,
+    circuit: &ConcreteCircuit,
+    instance: Vec<Vec<F>>,
+    iterations: usize,
+) -> Result<BTreeSet<(Column<Any>, usize)>, Error> {
+    let reference = MockProver::run(k, circuit, instance.clone())?;
+
+    let mut differing = BTreeSet::new();
+    for _ in 1..iterations {
+        let prover = MockProver::run(k, circuit, instance.clone())?;
+        differing.extend(reference.differing_cells(&prover));
+    }
+    Ok(differing)
+}
+
+/// Overwrites every usable advice cell of `prover` with a random field element.
+///
+/// This produces a worst-case dense witness, which is useful for benchmarking
+/// [`MockProver::verify`] and for stress-testing circuit checks. Fixed columns are
+/// defined by the circuit, and are left untouched, as are the rows reserved for
+/// blinding factors.
+pub fn random_fill<F: FieldExt>(prover: &mut MockProver<F>, mut rng: impl RngCore) {
+    let usable_rows = prover.usable_rows.clone();
+    for column in prover.advice.iter_mut() {
+        for cell in &mut column[usable_rows.clone()] {
+            *cell = CellValue::Assigned(F::random(&mut rng));
+        }
+    }
+}
+
+/// Checks that `circuit` constrains every advice cell it assigns, by corrupting a
+/// randomly chosen cell in each of `trials` trials and checking that
+/// [`MockProver::verify`] then fails.
+///
+/// Each trial replaces the value of one assigned advice cell with a different random
+/// value, and restores it afterwards. Unassigned cells, and the rows reserved for
+/// blinding factors, are never corrupted. This automates the "does my circuit
+/// constrain everything?" check for soundness testing.
+///
+/// Panics if the circuit fails to synthesize or is not satisfied to begin with, or if a
+/// corruption goes undetected, in which case the corrupted cell is reported.
+pub fn expect_sound<F: FieldExt, ConcreteCircuit: Circuit<F>>(
+    k: u32,
+    circuit: &ConcreteCircuit,
+    instance: Vec<Vec<F>>,
+    mut rng: impl RngCore,
+    trials: usize,
+) {
+    let mut prover = MockProver::run(k, circuit, instance).expect("circuit failed to synthesize");
+    assert_eq!(prover.verify(), Ok(()), "circuit is not satisfied");
+
+    let cells: Vec<(usize, usize)> = prover
+        .advice
+        .iter()
+        .enumerate()
+        .flat_map(|(column, cells)| {
+            prover
+                .usable_rows
+                .clone()
+                .filter(move |row| matches!(cells[*row], CellValue::Assigned(_)))
+                .map(move |row| (column, row))
+        })
+        .collect();
+    if cells.is_empty() {
+        return;
+    }
+
+    for _ in 0..trials {
+        let (column, row) = cells[(rng.next_u64() % cells.len() as u64) as usize];
+        let original = prover.advice[column][row];
+        let value = match original {
+            CellValue::Assigned(original) => {
+                let value = F::random(&mut rng);
+                if value == original {
+                    value + F::one()
+                } else {
+                    value
+                }
+            }
+            _ => unreachable!("only assigned cells are corrupted"),
+        };
+
+        prover.advice[column][row] = CellValue::Assigned(value);
+        let detected = prover.verify().is_err();
+        prover.advice[column][row] = original;
+
+        assert!(
+            detected,
+            "changing advice column {} at row {} to {} was not detected; the cell may be unconstrained",
+            column,
+            row,
+            format_field(value)
+        );
+    }
+}
+
+/// A type-erased [`Circuit`] that can be synthesized by a [`MockProver`].
+///
+/// `Circuit` has associated types and is not object-safe, so circuits of different
+/// types can't be passed to [`verify_all`] together as `&dyn Circuit<F>`. Every
+/// `Circuit` implements this trait, so they can be passed as `&dyn MockCircuit<F>`
+/// instead.
+pub trait MockCircuit<F: FieldExt> {
+    /// Synthesizes this circuit with [`MockProver::run`].
+    fn mock_prove(&self, k: u32, instance: Vec<Vec<F>>) -> Result<MockProver<F>, Error>;
+}
+
+impl<F: FieldExt, ConcreteCircuit: Circuit<F>> MockCircuit<F> for ConcreteCircuit {
+    fn mock_prove(&self, k: u32, instance: Vec<Vec<F>>) -> Result<MockProver<F>, Error> {
+        MockProver::run(k, self, instance)
+    }
+}
+
+/// Synthesizes and verifies each of the given `(k, circuit, instance)` cases, and
+/// returns the failures of each case along with its index in `cases`.
+///
+/// Every case is reported, in order, with an empty list of failures if it is
+/// satisfied. This gives a single aggregated report for a family of related circuits,
+/// such as in a property-based test suite.
+///
+/// Panics if any case fails to synthesize, as there is no witness to verify.
+pub fn verify_all<F: FieldExt>(
+    cases: &[(u32, &dyn MockCircuit<F>, Vec<Vec<F>>)],
+) -> Vec<(usize, Vec<VerifyFailure>)> {
+    cases
+        .iter()
+        .enumerate()
+        .map(|(index, (k, circuit, instance))| {
+            let prover = circuit
+                .mock_prove(*k, instance.clone())
+                .unwrap_or_else(|e| panic!("case {} failed to synthesize: {}", index, e));
+            let mut failures = Vec::new();
+            prover.verify_into(&mut failures);
+            (index, failures)
+        })
+        .collect()
+}
+
+/// Runs and verifies the same circuit with [`MockProver`] in each of several fields,
+/// returning the name of each field along with its verification result.
+///
+/// Some bugs only manifest in fields with a particular characteristic, such as a value
+/// that wraps around in a small field but not in a large one. A circuit whose result
+/// differs between fields has characteristic-dependent logic.
+///
+/// The circuit and its instance are given by an expression that is evaluated once for
+/// each field, with the identifier before it bound to the field type:
+///
+/// ```ignore
+/// use halo2curves::{bn256, pasta};
+///
+/// let results = halo2_proofs::verify_across_fields!(
+///     K,
+///     |F| (MyCircuit::<F>::new(), vec![vec![F::from(3)]]);
+///     pasta::Fp, pasta::Fq, bn256::Fr
+/// );
+/// let passed: Vec<_> = results.iter().map(|(_, result)| result.is_ok()).collect();
+/// assert!(passed.iter().all(|p| *p == passed[0]), "{:#?}", results);
+/// ```
+///
+/// Panics if the circuit fails to synthesize in any of the fields.
+#[macro_export]
+macro_rules! verify_across_fields {
+    ($k:expr, |$field:ident| $case:expr; $($ty:ty),+ $(,)?) => {{
+        let results: ::std::vec::Vec<(
+            &'static str,
+            ::std::result::Result<(), ::std::vec::Vec<$crate::dev::VerifyFailure>>,
+        )> = ::std::vec![$({
+            #[allow(non_camel_case_types, dead_code)]
+            type $field = $ty;
+            let (circuit, instance) = $case;
+            let prover = $crate::dev::MockProver::<$field>::run($k, &circuit, instance)
+                .unwrap_or_else(|e| {
+                    ::std::panic!("{} failed to synthesize: {}", ::std::stringify!($ty), e)
+                });
+            (::std::stringify!($ty), prover.verify())
+        }),+];
+        results
+    }};
+}
+
+/// A function that maps the tuple of values of a lookup's inputs, or of a row of its
+/// table, to a canonical form.
+///
+/// Set one for a lookup with [`MockProver::set_lookup_normalizer`].
+pub type LookupNormalizer<F> = fn(&[F]) -> Vec<F>;
+
+/// The order in which [`MockProver::first_failure`] and [`MockProver::iter_failures`]
+/// check the usable rows of the circuit.
+///
+/// Set with [`MockProver::set_scan_order`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// From the first row to the last. This is the default.
+    Forward,
+    /// From the last row to the first.
+    Reverse,
+    /// The rows of each region, in the order in which the regions were completed, and
+    /// then every row outside of any region.
+    RegionCompletion,
+}
+
+impl Default for ScanOrder {
+    fn default() -> Self {
+        ScanOrder::Forward
+    }
+}
+
+/// A selector whose usage in the constraint system contradicts its declared kind, as
+/// reported by [`MockProver::selector_kind_conflicts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelectorKindConflict {
+    /// A simple selector is used in a gate constraint that it doesn't multiply as a
+    /// whole, such as `(1 - q) * a`.
+    ///
+    /// Simple selectors may be compressed into a fixed column that takes arbitrary
+    /// non-zero values on the rows where they are enabled, which changes such a
+    /// constraint. The selector should be created with
+    /// [`ConstraintSystem::complex_selector`].
+    NotAFactor {
+        /// The simple selector.
+        selector: Selector,
+        /// The index of the gate.
+        gate_index: usize,
+        /// The index of the constraint within the gate.
+        constraint_index: usize,
+    },
+    /// A complex selector is only ever used to multiply whole gate constraints, and
+    /// never by a lookup, so it could be created with [`ConstraintSystem::selector`]
+    /// and be compressed with other simple selectors.
+    ///
+    /// This is checked for each selector on its own; two selectors that multiply the
+    /// same constraint can't both be simple.
+    CouldBeSimple {
+        /// The complex selector.
+        selector: Selector,
+    },
+}
+
+impl fmt::Display for SelectorKindConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAFactor {
+                selector,
+                gate_index,
+                constraint_index,
+            } => write!(
+                f,
+                "Simple selector {} does not multiply constraint {} of gate {}, and should be complex",
+                selector.0, constraint_index, gate_index
+            ),
+            Self::CouldBeSimple { selector } => write!(
+                f,
+                "Complex selector {} only multiplies gate constraints, and could be simple",
+                selector.0
+            ),
+        }
+    }
+}
+
+/// Wall-clock durations of the phases of [`MockProver::verify_timed`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VerifyTimings {
+    /// Time spent checking that cells used by enabled gates were assigned.
+    pub selectors: Duration,
+    /// Time spent evaluating gate constraints.
+    pub gates: Duration,
+    /// Time spent checking lookup inputs against their tables.
+    pub lookups: Duration,
+    /// Time spent checking that copy constraints preserve cell values.
+    pub permutations: Duration,
+}
+
+impl VerifyTimings {
+    /// Returns the total time spent verifying.
+    pub fn total(&self) -> Duration {
+        self.selectors + self.gates + self.lookups + self.permutations
+    }
+
+    /// Returns the time spent in `phase`.
+    fn phase_mut(&mut self, phase: VerifyPhase) -> &mut Duration {
+        match phase {
+            VerifyPhase::Selectors => &mut self.selectors,
+            VerifyPhase::Gates => &mut self.gates,
+            VerifyPhase::Lookups => &mut self.lookups,
+            VerifyPhase::Permutations => &mut self.permutations,
+        }
+    }
+}
+
+/// A phase of verification, as reported by [`MockProver::verify_with_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyPhase {
+    /// Checking that cells used by enabled gates were assigned.
+    Selectors,
+    /// Evaluating gate constraints.
+    Gates,
+    /// Checking lookup inputs against their tables.
+    Lookups,
+    /// Checking that copy constraints preserve cell values.
+    Permutations,
+}
+
+/// A check made while verifying a circuit.
+///
+/// Every verification method runs the checks in [`Check::ALL`], in that order, so that
+/// they all agree on what a satisfied circuit is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Check {
+    /// Cells used by gates enabled by simple selectors were assigned.
+    Selectors,
+    /// In the stricter verification mode, cells read by enabled lookups were assigned.
+    LookupCells,
+    /// In the stricter verification mode, cells read at a rotation were assigned, no
+    /// constraint relies on a wrapped rotation, and no cell was reassigned.
+    RotatedCells,
+    /// Gate constraints are satisfied.
+    Gates,
+    /// Lookup inputs exist in their tables.
+    Lookups,
+    /// Copy constraints preserve cell values.
+    Permutation,
+    /// If enabled with [`MockProver::set_report_instance_mismatches`], advice cells
+    /// copied to instance cells hold the instance values.
+    Instance,
+}
+
+impl Check {
+    /// Every check, in the order in which they are run.
+    const ALL: [Check; 7] = [
+        Check::Selectors,
+        Check::LookupCells,
+        Check::RotatedCells,
+        Check::Gates,
+        Check::Lookups,
+        Check::Permutation,
+        Check::Instance,
+    ];
+
+    /// Returns the phase that this check is reported as part of.
+    fn phase(self) -> VerifyPhase {
+        match self {
+            Check::Selectors | Check::LookupCells | Check::RotatedCells => VerifyPhase::Selectors,
+            Check::Gates => VerifyPhase::Gates,
+            Check::Lookups => VerifyPhase::Lookups,
+            Check::Permutation | Check::Instance => VerifyPhase::Permutations,
+        }
+    }
+
+    /// Returns `true` if this check can be run on a single row.
+    fn is_row_wise(self) -> bool {
+        matches!(self, Check::Gates | Check::Lookups | Check::Permutation)
+    }
+}
+
+/// Progress through verification, as reported by [`MockProver::verify_with_progress`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerifyProgress {
+    /// The phase currently being verified.
+    pub phase: VerifyPhase,
+    /// The fraction of the current phase that is complete, between 0 and 1.
+    pub fraction: f64,
+}
+
+/// A structured summary of verifying a circuit, as returned by
+/// [`MockProver::verify_report`].
+#[derive(Debug)]
+pub struct VerificationReport {
+    /// The reasons that the circuit is not satisfied, as [`MockProver::verify`] would
+    /// return them. This is empty if the circuit is satisfied.
+    pub failures: Vec<VerifyFailure>,
+    /// The number of rows on which gates were evaluated, including unusable rows.
+    pub rows_checked: usize,
+    /// The number of usable rows, on which lookup inputs were checked.
+    pub usable_rows: usize,
+    /// The number of failures found in each phase, in the order the phases ran.
+    pub phase_failures: [(VerifyPhase, usize); 4],
+    /// How long each phase took.
+    pub timings: VerifyTimings,
+    /// Whether the [stricter verification mode](MockProver::set_strict) was enabled.
+    pub strict: bool,
+}
+
+impl VerificationReport {
+    /// Returns `true` if the circuit is satisfied.
+    pub fn is_satisfied(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Converts this report into the result that [`MockProver::verify`] returns.
+    pub fn into_result(self) -> Result<(), Vec<VerifyFailure>> {
+        if self.failures.is_empty() {
+            Ok(())
+        } else {
+            Err(self.failures)
+        }
+    }
+}
+
+/// A flag that is set through a shared reference.
+///
+/// `Assignment::query_instance` only has `&self`, but `MockProver` must stay `Sync` so
+/// that it can be verified from several threads, which rules out `Cell`.
+#[derive(Debug, Default)]
+struct Flag(AtomicBool);
+
+impl Flag {
+    fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, value: bool) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
+
+impl Clone for Flag {
+    fn clone(&self) -> Self {
+        Flag(AtomicBool::new(self.get()))
+    }
+}
+
+/// The detailed cause of the last error returned by an `Assignment` method.
+///
+/// `Assignment` methods can only return an [`Error`], so the [`MockProverError`] behind
+/// it is kept here until synthesis returns. It is not needed after that, and can't be
+/// cloned, so clones start out empty.
+#[derive(Debug, Default)]
+struct PendingError(Option<MockProverError>);
+
+impl Clone for PendingError {
+    fn clone(&self) -> Self {
+        PendingError(None)
+    }
+}
+
+/// The failure of the first region that failed a `MockProver`'s region check.
+///
+/// Synthesis is aborted once a region has failed, and the failure is taken as soon as
+/// synthesis returns. It can't be cloned, so clones start out empty.
+#[derive(Debug, Default)]
+struct RegionFailure(Option<VerifyFailure>);
+
+impl Clone for RegionFailure {
+    fn clone(&self) -> Self {
+        RegionFailure(None)
+    }
+}
+
+/// The lookup tables built by [`MockProver::verify_into`], by lookup index.
+///
+/// They are kept between calls so that rebuilding them reuses their allocations. The
+/// tables are always rebuilt from the witness before they are read, so they never need
+/// to be invalidated, and clones start out empty.
+#[derive(Debug)]
+struct LookupScratch<F: Group + Field>(Mutex<Vec<Vec<Vec<Value<F>>>>>);
+
+impl<F: Group + Field> Default for LookupScratch<F> {
+    fn default() -> Self {
+        LookupScratch(Mutex::new(vec![]))
+    }
+}
+
+impl<F: Group + Field> Clone for LookupScratch<F> {
+    fn clone(&self) -> Self {
+        LookupScratch::default()
+    }
+}
+
+/// The options that the `MockProver::run_*` constructors synthesize a circuit with.
+struct RunOptions<F: Group + Field> {
+    // The values to bind the challenges to, instead of deriving them.
+    challenges: Option<Vec<F>>,
+    // The seed to derive the challenges from, instead of the default seed.
+    seed: Option<Vec<u8>>,
+    // Whether `copy` fails immediately if both cells are assigned different values.
+    check_copies: bool,
+    // Checks each region as it is exited, returning its failure, if any.
+    region_check: Option<fn(&MockProver<F>, usize) -> Option<VerifyFailure>>,
+    // Whether to record the assignment events made during synthesis.
+    record_events: bool,
+}
+
+impl<F: Group + Field> Default for RunOptions<F> {
+    fn default() -> Self {
+        RunOptions {
+            challenges: None,
+            seed: None,
+            check_copies: false,
+            region_check: None,
+            record_events: false,
+        }
+    }
+}
+
 /// A test prover for debugging circuits.
 ///
 /// The normal proving process, when applied to a buggy circuit implementation, might
@@ -171,6 +849,12 @@ impl<F: Group + Field> Mul<F> for Value<F> {
 /// are invalid: it stores all the private inputs along with the circuit internals, and
 /// then checks every constraint manually.
 ///
+/// Creating and verifying a `MockProver` requires `F: FieldExt`: the circuit's
+/// challenges are derived with [`FieldExt::from_bytes_wide`], and lookups are checked
+/// by sorting field elements. Methods that only inspect the layout, wiring or
+/// configuration of an existing `MockProver` (such as [`MockProver::row_gaps`] or
+/// [`MockProver::equality_classes`]) only require `F: Group + Field`.
+///
 /// # Examples
 ///
 /// ```
@@ -261,9 +945,9 @@ impl<F: Group + Field> Mul<F> for Value<F> {
 ///             offset: 0,
 ///         },
 ///         cell_values: vec![
-///             (((Any::advice(), 0).into(), 0).into(), "0x2".to_string()),
-///             (((Any::advice(), 1).into(), 0).into(), "0x4".to_string()),
-///             (((Any::advice(), 2).into(), 0).into(), "0x8".to_string()),
+///             (((Any::advice(), 0).into(), 0).into(), "2".to_string()),
+///             (((Any::advice(), 1).into(), 0).into(), "4".to_string()),
+///             (((Any::advice(), 2).into(), 0).into(), "8".to_string()),
 ///         ],
 ///     }])
 /// );
@@ -276,7 +960,7 @@ impl<F: Group + Field> Mul<F> for Value<F> {
 ///     } if current_k == 2,
 /// ));
 /// ```
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MockProver<F: Group + Field> {
     k: u32,
     n: u32,
@@ -294,17 +978,92 @@ pub struct MockProver<F: Group + Field> {
     advice: Vec<Vec<CellValue<F>>>,
     // The instance cells in the circuit, arranged as [column][row].
     instance: Vec<Vec<F>>,
+    // The number of values provided for each instance column, before padding.
+    instance_lens: Vec<usize>,
 
     selectors: Vec<Vec<bool>>,
 
     challenges: Vec<F>,
+    // The seed that the challenges were derived from, if they weren't given explicitly.
+    challenge_seed: Option<Vec<u8>>,
 
     permutation: permutation::keygen::Assembly,
 
     // A range of available rows for assignment and copies.
     usable_rows: Range<usize>,
+
+    // The value that unassigned cells take when evaluating expressions.
+    unassigned_default: F,
+
+    // Selectors that were enabled while no region was being assigned to.
+    floating_selectors: Vec<(Selector, usize)>,
+
+    // Whether to run the stricter checks that may flag deliberately unassigned cells.
+    strict: bool,
+    // Whether `copy` fails immediately if both cells are assigned different values.
+    check_copies: bool,
+    // Whether to report advice cells that don't hold the instance value they are
+    // copied to.
+    report_instance_mismatches: bool,
+    // Checks each region as it is exited, returning its failure, if any.
+    region_check: Option<fn(&MockProver<F>, usize) -> Option<VerifyFailure>>,
+    // The only phase whose advice columns are assigned, as in one pass of the real
+    // prover, or `None` if every phase is assigned at once.
+    phase: Option<u8>,
+    // The failure of the first region that failed `region_check`, after which
+    // synthesis is aborted.
+    failed_region: RegionFailure,
+    // The selectors queried by each lookup's inputs, recorded before compression.
+    lookup_selectors: Vec<Vec<Selector>>,
+    // The cells marked as outputs of the circuit, in the order they were marked.
+    outputs: Vec<(Column<Any>, usize)>,
+    // The assignment events made during synthesis, if they are being recorded.
+    events: Option<Vec<AssignmentEvent<F>>>,
+    // Cells that were assigned a different value by a later region than by an earlier
+    // one, as the column, row, and indices of the earlier and later regions.
+    conflicting_assignments: Vec<(Column<Any>, usize, usize, usize)>,
+    // Cells that may be assigned different values by different regions.
+    shared_cells: HashSet<(Column<Any>, usize)>,
+    // Whether synthesis read any instance values, which the witness may depend on.
+    instance_queried: Flag,
+    // The detailed cause of the last error returned during synthesis.
+    pending_error: PendingError,
+    // The selectors whose usage contradicts their kind, recorded before compression.
+    selector_kind_conflicts: Vec<SelectorKindConflict>,
+
+    // Maps lookup tuples to a canonical form before they are compared, by lookup index.
+    lookup_normalizers: HashMap<usize, LookupNormalizer<F>>,
+    // The lookup tables built by `verify_into`, kept to reuse their allocations.
+    lookup_scratch: LookupScratch<F>,
+    // The order in which the short-circuiting verification paths check rows.
+    scan_order: ScanOrder,
+
+    // Renders the field elements shown in verification failures.
+    field_formatter: FieldFormatter<F>,
+}
+
+/// A checkpoint of the witness state held by a [`MockProver`].
+///
+/// Created with [`MockProver::snapshot`], and rolled back to with
+/// [`MockProver::restore`]. The contents are intentionally opaque.
+#[derive(Clone, Debug)]
+pub struct WitnessSnapshot<F: Group + Field> {
+    fixed: Vec<Vec<CellValue<F>>>,
+    advice: Vec<Vec<CellValue<F>>>,
+    instance: Vec<Vec<F>>,
+    permutation: permutation::keygen::Assembly,
 }
 
+// `MockProver::verify_at_rows_par` shares the prover between threads.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[allow(dead_code)]
+    fn assert_mock_prover<F: Group + Field>() {
+        assert_send_sync::<MockProver<F>>();
+    }
+};
+
 impl<F: Field + Group> Assignment<F> for MockProver<F> {
     fn enter_region<NR, N>(&mut self, name: N)
     where
@@ -312,8 +1071,10 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         N: FnOnce() -> NR,
     {
         assert!(self.current_region.is_none());
+        let name: String = name().into();
+        self.record(|| AssignmentEvent::EnterRegion { name: name.clone() });
         self.current_region = Some(Region {
-            name: name().into(),
+            name,
             columns: HashSet::default(),
             rows: None,
             enabled_selectors: HashMap::default(),
@@ -323,6 +1084,14 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
 
     fn exit_region(&mut self) {
         self.regions.push(self.current_region.take().unwrap());
+        self.record(|| AssignmentEvent::ExitRegion);
+
+        if let Some(check) = self.region_check {
+            let region_index = self.regions.len() - 1;
+            if self.failed_region.0.is_none() {
+                self.failed_region.0 = check(self, region_index);
+            }
+        }
     }
 
     fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
@@ -335,16 +1104,22 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         }
 
         // Track that this selector was enabled. We require that all selectors are enabled
-        // inside some region (i.e. no floating selectors).
-        self.current_region
-            .as_mut()
-            .unwrap()
-            .enabled_selectors
-            .entry(*selector)
-            .or_default()
-            .push(row);
+        // inside some region (i.e. no floating selectors), but record any that aren't so
+        // they can be reported by `MockProver::floating_selectors`.
+        match self.current_region.as_mut() {
+            Some(region) => region
+                .enabled_selectors
+                .entry(*selector)
+                .or_default()
+                .push(row),
+            None => self.floating_selectors.push((*selector, row)),
+        }
 
         self.selectors[selector.0][row] = true;
+        self.record(|| AssignmentEvent::EnableSelector {
+            selector: *selector,
+            row,
+        });
 
         Ok(())
     }
@@ -357,6 +1132,7 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         if !self.usable_rows.contains(&row) {
             return Err(Error::not_enough_rows_available(self.k));
         }
+        self.instance_queried.set(true);
 
         self.instance
             .get(column.index())
@@ -379,7 +1155,18 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         AR: Into<String>,
     {
         if !self.usable_rows.contains(&row) {
-            return Err(Error::not_enough_rows_available(self.k));
+            return Err(self.unusable_row_error(column.into(), row));
+        }
+        // Abort synthesis as soon as a region has failed its check.
+        if self.failed_region.0.is_some() {
+            return Err(Error::Synthesis);
+        }
+        // Like the real prover, only assign the columns of the phase being synthesized.
+        if self
+            .phase
+            .map_or(false, |phase| column.column_type().phase() != phase)
+        {
+            return Ok(());
         }
 
         if let Some(region) = self.current_region.as_mut() {
@@ -391,12 +1178,33 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
                 .or_default();
         }
 
-        *self
+        let value = match (to().into_field().evaluate().assign(), self.phase) {
+            (Ok(value), _) => value,
+            (Err(_), Some(phase)) => {
+                return Err(self.fail(MockProverError::ValueUnavailableInPhase {
+                    column: column.into(),
+                    row,
+                    phase,
+                }))
+            }
+            (Err(error), None) => return Err(error),
+        };
+        let cell = match self
             .advice
             .get_mut(column.index())
             .and_then(|v| v.get_mut(row))
-            .ok_or(Error::BoundsFailure)? =
-            CellValue::Assigned(to().into_field().evaluate().assign()?);
+        {
+            Some(cell) => cell,
+            None => {
+                return Err(self.fail(MockProverError::CellOutOfBounds {
+                    column: column.into(),
+                    row,
+                }))
+            }
+        };
+        let previous = std::mem::replace(cell, CellValue::Assigned(value));
+        self.check_reassignment(column.into(), row, previous, value);
+        self.record(|| AssignmentEvent::AssignAdvice { column, row, value });
 
         Ok(())
     }
@@ -415,7 +1223,11 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         AR: Into<String>,
     {
         if !self.usable_rows.contains(&row) {
-            return Err(Error::not_enough_rows_available(self.k));
+            return Err(self.unusable_row_error(column.into(), row));
+        }
+        // Abort synthesis as soon as a region has failed its check.
+        if self.failed_region.0.is_some() {
+            return Err(Error::Synthesis);
         }
 
         if let Some(region) = self.current_region.as_mut() {
@@ -427,12 +1239,23 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
                 .or_default();
         }
 
-        *self
+        let value = to().into_field().evaluate().assign()?;
+        let cell = match self
             .fixed
             .get_mut(column.index())
             .and_then(|v| v.get_mut(row))
-            .ok_or(Error::BoundsFailure)? =
-            CellValue::Assigned(to().into_field().evaluate().assign()?);
+        {
+            Some(cell) => cell,
+            None => {
+                return Err(self.fail(MockProverError::CellOutOfBounds {
+                    column: column.into(),
+                    row,
+                }))
+            }
+        };
+        let previous = std::mem::replace(cell, CellValue::Assigned(value));
+        self.check_reassignment(column.into(), row, previous, value);
+        self.record(|| AssignmentEvent::AssignFixed { column, row, value });
 
         Ok(())
     }
@@ -449,7 +1272,42 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         }
 
         self.permutation
-            .copy(left_column, left_row, right_column, right_row)
+            .copy(left_column, left_row, right_column, right_row)?;
+
+        if self.check_copies {
+            if let (Some(left), Some(right)) = (
+                self.assigned_value(left_column, left_row),
+                self.assigned_value(right_column, right_row),
+            ) {
+                if left != right {
+                    return Err(self.fail(MockProverError::CopyMismatch {
+                        left_column,
+                        left_row,
+                        right_column,
+                        right_row,
+                    }));
+                }
+            }
+        }
+        self.record(|| AssignmentEvent::Copy {
+            left_column,
+            left_row,
+            right_column,
+            right_row,
+        });
+
+        Ok(())
+    }
+
+    fn mark_output(&mut self, column: Column<Any>, row: usize) -> Result<(), Error> {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        self.outputs.push((column, row));
+        self.record(|| AssignmentEvent::MarkOutput { column, row });
+
+        Ok(())
     }
 
     fn fill_from_row(
@@ -470,7 +1328,11 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
     }
 
     fn get_challenge(&self, challenge: Challenge) -> circuit::Value<F> {
-        circuit::Value::known(self.challenges[challenge.index()])
+        match self.phase {
+            // A challenge is only drawn once the phase it is usable after is finished.
+            Some(phase) if challenge.phase() >= phase => circuit::Value::unknown(),
+            _ => circuit::Value::known(self.challenges[challenge.index()]),
+        }
     }
 
     fn push_namespace<NR, N>(&mut self, _: N)
@@ -486,882 +1348,10236 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
     }
 }
 
-impl<F: FieldExt> MockProver<F> {
-    /// Runs a synthetic keygen-and-prove operation on the given circuit, collecting data
-    /// about the constraints and their assignments.
-    pub fn run<ConcreteCircuit: Circuit<F>>(
-        k: u32,
-        circuit: &ConcreteCircuit,
-        instance: Vec<Vec<F>>,
-    ) -> Result<Self, Error> {
-        let n = 1 << k;
+// Methods that don't evaluate the circuit, and so only need basic field operations.
+impl<F: Group + Field> MockProver<F> {
+    /// Returns the error for an assignment to the cell at `row` of `column`, where `row`
+    /// is not a usable row.
+    ///
+    /// If the cell is read by a lookup's table expressions, and is being assigned in a
+    /// region, the region is loading a table that doesn't fit. A
+    /// [`MockProverError::TableTooLarge`] naming the region and saying how large `k`
+    /// must be is then recorded as the cause of the error.
+    fn unusable_row_error(&mut self, column: Column<Any>, row: usize) -> Error {
+        let region = match &self.current_region {
+            Some(region) if self.is_table_column(column) => region,
+            _ => return Error::not_enough_rows_available(self.k),
+        };
 
-        let mut cs = ConstraintSystem::default();
-        let config = ConcreteCircuit::configure(&mut cs);
-        let cs = cs;
+        let start = region.rows.map_or(row, |(start, _)| start.min(row));
+        let minimum_k = (row + self.cs.blinding_factors() + 2)
+            .next_power_of_two()
+            .trailing_zeros();
+        let error = MockProverError::TableTooLarge {
+            region: region.name.clone(),
+            entries: row - start + 1,
+            usable_rows: self.usable_rows.len(),
+            current_k: self.k,
+            minimum_k,
+        };
+        self.fail(error)
+    }
 
-        if n < cs.minimum_rows() {
-            return Err(Error::not_enough_rows_available(k));
-        }
+    /// Returns `true` if `column` is read by the table expressions of any lookup.
+    fn is_table_column(&self, column: Column<Any>) -> bool {
+        self.cs
+            .lookups
+            .iter()
+            .flat_map(|lookup| lookup.table_expressions.iter())
+            .any(|expression| {
+                expression.evaluate(
+                    &|_| false,
+                    &|_| false,
+                    &|query| column == Column::new(query.column_index, Any::Fixed),
+                    &|query| column == self.advice_column(query.column_index),
+                    &|_| false,
+                    &|_| false,
+                    &|a| a,
+                    &|a, b| a || b,
+                    &|a, b| a || b,
+                    &|a, _| a,
+                )
+            })
+    }
 
-        if instance.len() != cs.num_instance_columns {
-            return Err(Error::InvalidInstances);
+    /// Notes a conflicting assignment if the cell at `row` of `column`, which held
+    /// `previous`, was assigned by an earlier region and is now assigned a different
+    /// `value` by the current region.
+    fn check_reassignment(
+        &mut self,
+        column: Column<Any>,
+        row: usize,
+        previous: CellValue<F>,
+        value: F,
+    ) {
+        let differs = matches!(previous, CellValue::Assigned(previous) if previous != value);
+        if !differs || self.current_region.is_none() {
+            return;
+        }
+        if let Some(earlier) = self
+            .regions
+            .iter()
+            .rposition(|region| region.cells.contains_key(&(column, row)))
+        {
+            self.conflicting_assignments
+                .push((column, row, earlier, self.regions.len()));
         }
+    }
 
-        let instance = instance
-            .into_iter()
-            .map(|mut instance| {
-                if instance.len() > n - (cs.blinding_factors() + 1) {
-                    return Err(Error::InstanceTooLarge);
+    /// Records `error` as the cause of the error that an `Assignment` method is about to
+    /// return, and returns the closest [`Error`] to return in its place.
+    fn fail(&mut self, error: MockProverError) -> Error {
+        let closest = error
+            .closest_error()
+            .expect("only errors with more context than `Error` are recorded");
+        self.pending_error.0 = Some(error);
+        closest
+    }
+
+    /// Returns the detailed cause of `error`, which was returned by an `Assignment`
+    /// method or by synthesis.
+    fn detailed_error(&mut self, error: Error) -> MockProverError {
+        match self.pending_error.0.take() {
+            // The circuit may have recovered from the recorded error and then failed
+            // for another reason, so only use it if it matches.
+            Some(cause)
+                if cause.closest_error().map_or(false, |closest| {
+                    std::mem::discriminant(&closest) == std::mem::discriminant(&error)
+                }) =>
+            {
+                cause
+            }
+            _ => error.into(),
+        }
+    }
+
+    /// Records an assignment event, if events are being recorded.
+    fn record(&mut self, event: impl FnOnce() -> AssignmentEvent<F>) {
+        if let Some(events) = self.events.as_mut() {
+            events.push(event());
+        }
+    }
+
+    /// Returns the advice column with the given index, in its configured phase.
+    fn advice_column(&self, index: usize) -> Column<Any> {
+        Column::new(
+            index,
+            Any::Advice(Advice {
+                phase: self.cs.advice_column_phase[index],
+            }),
+        )
+    }
+
+    /// Returns the number of values that were provided for the instance column with the
+    /// given index, before it was padded with zeroes.
+    ///
+    /// This tells how much of an instance column is user-provided rather than
+    /// implicitly zero, which matters for lookup tables backed by instance columns.
+    ///
+    /// Panics if `column` is not the index of an instance column.
+    pub fn instance_len(&self, column: usize) -> usize {
+        self.instance_lens[column]
+    }
+
+    /// Returns the values of the circuit's challenges, indexed by
+    /// [`Challenge::index`].
+    pub fn challenges(&self) -> &[F] {
+        &self.challenges
+    }
+
+    /// Returns the seed that the circuit's challenges were derived from, or `None` if
+    /// they were given explicitly with [`MockProver::run_with_challenges`].
+    pub fn challenge_seed(&self) -> Option<&[u8]> {
+        self.challenge_seed.as_deref()
+    }
+
+    /// Sets the value that unassigned advice and fixed cells take when evaluating gates
+    /// and lookups during verification. By default this is zero.
+    ///
+    /// Defaulting to zero can mask forgotten assignments, because zero often happens to
+    /// satisfy a constraint. Setting this to a sentinel such as a random field element
+    /// makes any gate or lookup that reads an unassigned cell fail loudly instead.
+    pub fn set_unassigned_default(&mut self, value: F) {
+        self.unassigned_default = value;
+    }
+
+    /// Enables or disables the stricter verification mode. It is disabled by default.
+    ///
+    /// In this mode, verification additionally reports checks that may have false
+    /// positives for circuits that deliberately leave cells unassigned:
+    /// - Lookup inputs must not read unassigned advice cells on rows where any selector
+    ///   queried by those inputs is enabled.
+    /// - Gates enabled by complex selectors must not read, at a non-zero rotation, cells
+    ///   that no region assigned.
+    /// - Constraints of gates enabled by selectors must not be satisfied only because of
+    ///   a cell read at a rotation that wraps around the end of the circuit.
+    /// - A cell assigned by one region must not be assigned a different value by a later
+    ///   region, unless it was allowed with [`MockProver::allow_shared_cell`].
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Enables or disables reporting a `VerifyFailure::InstanceMismatch` for each advice
+    /// cell that is copied to an instance cell but holds a different value. It is
+    /// disabled by default.
+    ///
+    /// Such mismatches are always reported as `VerifyFailure::Permutation` failures.
+    /// When enabled, the targeted failures are reported as well, naming both values.
+    pub fn set_report_instance_mismatches(&mut self, enabled: bool) {
+        self.report_instance_mismatches = enabled;
+    }
+
+    /// Sets the order in which [`MockProver::first_failure`] and
+    /// [`MockProver::iter_failures`] check the usable rows of the circuit. By default
+    /// this is [`ScanOrder::Forward`].
+    ///
+    /// A bug is often in the region that was written last, in which case scanning in
+    /// reverse finds it sooner. The order doesn't change which failures exist, only
+    /// which are found first. [`MockProver::verify`] and the other methods that collect
+    /// every failure always check the rows from first to last.
+    pub fn set_scan_order(&mut self, order: ScanOrder) {
+        self.scan_order = order;
+    }
+
+    /// Returns the usable rows of the circuit, in the order set with
+    /// [`MockProver::set_scan_order`].
+    fn scan_rows(&self) -> Vec<usize> {
+        match self.scan_order {
+            ScanOrder::Forward => self.usable_rows.clone().collect(),
+            ScanOrder::Reverse => self.usable_rows.clone().rev().collect(),
+            ScanOrder::RegionCompletion => {
+                let mut seen = vec![false; self.n as usize];
+                let mut rows = vec![];
+                let region_rows = self
+                    .regions
+                    .iter()
+                    .filter_map(|region| region.rows)
+                    .flat_map(|(start, end)| start..=end);
+                for row in region_rows.chain(self.usable_rows.clone()) {
+                    if self.usable_rows.contains(&row) && !seen[row] {
+                        seen[row] = true;
+                        rows.push(row);
+                    }
                 }
+                rows
+            }
+        }
+    }
 
-                instance.resize(n, F::zero());
-                Ok(instance)
+    /// Allows the cell at `row` of `column` to be assigned different values by
+    /// different regions, without the stricter verification mode reporting it.
+    ///
+    /// Some layouts deliberately reuse a cell across regions, such as a boundary cell
+    /// shared by two adjacent regions, where the later region's value is the one that
+    /// counts. Mark each such cell, so that only unintended conflicting assignments are
+    /// reported. See [`MockProver::set_strict`].
+    pub fn allow_shared_cell(&mut self, column: impl Into<Column<Any>>, row: usize) {
+        self.shared_cells.insert((column.into(), row));
+    }
+
+    /// Sets the function used to render the field elements shown in verification
+    /// failures. By default this is [`format_field`].
+    ///
+    /// This only affects failures found after it is called.
+    pub fn set_field_formatter(&mut self, formatter: FieldFormatter<F>) {
+        self.field_formatter = formatter;
+    }
+
+    /// Sets the function that the input tuples and table rows of the lookup with the
+    /// given index are mapped through before they are compared. By default, they are
+    /// compared as they are.
+    ///
+    /// This is useful for testing tables that encode values in a canonical form. Tuples
+    /// that read a poisoned cell are never normalized.
+    ///
+    /// Panics if `lookup_index` is not the index of a lookup.
+    pub fn set_lookup_normalizer(&mut self, lookup_index: usize, normalizer: LookupNormalizer<F>) {
+        assert!(
+            lookup_index < self.cs.lookups.len(),
+            "lookup {} does not exist",
+            lookup_index
+        );
+        self.lookup_normalizers.insert(lookup_index, normalizer);
+    }
+
+    /// Maps the given tuple of the lookup with the given index through its normalizer,
+    /// if it has one.
+    fn normalize_lookup_tuple(&self, lookup_index: usize, tuple: Vec<Value<F>>) -> Vec<Value<F>> {
+        let normalizer = match self.lookup_normalizers.get(&lookup_index) {
+            Some(normalizer) => normalizer,
+            None => return tuple,
+        };
+        let values: Option<Vec<F>> = tuple
+            .iter()
+            .map(|value| match value {
+                Value::Real(value) => Some(*value),
+                Value::Poison => None,
             })
-            .collect::<Result<Vec<_>, _>>()?;
+            .collect();
+        match values {
+            Some(values) => normalizer(&values).into_iter().map(Value::Real).collect(),
+            None => tuple,
+        }
+    }
 
-        // Fixed columns contain no blinding factors.
-        let fixed = vec![vec![CellValue::Unassigned; n]; cs.num_fixed_columns];
-        let selectors = vec![vec![false; n]; cs.num_selectors];
-        // Advice columns contain blinding factors.
-        let blinding_factors = cs.blinding_factors();
-        let usable_rows = n - (blinding_factors + 1);
-        let advice = vec![
-            {
-                let mut column = vec![CellValue::Unassigned; n];
-                // Poison unusable rows.
-                for (i, cell) in column.iter_mut().enumerate().skip(usable_rows) {
-                    *cell = CellValue::Poison(i);
+    /// Captures the current fixed, advice and instance assignments, along with the
+    /// copy constraints, so that they can later be rolled back with
+    /// [`MockProver::restore`].
+    ///
+    /// This is intended for try-and-revert workflows, where the witness is mutated
+    /// (for example by calling [`Assignment`] methods directly) without having to
+    /// re-synthesize the circuit afterwards.
+    pub fn snapshot(&self) -> WitnessSnapshot<F> {
+        WitnessSnapshot {
+            fixed: self.fixed.clone(),
+            advice: self.advice.clone(),
+            instance: self.instance.clone(),
+            permutation: self.permutation.clone(),
+        }
+    }
+
+    /// Rolls the witness state back to a checkpoint previously taken with
+    /// [`MockProver::snapshot`].
+    ///
+    /// The snapshot must have been taken from this `MockProver` (or one synthesized
+    /// from the same circuit with the same `k`).
+    pub fn restore(&mut self, snapshot: WitnessSnapshot<F>) {
+        self.fixed = snapshot.fixed;
+        self.advice = snapshot.advice;
+        self.instance = snapshot.instance;
+        self.permutation = snapshot.permutation;
+    }
+
+    /// Returns the phase in which the given advice column is assigned, as configured in
+    /// the circuit's constraint system.
+    ///
+    /// Gates and lookups are only evaluated once every phase has been assigned, so they
+    /// may query columns and challenges from any phase. The witness, however, is
+    /// assigned one phase at a time by the real prover; see [`MockProver::run`].
+    ///
+    /// Panics if `column` is not part of this circuit.
+    pub fn advice_phase(&self, column: Column<Advice>) -> u8 {
+        Advice {
+            phase: self.cs.advice_column_phase[column.index()],
+        }
+        .phase()
+    }
+
+    /// Returns a summary of the structure of the circuit: its gates, lookups,
+    /// permutation columns and column counts.
+    ///
+    /// This depends only on the constraint system, and not on the witness.
+    pub fn describe(&self) -> CircuitDescription {
+        CircuitDescription::new(&self.cs)
+    }
+
+    /// Returns a map from each selector to the indices of the gates that query it.
+    ///
+    /// Enabling a selector on a row activates every gate it maps to, which explains why
+    /// a single `enable` can cause failures in several gates. Selectors that aren't
+    /// queried by any gate are omitted.
+    pub fn selector_gate_map(&self) -> HashMap<Selector, Vec<usize>> {
+        let mut map: HashMap<Selector, Vec<usize>> = HashMap::new();
+        for (gate_index, gate) in self.cs.gates.iter().enumerate() {
+            for selector in gate.queried_selectors() {
+                let gates = map.entry(*selector).or_default();
+                // A gate may query the same selector more than once.
+                if gates.last() != Some(&gate_index) {
+                    gates.push(gate_index);
                 }
-                column
-            };
-            cs.num_advice_columns
-        ];
-        let permutation = permutation::keygen::Assembly::new(n, &cs.permutation);
-        let constants = cs.constants.clone();
+            }
+        }
+        map
+    }
 
-        // Use hash chain to derive deterministic challenges for testing
-        let challenges = {
-            let mut hash: [u8; 64] = blake2b(b"Halo2-MockProver").as_bytes().try_into().unwrap();
-            iter::repeat_with(|| {
-                hash = blake2b(&hash).as_bytes().try_into().unwrap();
-                F::from_bytes_wide(&hash)
+    /// Returns the rows on which each selector was enabled, across every region, in
+    /// increasing order.
+    ///
+    /// This is the global view of selector usage. Selectors that were never enabled are
+    /// omitted.
+    pub fn all_enabled_selectors(&self) -> HashMap<Selector, Vec<usize>> {
+        let mut map: HashMap<Selector, Vec<usize>> = HashMap::new();
+        for region in self.regions.iter() {
+            for (selector, rows) in region.enabled_selectors.iter() {
+                map.entry(*selector).or_default().extend(rows);
+            }
+        }
+        for rows in map.values_mut() {
+            rows.sort_unstable();
+            rows.dedup();
+        }
+        map
+    }
+
+    /// Returns the selectors whose usage in the constraint system contradicts their
+    /// declared kind: simple selectors that don't multiply a whole gate constraint, and
+    /// complex selectors that could have been simple.
+    ///
+    /// Mixing up [`ConstraintSystem::selector`] and
+    /// [`ConstraintSystem::complex_selector`] leads to subtle bugs when selectors are
+    /// compressed, or to missed optimizations. This depends only on the constraint
+    /// system, and not on the witness.
+    pub fn selector_kind_conflicts(&self) -> Vec<SelectorKindConflict> {
+        self.selector_kind_conflicts.clone()
+    }
+
+    /// Returns the indices of the gates that are controlled by selectors, none of which
+    /// was enabled on any row, in increasing order.
+    ///
+    /// These gates still contribute to the circuit's degree and proving cost, but
+    /// constrain nothing in this witness. That may be intended, for conditional logic
+    /// that this witness doesn't exercise, or a sign of a forgotten `enable`. Gates that
+    /// don't query any selector apply to every row, and so are never dormant.
+    pub fn dormant_gates(&self) -> Vec<usize> {
+        self.cs
+            .gates
+            .iter()
+            .enumerate()
+            .filter(|(_, gate)| {
+                let selectors = gate.queried_selectors();
+                !selectors.is_empty()
+                    && selectors
+                        .iter()
+                        .all(|selector| !self.selectors[selector.0].contains(&true))
             })
-            .take(cs.num_challenges)
+            .map(|(gate_index, _)| gate_index)
             .collect()
-        };
+    }
 
-        let mut prover = MockProver {
-            k,
-            n: n as u32,
-            cs,
-            regions: vec![],
-            current_region: None,
-            fixed,
-            advice,
-            instance,
-            selectors,
-            challenges,
-            permutation,
-            usable_rows: 0..usable_rows,
-        };
+    /// Returns the offsets, relative to the start of the region with the given index,
+    /// at which `selector` was enabled within that region, in increasing order.
+    ///
+    /// Repetitive sub-circuits usually enable their selectors in a regular pattern (for
+    /// example, on every even offset), which tests can assert on directly. The start of
+    /// a region is the earliest row on which it assigned a cell or enabled a selector.
+    ///
+    /// Panics if `region_index` is not the index of a region.
+    pub fn selector_pattern(&self, region_index: usize, selector: Selector) -> Vec<usize> {
+        let region = &self.regions[region_index];
+        let start = region
+            .enabled_selectors
+            .values()
+            .flatten()
+            .copied()
+            .chain(region.rows.map(|(start, _)| start))
+            .min();
+
+        let mut offsets: Vec<_> = region
+            .enabled_selectors
+            .get(&selector)
+            .into_iter()
+            .flatten()
+            .map(|row| row - start.unwrap())
+            .collect();
+        offsets.sort_unstable();
+        offsets
+    }
 
-        ConcreteCircuit::FloorPlanner::synthesize(&mut prover, circuit, config, constants)?;
+    /// Returns the rows on which more than one of the given `selectors` is enabled, in
+    /// increasing order.
+    ///
+    /// Mutually exclusive sub-circuits often rely on never being enabled on the same
+    /// row, so an empty result shows that their layouts don't overlap.
+    pub fn selector_overlaps(&self, selectors: &[Selector]) -> Vec<usize> {
+        (0..self.n as usize)
+            .filter(|row| {
+                selectors
+                    .iter()
+                    .filter(|selector| self.selectors[selector.0][*row])
+                    .count()
+                    > 1
+            })
+            .collect()
+    }
 
-        let (cs, selector_polys) = prover.cs.compress_selectors(prover.selectors.clone());
-        prover.cs = cs;
-        prover.fixed.extend(selector_polys.into_iter().map(|poly| {
-            let mut v = vec![CellValue::Unassigned; n];
-            for (v, p) in v.iter_mut().zip(&poly[..]) {
-                *v = CellValue::Assigned(*p);
+    /// Returns, for each row of the circuit, the number of gate constraints that are
+    /// enabled on it by a selector.
+    ///
+    /// Rows with many active constraints show where the circuit's degree and column
+    /// pressure is concentrated. Gates that don't query any selector apply uniformly to
+    /// every row, and so are not counted.
+    pub fn row_costs(&self) -> Vec<usize> {
+        let mut costs = vec![0; self.n as usize];
+        for gate in self.cs.gates.iter() {
+            let selectors = gate.queried_selectors();
+            if selectors.is_empty() {
+                continue;
             }
-            v
-        }));
+            let constraints = gate.polynomials().len();
+            for (row, cost) in costs.iter_mut().enumerate() {
+                if selectors
+                    .iter()
+                    .any(|selector| self.selectors[selector.0][row])
+                {
+                    *cost += constraints;
+                }
+            }
+        }
+        costs
+    }
 
-        Ok(prover)
+    /// Returns the number of `(gate, constraint, row)` triples for which the gate is
+    /// enabled by a selector, summed over every row of the circuit.
+    ///
+    /// This is a rough proxy for the work the circuit's gates represent, which is useful
+    /// as a CI guardrail against accidental constraint explosions. It is an estimate,
+    /// not the exact cost of proving: the prover evaluates every constraint on every
+    /// row regardless of selectors, and its cost is dominated by the circuit's size and
+    /// [quotient degree](MockProver::quotient_degree). As with
+    /// [`MockProver::row_costs`], gates that don't query any selector are not counted.
+    pub fn num_active_constraints(&self) -> usize {
+        self.row_costs().into_iter().sum()
     }
 
-    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
-    /// the reasons that the circuit is not satisfied.
-    pub fn verify(&self) -> Result<(), Vec<VerifyFailure>> {
-        self.verify_at_rows(self.usable_rows.clone(), self.usable_rows.clone())
+    /// Returns the number of regions with the given name.
+    ///
+    /// Repetitive circuits create one region per iteration, so asserting on the count
+    /// catches loops that ran the wrong number of times.
+    pub fn count_regions_named(&self, name: &str) -> usize {
+        self.regions
+            .iter()
+            .filter(|region| region.name == name)
+            .count()
     }
 
-    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
-    /// the reasons that the circuit is not satisfied.
-    /// Constraints are only checked at `gate_row_ids`,
-    /// and lookup inputs are only checked at `lookup_input_row_ids`
-    pub fn verify_at_rows<I: Clone + Iterator<Item = usize>>(
+    /// Returns the columns in which the region with the given index assigned at least
+    /// one cell.
+    ///
+    /// Columns that the region only enabled selectors in are not included. This is
+    /// useful for checking which chips touch which columns.
+    ///
+    /// Panics if `region_index` is not the index of a region.
+    pub fn region_columns(&self, region_index: usize) -> HashSet<Column<Any>> {
+        self.regions[region_index]
+            .cells
+            .keys()
+            .map(|(column, _)| *column)
+            .collect()
+    }
+
+    /// Asserts that the region with the given index assigned no cell in `column`.
+    ///
+    /// This enforces architectural boundaries between chips in large circuits, such as
+    /// "the range-check chip must not touch the hash columns", so that accidental
+    /// coupling makes tests fail loudly.
+    ///
+    /// Panics, listing the offending rows, if the region assigned a cell in `column`,
+    /// or if `region_index` is not the index of a region.
+    pub fn assert_column_unused_in_region(
         &self,
-        gate_row_ids: I,
-        lookup_input_row_ids: I,
-    ) -> Result<(), Vec<VerifyFailure>> {
+        region_index: usize,
+        column: impl Into<Column<Any>>,
+    ) {
+        let column = column.into();
+        let region = &self.regions[region_index];
+        let mut rows: Vec<_> = region
+            .cells
+            .keys()
+            .filter(|(c, _)| *c == column)
+            .map(|(_, row)| *row)
+            .collect();
+        rows.sort_unstable();
+        assert!(
+            rows.is_empty(),
+            "region {} ('{}') assigned column {:?} at rows {:?}",
+            region_index,
+            region.name,
+            column,
+            rows
+        );
+    }
+
+    /// Asserts that every use of an instance cell agrees with the instance values that
+    /// were provided.
+    ///
+    /// Specifically, every cell that is constrained by the permutation to equal an
+    /// instance cell must have been assigned the same value, and every instance cell
+    /// that a gate reads on a row where it is enabled, or that a lookup input reads on
+    /// a row where the lookup is active, must be one that was provided rather than
+    /// implicit padding. This catches a public input that is read in a different place
+    /// than intended, such as through a wrong rotation.
+    ///
+    /// Panics, listing every inconsistent use, if any are found.
+    pub fn assert_instance_consistent(&self) {
         let n = self.n as i32;
+        let mut problems = vec![];
 
-        // check all the row ids are valid
-        for row_id in gate_row_ids.clone() {
-            if !self.usable_rows.contains(&row_id) {
-                panic!("invalid gate row id {}", row_id)
+        for class in self.equality_classes() {
+            for (column, row) in class
+                .iter()
+                .filter(|(c, _)| c.column_type() == &Any::Instance)
+            {
+                let expected = self.instance[column.index()][*row];
+                for (other_column, other_row) in class.iter() {
+                    let actual = match other_column.column_type() {
+                        Any::Advice(_) => self.advice[other_column.index()][*other_row],
+                        Any::Fixed => self.fixed[other_column.index()][*other_row],
+                        Any::Instance => {
+                            CellValue::Assigned(self.instance[other_column.index()][*other_row])
+                        }
+                    };
+                    if let CellValue::Assigned(actual) = actual {
+                        if actual != expected {
+                            problems.push(format!(
+                                "instance column {} row {} is {}, but the copied cell {:?} at row {} is {}",
+                                column.index(),
+                                row,
+                                (self.field_formatter)(expected),
+                                other_column,
+                                other_row,
+                                (self.field_formatter)(actual),
+                            ));
+                        }
+                    }
+                }
             }
         }
-        for row_id in lookup_input_row_ids.clone() {
-            if !self.usable_rows.contains(&row_id) {
-                panic!("invalid lookup row id {}", row_id)
+
+        let mut check_read = |what: String, column: usize, row: usize, rotation: i32| {
+            let cell_row = ((row as i32 + n + rotation) % n) as usize;
+            if cell_row >= self.instance_lens[column] {
+                problems.push(format!(
+                    "{} reads instance column {} at row {} (row {}, rotation {}), but only {} value(s) were provided",
+                    what, column, cell_row, row, rotation, self.instance_lens[column]
+                ));
+            }
+        };
+
+        for (gate_index, gate) in self.cs.gates.iter().enumerate() {
+            let selectors = gate.queried_selectors();
+            let rows: Vec<usize> = self
+                .usable_rows
+                .clone()
+                .filter(|row| {
+                    selectors.is_empty()
+                        || selectors
+                            .iter()
+                            .any(|selector| self.selectors[selector.0][*row])
+                })
+                .collect();
+            for cell in gate
+                .queried_cells()
+                .iter()
+                .filter(|cell| cell.column.column_type() == &Any::Instance)
+            {
+                for row in rows.iter() {
+                    check_read(
+                        format!("gate {} ('{}')", gate_index, gate.name()),
+                        cell.column.index(),
+                        *row,
+                        cell.rotation.0,
+                    );
+                }
             }
         }
 
-        // Check that within each region, all cells used in instantiated gates have been
-        // assigned to.
-        let selector_errors = self.regions.iter().enumerate().flat_map(|(r_i, r)| {
-            r.enabled_selectors.iter().flat_map(move |(selector, at)| {
-                // Find the gates enabled by this selector
-                self.cs
-                    .gates
-                    .iter()
-                    // Assume that if a queried selector is enabled, the user wants to use the
-                    // corresponding gate in some way.
-                    //
-                    // TODO: This will trip up on the reverse case, where leaving a selector
-                    // un-enabled keeps a gate enabled. We could alternatively require that
-                    // every selector is explicitly enabled or disabled on every row? But that
-                    // seems messy and confusing.
-                    .enumerate()
-                    .filter(move |(_, g)| g.queried_selectors().contains(selector))
-                    .flat_map(move |(gate_index, gate)| {
-                        at.iter().flat_map(move |selector_row| {
-                            // Selectors are queried with no rotation.
-                            let gate_row = *selector_row as i32;
-
-                            gate.queried_cells().iter().filter_map(move |cell| {
-                                // Determine where this cell should have been assigned.
-                                let cell_row = ((gate_row + n + cell.rotation.0) % n) as usize;
-
-                                // Check that it was assigned!
-                                if r.cells.contains_key(&(cell.column, cell_row)) {
-                                    None
-                                } else {
-                                    Some(VerifyFailure::CellNotAssigned {
-                                        gate: (gate_index, gate.name()).into(),
-                                        region: (r_i, r.name.clone()).into(),
-                                        gate_offset: *selector_row,
-                                        column: cell.column,
-                                        offset: cell_row as isize - r.rows.unwrap().0 as isize,
-                                    })
-                                }
-                            })
-                        })
-                    })
-            })
+        for (lookup_index, lookup) in self.cs.lookups.iter().enumerate() {
+            let queries: Vec<(usize, i32)> = lookup
+                .input_expressions
+                .iter()
+                .flat_map(|expression| {
+                    expression.evaluate(
+                        &|_| vec![],
+                        &|_| vec![],
+                        &|_| vec![],
+                        &|_| vec![],
+                        &|query| vec![(query.column_index, query.rotation.0)],
+                        &|_| vec![],
+                        &|a| a,
+                        &|mut a, mut b| {
+                            a.append(&mut b);
+                            a
+                        },
+                        &|mut a, mut b| {
+                            a.append(&mut b);
+                            a
+                        },
+                        &|a, _| a,
+                    )
+                })
+                .collect();
+            for row in self.lookup_active_rows(lookup_index) {
+                for (column, rotation) in queries.iter() {
+                    check_read(
+                        format!("lookup {} ('{}')", lookup_index, lookup.name),
+                        *column,
+                        row,
+                        *rotation,
+                    );
+                }
+            }
+        }
+
+        assert!(
+            problems.is_empty(),
+            "instance is used inconsistently:\n- {}",
+            problems.join("\n- ")
+        );
+    }
+
+    /// Asserts that the region with the given index spans exactly the rows in
+    /// `expected`, from its first assigned row up to and including its last one.
+    ///
+    /// This pins down the layout of a circuit, so that a change to a floor planner or
+    /// chip that moves a region makes layout regression tests fail loudly.
+    ///
+    /// Panics if the region spans different rows, if it assigned no cells, or if
+    /// `region_index` is not the index of a region.
+    pub fn assert_region_rows(&self, region_index: usize, expected: Range<usize>) {
+        let region = &self.regions[region_index];
+        let (start, end) = region.rows.unwrap_or_else(|| {
+            panic!(
+                "region {} ('{}') has no assigned cells",
+                region_index, region.name
+            )
         });
+        assert_eq!(
+            start..end + 1,
+            expected,
+            "region {} ('{}') spans unexpected rows",
+            region_index,
+            region.name
+        );
+    }
 
-        // Check that all gates are satisfied for all rows.
-        let gate_errors =
-            self.cs
-                .gates
+    /// Returns `true` if the cells `a` and `b`, each given as a column and row, are
+    /// constrained to be equal by the permutation, either directly or through a chain of
+    /// copy constraints.
+    ///
+    /// This checks the wiring of the circuit independently of the values assigned to
+    /// the cells. Every cell is considered to be copied to itself.
+    pub fn is_copied(&self, a: (Column<Any>, usize), b: (Column<Any>, usize)) -> bool {
+        if a == b {
+            return true;
+        }
+
+        let columns = self.cs.permutation.get_columns();
+        let position = |(column, row): (Column<Any>, usize)| {
+            columns
                 .iter()
-                .enumerate()
-                .flat_map(|(gate_index, gate)| {
-                    let blinding_rows =
-                        (self.n as usize - (self.cs.blinding_factors() + 1))..(self.n as usize);
-                    (gate_row_ids
-                        .clone()
-                        .into_iter()
-                        .chain(blinding_rows.into_iter()))
-                    .flat_map(move |row| {
-                        let row = row as i32 + n;
-                        gate.polynomials().iter().enumerate().filter_map(
-                            move |(poly_index, poly)| match poly.evaluate_lazy(
-                                &|scalar| Value::Real(scalar),
-                                &|_| panic!("virtual selectors are removed during optimization"),
-                                &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
-                                &util::load(n, row, &self.cs.advice_queries, &self.advice),
-                                &util::load_instance(
-                                    n,
-                                    row,
-                                    &self.cs.instance_queries,
-                                    &self.instance,
-                                ),
-                                &|challenge| Value::Real(self.challenges[challenge.index()]),
-                                &|a| -a,
-                                &|a, b| a + b,
-                                &|a, b| a * b,
-                                &|a, scalar| a * scalar,
-                                &Value::Real(F::zero()),
-                            ) {
-                                Value::Real(x) if x.is_zero_vartime() => None,
-                                Value::Real(_) => Some(VerifyFailure::ConstraintNotSatisfied {
-                                    constraint: (
-                                        (gate_index, gate.name()).into(),
-                                        poly_index,
-                                        gate.constraint_name(poly_index),
-                                    )
-                                        .into(),
-                                    location: FailureLocation::find_expressions(
-                                        &self.cs,
-                                        &self.regions,
-                                        (row - n) as usize,
-                                        Some(poly).into_iter(),
-                                    ),
-                                    cell_values: util::cell_values(
-                                        gate,
-                                        poly,
-                                        &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
-                                        &util::load(n, row, &self.cs.advice_queries, &self.advice),
-                                        &util::load_instance(
-                                            n,
-                                            row,
-                                            &self.cs.instance_queries,
-                                            &self.instance,
-                                        ),
-                                    ),
-                                }),
-                                Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
-                                    constraint: (
-                                        (gate_index, gate.name()).into(),
-                                        poly_index,
-                                        gate.constraint_name(poly_index),
-                                    )
-                                        .into(),
-                                }),
-                            },
-                        )
-                    })
-                });
+                .position(|c| *c == column)
+                .filter(|_| row < self.n as usize)
+                .map(|index| (index, row))
+        };
+        let (a, b) = match (position(a), position(b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return false,
+        };
 
-        let mut cached_table = Vec::new();
-        let mut cached_table_identifier = Vec::new();
-        // Check that all lookups exist in their respective tables.
-        let lookup_errors =
-            self.cs
-                .lookups
-                .iter()
-                .enumerate()
-                .flat_map(|(lookup_index, lookup)| {
-                    let load = |expression: &Expression<F>, row| {
-                        expression.evaluate_lazy(
-                            &|scalar| Value::Real(scalar),
-                            &|_| panic!("virtual selectors are removed during optimization"),
-                            &|query| {
-                                let query = self.cs.fixed_queries[query.index];
-                                let column_index = query.0.index();
-                                let rotation = query.1 .0;
-                                self.fixed[column_index]
-                                    [(row as i32 + n + rotation) as usize % n as usize]
-                                    .into()
-                            },
-                            &|query| {
-                                let query = self.cs.advice_queries[query.index];
-                                let column_index = query.0.index();
-                                let rotation = query.1 .0;
-                                self.advice[column_index]
-                                    [(row as i32 + n + rotation) as usize % n as usize]
-                                    .into()
-                            },
-                            &|query| {
-                                let query = self.cs.instance_queries[query.index];
-                                let column_index = query.0.index();
-                                let rotation = query.1 .0;
-                                Value::Real(
-                                    self.instance[column_index]
-                                        [(row as i32 + n + rotation) as usize % n as usize],
-                                )
-                            },
-                            &|challenge| Value::Real(self.challenges[challenge.index()]),
-                            &|a| -a,
-                            &|a, b| a + b,
-                            &|a, b| a * b,
-                            &|a, scalar| a * scalar,
-                            &Value::Real(F::zero()),
-                        )
-                    };
-
-                    assert!(lookup.table_expressions.len() == lookup.input_expressions.len());
-                    assert!(self.usable_rows.end > 0);
-
-                    // We optimize on the basis that the table might have been filled so that the last
-                    // usable row now has the fill contents (it doesn't matter if there was no filling).
-                    // Note that this "fill row" necessarily exists in the table, and we use that fact to
-                    // slightly simplify the optimization: we're only trying to check that all input rows
-                    // are contained in the table, and so we can safely just drop input rows that
-                    // match the fill row.
-                    let fill_row: Vec<_> = lookup
-                        .table_expressions
-                        .iter()
-                        .map(move |c| load(c, self.usable_rows.end - 1))
-                        .collect();
+        // Walk the cycle containing `a`, looking for `b`.
+        let mut cell = self.permutation.mapping[a.0][a.1];
+        while cell != a {
+            if cell == b {
+                return true;
+            }
+            cell = self.permutation.mapping[cell.0][cell.1];
+        }
+        false
+    }
 
-                    let table_identifier = lookup
-                        .table_expressions
-                        .iter()
-                        .map(Expression::identifier)
-                        .collect::<Vec<_>>();
-                    if table_identifier != cached_table_identifier {
-                        cached_table_identifier = table_identifier;
+    /// Returns `true` if verifying this `MockProver` would pass vacuously: no selector
+    /// was enabled, every gate and lookup is controlled by a selector, and no copy
+    /// constraint was made.
+    ///
+    /// This catches tests that accidentally verify an empty circuit, and so give false
+    /// confidence.
+    pub fn is_trivial(&self) -> bool {
+        let no_selectors = self.selectors.iter().flatten().all(|enabled| !enabled);
+        let gates_selected = self
+            .cs
+            .gates
+            .iter()
+            .all(|gate| !gate.queried_selectors().is_empty());
+        let lookups_selected = self
+            .lookup_selectors
+            .iter()
+            .all(|selectors| !selectors.is_empty());
+        let no_copies = self
+            .permutation
+            .mapping
+            .iter()
+            .enumerate()
+            .all(|(column, cells)| {
+                cells
+                    .iter()
+                    .enumerate()
+                    .all(|(row, cell)| *cell == (column, row))
+            });
 
-                        // In the real prover, the lookup expressions are never enforced on
-                        // unusable rows, due to the (1 - (l_last(X) + l_blind(X))) term.
-                        cached_table = self
-                            .usable_rows
-                            .clone()
-                            .filter_map(|table_row| {
-                                let t = lookup
-                                    .table_expressions
-                                    .iter()
-                                    .map(move |c| load(c, table_row))
-                                    .collect();
+        no_selectors && gates_selected && lookups_selected && no_copies
+    }
 
-                                if t != fill_row {
-                                    Some(t)
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect();
-                        cached_table.sort_unstable();
-                    }
-                    let table = &cached_table;
+    /// Returns the rows on which the lookup with the given index is active, in
+    /// increasing order.
+    ///
+    /// A lookup whose inputs are multiplied by a selector only constrains the rows on
+    /// which that selector is enabled; on every other row its inputs collapse to the
+    /// default tuple. The enabling selectors are the ones queried by the lookup's input
+    /// expressions, and a row is active if any of them is enabled on it. A lookup that
+    /// doesn't query any selector is active on every usable row.
+    ///
+    /// Panics if `lookup_index` is not the index of a lookup.
+    pub fn lookup_active_rows(&self, lookup_index: usize) -> Vec<usize> {
+        let selectors = &self.lookup_selectors[lookup_index];
+        if selectors.is_empty() {
+            return self.usable_rows.clone().collect();
+        }
+        (0..self.n as usize)
+            .filter(|row| {
+                selectors
+                    .iter()
+                    .any(|selector| self.selectors[selector.0][*row])
+            })
+            .collect()
+    }
 
-                    let mut inputs: Vec<(Vec<_>, usize)> = lookup_input_row_ids
-                        .clone()
-                        .into_iter()
-                        .filter_map(|input_row| {
-                            let t = lookup
-                                .input_expressions
-                                .iter()
-                                .map(move |c| load(c, input_row))
-                                .collect();
+    /// Returns the equality classes of the permutation argument: groups of cells, each
+    /// given as a column and row, that are all constrained to be equal to each other.
+    ///
+    /// Seeing a whole class at once, rather than the individual copy constraints that
+    /// built it, makes it obvious when a cell has been tied into the wrong group. Cells
+    /// that aren't copied to any other cell are omitted. Within each class, cells are
+    /// ordered by column and then row, and classes are ordered by their first cell.
+    pub fn equality_classes(&self) -> Vec<Vec<(Column<Any>, usize)>> {
+        let columns = self.cs.permutation.get_columns();
+
+        let mut classes: BTreeMap<usize, Vec<(Column<Any>, usize)>> = BTreeMap::new();
+        for (column, ids) in self.equality_class_ids().into_iter().enumerate() {
+            for (row, id) in ids.into_iter().enumerate() {
+                classes.entry(id).or_default().push((columns[column], row));
+            }
+        }
 
-                            if t != fill_row {
-                                // Also keep track of the original input row, since we're going to sort.
-                                Some((t, input_row))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    inputs.sort_unstable();
+        let mut classes: Vec<_> = classes
+            .into_values()
+            .filter(|class| class.len() > 1)
+            .map(|mut class| {
+                class.sort_unstable();
+                class
+            })
+            .collect();
+        classes.sort_unstable();
+        classes
+    }
 
-                    let mut i = 0;
-                    inputs
-                        .iter()
-                        .filter_map(move |(input, input_row)| {
-                            while i < table.len() && &table[i] < input {
-                                i += 1;
-                            }
-                            if i == table.len() || &table[i] > input {
-                                assert!(table.binary_search(input).is_err());
+    /// Assigns each cell in the permutation an identifier of its equality class, such
+    /// that two cells are constrained to be equal exactly when their identifiers match.
+    /// The result is indexed by `[column][row]`, where `column` indexes the columns of
+    /// the permutation argument.
+    fn equality_class_ids(&self) -> Vec<Vec<usize>> {
+        let mapping = &self.permutation.mapping;
+        let mut ids: Vec<Vec<Option<usize>>> = mapping
+            .iter()
+            .map(|column| vec![None; column.len()])
+            .collect();
 
-                                Some(VerifyFailure::Lookup {
-                                    name: lookup.name,
-                                    lookup_index,
-                                    location: FailureLocation::find_expressions(
-                                        &self.cs,
-                                        &self.regions,
-                                        *input_row,
-                                        lookup.input_expressions.iter(),
-                                    ),
-                                })
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                });
+        let mut next_id = 0;
+        for (column, rows) in mapping.iter().enumerate() {
+            for row in 0..rows.len() {
+                if ids[column][row].is_some() {
+                    continue;
+                }
+                // Walk the cycle containing this cell, labelling every cell in it.
+                let mut cell = (column, row);
+                while ids[cell.0][cell.1].is_none() {
+                    ids[cell.0][cell.1] = Some(next_id);
+                    cell = mapping[cell.0][cell.1];
+                }
+                next_id += 1;
+            }
+        }
 
-        // Check that permutations preserve the original values of the cells.
-        let perm_errors = {
-            // Original values of columns involved in the permutation.
-            let original = |column, row| {
-                self.cs
-                    .permutation
-                    .get_columns()
-                    .get(column)
-                    .map(|c: &Column<Any>| match c.column_type() {
-                        Any::Advice(_) => self.advice[c.index()][row],
-                        Any::Fixed => self.fixed[c.index()][row],
-                        Any::Instance => CellValue::Assigned(self.instance[c.index()][row]),
-                    })
-                    .unwrap()
-            };
+        ids.into_iter()
+            .map(|column| column.into_iter().map(Option::unwrap).collect())
+            .collect()
+    }
 
-            // Iterate over each column of the permutation
-            self.permutation
-                .mapping
-                .iter()
-                .enumerate()
-                .flat_map(move |(column, values)| {
-                    // Iterate over each row of the column to check that the cell's
-                    // value is preserved by the mapping.
-                    values.iter().enumerate().filter_map(move |(row, cell)| {
-                        let original_cell = original(column, row);
-                        let permuted_cell = original(cell.0, cell.1);
-                        if original_cell == permuted_cell {
-                            None
-                        } else {
-                            let columns = self.cs.permutation.get_columns();
-                            let column = columns.get(column).unwrap();
-                            Some(VerifyFailure::Permutation {
-                                column: (*column).into(),
-                                location: FailureLocation::find(
-                                    &self.regions,
-                                    row,
-                                    Some(column).into_iter().cloned().collect(),
-                                ),
-                            })
-                        }
-                    })
-                })
-        };
+    /// Returns the degree of the quotient polynomial, in multiples of `n`, that the
+    /// circuit's highest-degree constraint implies.
+    ///
+    /// This is `degree - 1`, where `degree` is the maximum degree of the circuit's gates,
+    /// lookups and permutation argument. The prover evaluates the circuit over an
+    /// extended domain of at least `quotient_degree * n` rows, so raising the degree of a
+    /// single gate can double the cost of proving. This depends only on the constraint
+    /// system, and not on the witness.
+    pub fn quotient_degree(&self) -> usize {
+        self.cs.degree() - 1
+    }
 
-        let mut errors: Vec<_> = iter::empty()
-            .chain(selector_errors)
-            .chain(gate_errors)
-            .chain(lookup_errors)
-            .chain(perm_errors)
-            .collect();
-        if errors.is_empty() {
-            Ok(())
-        } else {
-            // Remove any duplicate `ConstraintPoisoned` errors (we check all unavailable
-            // rows in case the trigger is row-specific, but the error message only points
-            // at the constraint).
-            errors.dedup_by(|a, b| match (a, b) {
-                (
-                    a @ VerifyFailure::ConstraintPoisoned { .. },
-                    b @ VerifyFailure::ConstraintPoisoned { .. },
-                ) => a == b,
-                _ => false,
-            });
-            Err(errors)
-        }
+    /// Returns the names of the constraints of the gate with the given index, in the
+    /// order in which the gate defines them. Unnamed constraints have an empty name.
+    ///
+    /// This lets tests refer to a constraint by its name rather than by its index.
+    ///
+    /// Panics if `gate_index` is not the index of a gate.
+    pub fn gate_constraint_names(&self, gate_index: usize) -> Vec<&'static str> {
+        let gate = &self.cs.gates[gate_index];
+        (0..gate.polynomials().len())
+            .map(|poly_index| gate.constraint_name(poly_index))
+            .collect()
     }
 
-    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
-    /// the reasons that the circuit is not satisfied.
-    /// Constraints and lookup are checked at `usable_rows`, parallelly.
-    pub fn verify_par(&self) -> Result<(), Vec<VerifyFailure>> {
-        self.verify_at_rows_par(self.usable_rows.clone(), self.usable_rows.clone())
+    /// Returns the degree of each constraint polynomial of the circuit's gates, as
+    /// `(gate_index, constraint_index, degree)` tuples in gate order.
+    ///
+    /// The degree of the most expensive constraint sets the
+    /// [quotient degree](MockProver::quotient_degree) of the whole circuit, so this
+    /// shows which constraints would need to be split up (for example, a degree-5
+    /// constraint into two degree-3 constraints) to make proving cheaper. Selectors count
+    /// towards the degree.
+    pub fn gate_term_degrees(&self) -> Vec<(usize, usize, usize)> {
+        self.cs
+            .gates
+            .iter()
+            .enumerate()
+            .flat_map(|(gate_index, gate)| {
+                gate.polynomials()
+                    .iter()
+                    .enumerate()
+                    .map(move |(poly_index, poly)| (gate_index, poly_index, poly.degree()))
+            })
+            .collect()
     }
 
-    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
-    /// the reasons that the circuit is not satisfied.
-    /// Constraints are only checked at `gate_row_ids`,
-    /// and lookup inputs are only checked at `lookup_input_row_ids`, parallelly.
-    pub fn verify_at_rows_par<I: Clone + Iterator<Item = usize>>(
-        &self,
-        gate_row_ids: I,
-        lookup_input_row_ids: I,
-    ) -> Result<(), Vec<VerifyFailure>> {
-        let n = self.n as i32;
+    /// Returns the advice column and rotation of the advice query with the given index,
+    /// as referenced by [`Expression::Advice`], or `None` if there is no such query.
+    pub fn resolve_advice_query(&self, index: usize) -> Option<(Column<Advice>, Rotation)> {
+        self.cs.advice_queries.get(index).copied()
+    }
 
-        let gate_row_ids = gate_row_ids.collect::<Vec<_>>();
-        let lookup_input_row_ids = lookup_input_row_ids.collect::<Vec<_>>();
+    /// Returns the fixed column and rotation of the fixed query with the given index,
+    /// as referenced by [`Expression::Fixed`], or `None` if there is no such query.
+    ///
+    /// Once selectors have been compressed, this includes the queries of the fixed
+    /// columns they were compressed into.
+    pub fn resolve_fixed_query(&self, index: usize) -> Option<(Column<Fixed>, Rotation)> {
+        self.cs.fixed_queries.get(index).copied()
+    }
 
-        // check all the row ids are valid
-        gate_row_ids.par_iter().for_each(|row_id| {
-            if !self.usable_rows.contains(row_id) {
-                panic!("invalid gate row id {}", row_id);
-            }
-        });
-        lookup_input_row_ids.par_iter().for_each(|row_id| {
-            if !self.usable_rows.contains(row_id) {
-                panic!("invalid gate row id {}", row_id);
-            }
-        });
+    /// Returns the instance column and rotation of the instance query with the given
+    /// index, as referenced by [`Expression::Instance`], or `None` if there is no such
+    /// query.
+    pub fn resolve_instance_query(&self, index: usize) -> Option<(Column<Instance>, Rotation)> {
+        self.cs.instance_queries.get(index).copied()
+    }
 
-        // Check that within each region, all cells used in instantiated gates have been
-        // assigned to.
-        let selector_errors = self.regions.iter().enumerate().flat_map(|(r_i, r)| {
-            r.enabled_selectors.iter().flat_map(move |(selector, at)| {
-                // Find the gates enabled by this selector
+    /// Returns the minimum and maximum rotations at which any column is queried by the
+    /// circuit's gates and lookups. The returned range always includes zero.
+    ///
+    /// A gate enabled on a row reads the rows offset from it by these rotations, so
+    /// they determine how much padding a layouter must leave around its assignments.
+    /// This depends only on the constraint system, and not on the witness.
+    pub fn max_rotation(&self) -> (i32, i32) {
+        let rotations = self
+            .cs
+            .fixed_queries
+            .iter()
+            .map(|(_, rotation)| rotation.0)
+            .chain(
                 self.cs
-                    .gates
+                    .advice_queries
                     .iter()
-                    // Assume that if a queried selector is enabled, the user wants to use the
-                    // corresponding gate in some way.
-                    //
-                    // TODO: This will trip up on the reverse case, where leaving a selector
-                    // un-enabled keeps a gate enabled. We could alternatively require that
-                    // every selector is explicitly enabled or disabled on every row? But that
-                    // seems messy and confusing.
-                    .enumerate()
-                    .filter(move |(_, g)| g.queried_selectors().contains(selector))
-                    .flat_map(move |(gate_index, gate)| {
-                        at.par_iter()
-                            .flat_map(move |selector_row| {
-                                // Selectors are queried with no rotation.
-                                let gate_row = *selector_row as i32;
+                    .map(|(_, rotation)| rotation.0),
+            )
+            .chain(
+                self.cs
+                    .instance_queries
+                    .iter()
+                    .map(|(_, rotation)| rotation.0),
+            );
 
-                                gate.queried_cells()
-                                    .iter()
-                                    .filter_map(move |cell| {
-                                        // Determine where this cell should have been assigned.
-                                        let cell_row =
-                                            ((gate_row + n + cell.rotation.0) % n) as usize;
+        rotations.fold((0, 0), |(min, max), rotation| {
+            (min.min(rotation), max.max(rotation))
+        })
+    }
 
-                                        // Check that it was assigned!
-                                        if r.cells.contains_key(&(cell.column, cell_row)) {
-                                            None
-                                        } else {
-                                            Some(VerifyFailure::CellNotAssigned {
-                                                gate: (gate_index, gate.name()).into(),
-                                                region: (r_i, r.name.clone()).into(),
-                                                gate_offset: *selector_row,
-                                                column: cell.column,
-                                                offset: cell_row as isize
-                                                    - r.rows.unwrap().0 as isize,
-                                            })
-                                        }
-                                    })
-                                    .collect::<Vec<_>>()
-                            })
-                            .collect::<Vec<_>>()
-                    })
-            })
-        });
+    /// Returns the ranges of usable rows, in increasing order, that are not occupied by
+    /// any region.
+    ///
+    /// This is an aid for auditing how tightly the floor planner packs regions: large
+    /// gaps are wasted rows, and may mean that the circuit needs a larger `k` than it
+    /// should. Rows after the last region are not reported, and neither are the rows
+    /// reserved for blinding factors. Lookup tables are not assigned within regions, so
+    /// the rows they occupy may be reported as gaps.
+    pub fn row_gaps(&self) -> Vec<Range<usize>> {
+        let mut occupied: Vec<_> = self.regions.iter().filter_map(|r| r.rows).collect();
+        occupied.sort_unstable();
+
+        let mut gaps = vec![];
+        let mut next_free = self.usable_rows.start;
+        for (start, end) in occupied {
+            if start > next_free {
+                gaps.push(next_free..start);
+            }
+            next_free = next_free.max(end + 1);
+        }
+        gaps
+    }
 
-        // Check that all gates are satisfied for all rows.
-        let gate_errors = self
+    /// Returns the number of rows used by the circuit: one more than the highest row on
+    /// which any fixed or advice cell was assigned, or any selector was enabled.
+    ///
+    /// This makes it easy to guard against a refactor accidentally bloating a circuit
+    /// that targets a fixed `k`:
+    ///
+    /// ```ignore
+    /// assert!(prover.used_rows() <= budget);
+    /// ```
+    ///
+    /// Layouters pad lookup table columns to the end of the usable rows, so a table
+    /// column is only counted up to its last row that differs from that padding.
+    /// Instance columns are not counted.
+    pub fn used_rows(&self) -> usize {
+        let last_assigned = |column: &Vec<CellValue<F>>| {
+            column
+                .iter()
+                .rposition(|cell| matches!(cell, CellValue::Assigned(_)))
+        };
+
+        let table_columns: HashSet<usize> = self
             .cs
-            .gates
+            .lookups
+            .iter()
+            .flat_map(|lookup| lookup.table_expressions.iter())
+            .flat_map(|expression| {
+                expression.evaluate(
+                    &|_| vec![],
+                    &|_| vec![],
+                    &|query| vec![query.column_index],
+                    &|_| vec![],
+                    &|_| vec![],
+                    &|_| vec![],
+                    &|a| a,
+                    &|mut a, mut b| {
+                        a.append(&mut b);
+                        a
+                    },
+                    &|mut a, mut b| {
+                        a.append(&mut b);
+                        a
+                    },
+                    &|a, _| a,
+                )
+            })
+            .collect();
+        let selector_columns: HashSet<usize> =
+            self.cs.selector_map.iter().map(|c| c.index()).collect();
+
+        let fixed = self
+            .fixed
             .iter()
             .enumerate()
-            .flat_map(|(gate_index, gate)| {
-                let blinding_rows =
-                    (self.n as usize - (self.cs.blinding_factors() + 1))..(self.n as usize);
-                (gate_row_ids
-                    .clone()
-                    .into_par_iter()
-                    .chain(blinding_rows.into_par_iter()))
-                .flat_map(move |row| {
-                    let row = row as i32 + n;
-                    gate.polynomials()
+            .filter(|(index, _)| !selector_columns.contains(index))
+            .filter_map(|(index, column)| {
+                if table_columns.contains(&index) && self.usable_rows.end > 0 {
+                    let padding = &column[self.usable_rows.end - 1];
+                    column[..self.usable_rows.end]
                         .iter()
-                        .enumerate()
-                        .filter_map(move |(poly_index, poly)| {
-                            match poly.evaluate_lazy(
-                                &|scalar| Value::Real(scalar),
-                                &|_| panic!("virtual selectors are removed during optimization"),
-                                &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
-                                &util::load(n, row, &self.cs.advice_queries, &self.advice),
-                                &util::load_instance(
-                                    n,
-                                    row,
-                                    &self.cs.instance_queries,
-                                    &self.instance,
-                                ),
-                                &|challenge| Value::Real(self.challenges[challenge.index()]),
-                                &|a| -a,
-                                &|a, b| a + b,
-                                &|a, b| a * b,
-                                &|a, scalar| a * scalar,
-                                &Value::Real(F::zero()),
-                            ) {
-                                Value::Real(x) if x.is_zero_vartime() => None,
-                                Value::Real(_) => Some(VerifyFailure::ConstraintNotSatisfied {
-                                    constraint: (
-                                        (gate_index, gate.name()).into(),
-                                        poly_index,
-                                        gate.constraint_name(poly_index),
-                                    )
-                                        .into(),
-                                    location: FailureLocation::find_expressions(
-                                        &self.cs,
-                                        &self.regions,
-                                        (row - n) as usize,
-                                        Some(poly).into_iter(),
-                                    ),
-                                    cell_values: util::cell_values(
-                                        gate,
-                                        poly,
-                                        &util::load(n, row, &self.cs.fixed_queries, &self.fixed),
-                                        &util::load(n, row, &self.cs.advice_queries, &self.advice),
-                                        &util::load_instance(
-                                            n,
-                                            row,
-                                            &self.cs.instance_queries,
-                                            &self.instance,
-                                        ),
-                                    ),
-                                }),
-                                Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
-                                    constraint: (
-                                        (gate_index, gate.name()).into(),
-                                        poly_index,
-                                        gate.constraint_name(poly_index),
-                                    )
-                                        .into(),
-                                }),
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                })
-                .collect::<Vec<_>>()
+                        .rposition(|cell| cell != padding)
+                } else {
+                    last_assigned(column)
+                }
             });
+        let advice = self.advice.iter().filter_map(last_assigned);
+        let selectors = self
+            .selectors
+            .iter()
+            .filter_map(|column| column.iter().rposition(|enabled| *enabled));
 
-        let mut cached_table = Vec::new();
-        let mut cached_table_identifier = Vec::new();
-        // Check that all lookups exist in their respective tables.
-        let lookup_errors =
-            self.cs
-                .lookups
-                .iter()
-                .enumerate()
-                .flat_map(|(lookup_index, lookup)| {
-                    let load = |expression: &Expression<F>, row| {
-                        expression.evaluate_lazy(
-                            &|scalar| Value::Real(scalar),
-                            &|_| panic!("virtual selectors are removed during optimization"),
-                            &|query| {
-                                self.fixed[query.column_index]
-                                    [(row as i32 + n + query.rotation.0) as usize % n as usize]
-                                    .into()
-                            },
-                            &|query| {
-                                self.advice[query.column_index]
-                                    [(row as i32 + n + query.rotation.0) as usize % n as usize]
-                                    .into()
-                            },
-                            &|query| {
-                                Value::Real(
-                                    self.instance[query.column_index]
-                                        [(row as i32 + n + query.rotation.0) as usize % n as usize],
-                                )
-                            },
-                            &|challenge| Value::Real(self.challenges[challenge.index()]),
-                            &|a| -a,
-                            &|a, b| a + b,
-                            &|a, b| a * b,
-                            &|a, scalar| a * scalar,
-                            &Value::Real(F::zero()),
-                        )
-                    };
+        fixed
+            .chain(advice)
+            .chain(selectors)
+            .max()
+            .map_or(0, |row| row + 1)
+    }
 
-                    assert!(lookup.table_expressions.len() == lookup.input_expressions.len());
-                    assert!(self.usable_rows.end > 0);
+    /// Returns the indices of the fixed columns whose assigned values are all either
+    /// zero or one, in increasing order.
+    ///
+    /// Such columns could often be modeled as selectors instead, which lets them be
+    /// optimized away by selector combining. The fixed columns that selectors were
+    /// compressed into are not reported, nor are columns without any assigned cells.
+    pub fn boolean_fixed_columns(&self) -> Vec<usize> {
+        let selector_columns: HashSet<usize> =
+            self.cs.selector_map.iter().map(|c| c.index()).collect();
+
+        self.fixed
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !selector_columns.contains(index))
+            .filter(|(_, column)| {
+                let mut assigned = column
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        CellValue::Assigned(value) => Some(value),
+                        CellValue::Unassigned | CellValue::Poison(_) => None,
+                    })
+                    .peekable();
+                assigned.peek().is_some()
+                    && assigned.all(|value| *value == F::zero() || *value == F::one())
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
 
-                    // We optimize on the basis that the table might have been filled so that the last
-                    // usable row now has the fill contents (it doesn't matter if there was no filling).
-                    // Note that this "fill row" necessarily exists in the table, and we use that fact to
-                    // slightly simplify the optimization: we're only trying to check that all input rows
-                    // are contained in the table, and so we can safely just drop input rows that
-                    // match the fill row.
-                    let fill_row: Vec<_> = lookup
-                        .table_expressions
-                        .iter()
-                        .map(move |c| load(c, self.usable_rows.end - 1))
-                        .collect();
+    /// Returns the indices of the regions in which no cells were assigned.
+    ///
+    /// A region that is entered and exited without assigning anything is usually a bug
+    /// in chip code, such as assignment logic that was accidentally skipped. This is a
+    /// cheap lint that doesn't require verifying the circuit. Enabling a selector does
+    /// not count as an assignment.
+    pub fn empty_regions(&self) -> Vec<usize> {
+        self.regions
+            .iter()
+            .enumerate()
+            .filter(|(_, region)| region.cells.is_empty())
+            .map(|(index, _)| index)
+            .collect()
+    }
 
-                    let table_identifier = lookup
-                        .table_expressions
-                        .iter()
-                        .map(Expression::identifier)
-                        .collect::<Vec<_>>();
-                    if table_identifier != cached_table_identifier {
-                        cached_table_identifier = table_identifier;
+    /// Returns the selectors, along with the rows at which they were enabled, that were
+    /// not enabled inside a committed region.
+    ///
+    /// This happens if a selector is enabled while no region is being assigned to, or
+    /// within a region that was never exited. A well-behaved layouter never does either,
+    /// so any selectors returned here indicate a malformed layouter or a custom
+    /// [`Assignment`] implementation. Gates using these selectors are still checked by
+    /// [`MockProver::verify`], but their cells are not checked for assignment.
+    pub fn floating_selectors(&self) -> Vec<(Selector, usize)> {
+        let unexited = self.current_region.iter().flat_map(|region| {
+            region
+                .enabled_selectors
+                .iter()
+                .flat_map(|(selector, rows)| rows.iter().map(move |row| (*selector, *row)))
+        });
 
-                        // In the real prover, the lookup expressions are never enforced on
-                        // unusable rows, due to the (1 - (l_last(X) + l_blind(X))) term.
-                        cached_table = self
-                            .usable_rows
-                            .clone()
-                            .into_par_iter()
-                            .filter_map(|table_row| {
-                                let t = lookup
-                                    .table_expressions
-                                    .iter()
-                                    .map(move |c| load(c, table_row))
-                                    .collect();
+        self.floating_selectors
+            .iter()
+            .cloned()
+            .chain(unexited)
+            .collect()
+    }
 
-                                if t != fill_row {
-                                    Some(t)
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect();
-                        cached_table.par_sort_unstable();
-                    }
-                    let table = &cached_table;
+    /// Returns the value currently assigned to the given cell, or `None` if it is
+    /// unassigned. Instance cells are always assigned.
+    fn assigned_value(&self, column: Column<Any>, row: usize) -> Option<F> {
+        let cell = match column.column_type() {
+            Any::Advice(_) => self.advice[column.index()][row],
+            Any::Fixed => self.fixed[column.index()][row],
+            Any::Instance => {
+                return Some(
+                    self.instance[column.index()]
+                        .get(row)
+                        .copied()
+                        .unwrap_or_else(F::zero),
+                )
+            }
+        };
+        match cell {
+            CellValue::Assigned(value) => Some(value),
+            CellValue::Unassigned | CellValue::Poison(_) => None,
+        }
+    }
 
-                    let mut inputs: Vec<(Vec<_>, usize)> = lookup_input_row_ids
-                        .clone()
-                        .into_par_iter()
-                        .filter_map(|input_row| {
-                            let t = lookup
-                                .input_expressions
-                                .iter()
-                                .map(move |c| load(c, input_row))
-                                .collect();
+    /// Returns the rows that are reserved for blinding factors, and so cannot be
+    /// assigned to.
+    fn unusable_rows(&self) -> Range<usize> {
+        self.usable_rows.end..(self.n as usize)
+    }
+}
 
-                            if t != fill_row {
-                                // Also keep track of the original input row, since we're going to sort.
-                                Some((t, input_row))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    inputs.par_sort_unstable();
+impl<F: FieldExt> MockProver<F> {
+    /// Runs a synthetic keygen-and-prove operation on the given circuit, collecting data
+    /// about the constraints and their assignments.
+    ///
+    /// The witness is assigned in a single pass, with every challenge available. If the
+    /// circuit uses more than one phase, it is then synthesized again once per phase,
+    /// as by the real prover: each pass only assigns the advice columns of its phase,
+    /// and only knows the challenges of earlier phases. If a column is assigned a value
+    /// that isn't known in its phase, such as one computed from a later-phase cell,
+    /// this returns [`Error::Synthesis`], and the other constructors return
+    /// [`MockProverError::ValueUnavailableInPhase`].
+    pub fn run<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+    ) -> Result<Self, Error> {
+        Self::run_with_options(k, circuit, instance, RunOptions::default())
+            .and_then(Self::fully_synthesized)
+            .map_err(Error::from)
+    }
 
-                    inputs
-                        .par_iter()
-                        .filter_map(move |(input, input_row)| {
-                            if table.binary_search(input).is_err() {
-                                Some(VerifyFailure::Lookup {
-                                    name: lookup.name,
-                                    lookup_index,
-                                    location: FailureLocation::find_expressions(
-                                        &self.cs,
-                                        &self.regions,
-                                        *input_row,
-                                        lookup.input_expressions.iter(),
-                                    ),
-                                })
-                            } else {
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                });
+    /// Behaves like [`MockProver::run`], but reuses a constraint system and config that
+    /// have already been produced by `ConcreteCircuit::configure`.
+    ///
+    /// This avoids re-configuring the circuit when checking many witnesses against the
+    /// same circuit shape. The caller is responsible for ensuring that `cs` and `config`
+    /// are exactly what `ConcreteCircuit::configure` would produce; no check is made.
+    pub fn run_with_config<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        cs: &ConstraintSystem<F>,
+        config: ConcreteCircuit::Config,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+    ) -> Result<Self, MockProverError> {
+        Self::synthesize_with_config(
+            k,
+            cs.clone(),
+            config,
+            circuit,
+            instance,
+            RunOptions::default(),
+        )
+        .and_then(Self::fully_synthesized)
+    }
 
-        // Check that permutations preserve the original values of the cells.
-        let perm_errors = {
-            // Original values of columns involved in the permutation.
-            let original = |column, row| {
-                self.cs
-                    .permutation
-                    .get_columns()
-                    .get(column)
-                    .map(|c: &Column<Any>| match c.column_type() {
-                        Any::Advice(_) => self.advice[c.index()][row],
-                        Any::Fixed => self.fixed[c.index()][row],
-                        Any::Instance => CellValue::Assigned(self.instance[c.index()][row]),
-                    })
-                    .unwrap()
-            };
+    /// Behaves like [`MockProver::run`], but binds the circuit's challenges to the given
+    /// values instead of deriving them deterministically.
+    ///
+    /// `challenges` is indexed by [`Challenge::index`], and must contain exactly one
+    /// value for each challenge used by the circuit. The values are returned by
+    /// [`Assignment::get_challenge`] during synthesis, and used wherever gates and
+    /// lookups query the challenges during verification. This makes it possible to test
+    /// phased circuits end-to-end against specific challenge values.
+    pub fn run_with_challenges<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+        challenges: Vec<F>,
+    ) -> Result<Self, MockProverError> {
+        let options = RunOptions {
+            challenges: Some(challenges),
+            ..RunOptions::default()
+        };
+        Self::run_with_options(k, circuit, instance, options).and_then(Self::fully_synthesized)
+    }
 
-            // Iterate over each column of the permutation
-            self.permutation
-                .mapping
-                .iter()
-                .enumerate()
-                .flat_map(move |(column, values)| {
-                    // Iterate over each row of the column to check that the cell's
-                    // value is preserved by the mapping.
-                    values
-                        .par_iter()
-                        .enumerate()
-                        .filter_map(move |(row, cell)| {
-                            let original_cell = original(column, row);
-                            let permuted_cell = original(cell.0, cell.1);
-                            if original_cell == permuted_cell {
-                                None
-                            } else {
-                                let columns = self.cs.permutation.get_columns();
-                                let column = columns.get(column).unwrap();
-                                Some(VerifyFailure::Permutation {
-                                    column: (*column).into(),
-                                    location: FailureLocation::find(
-                                        &self.regions,
-                                        row,
-                                        Some(column).into_iter().cloned().collect(),
-                                    ),
-                                })
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                })
+    /// Behaves like [`MockProver::run`], but derives the circuit's challenges from
+    /// `seed`.
+    ///
+    /// The challenges are a pure function of `seed` and the number of challenges used by
+    /// the circuit: running the same circuit with the same seed always produces the same
+    /// challenges, on any platform, and [`MockProver::run`] behaves as if it were given
+    /// a fixed default seed. This makes it possible to reproduce, or vary, the
+    /// challenge values that a circuit's witness was computed from. The derived values
+    /// are returned by [`MockProver::challenges`].
+    ///
+    /// The real prover draws its challenges from the transcript, so they depend on the
+    /// commitments to the witness and will not match the challenges derived here. To
+    /// check a witness against specific challenge values, use
+    /// [`MockProver::run_with_challenges`].
+    pub fn run_with_seed<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+        seed: &[u8],
+    ) -> Result<Self, MockProverError> {
+        let options = RunOptions {
+            seed: Some(seed.to_vec()),
+            ..RunOptions::default()
         };
+        Self::run_with_options(k, circuit, instance, options).and_then(Self::fully_synthesized)
+    }
 
-        let mut errors: Vec<_> = iter::empty()
-            .chain(selector_errors)
-            .chain(gate_errors)
-            .chain(lookup_errors)
-            .chain(perm_errors)
-            .collect();
-        if errors.is_empty() {
-            Ok(())
-        } else {
-            // Remove any duplicate `ConstraintPoisoned` errors (we check all unavailable
-            // rows in case the trigger is row-specific, but the error message only points
-            // at the constraint).
-            errors.dedup_by(|a, b| match (a, b) {
-                (
-                    a @ VerifyFailure::ConstraintPoisoned { .. },
-                    b @ VerifyFailure::ConstraintPoisoned { .. },
-                ) => a == b,
-                _ => false,
-            });
-            Err(errors)
-        }
+    /// Behaves like [`MockProver::run`], but checks each copy constraint as soon as it
+    /// is created.
+    ///
+    /// If both cells of a copy constraint have already been assigned when the
+    /// constraint is created, and their values differ, synthesis fails with
+    /// [`MockProverError::CopyMismatch`]. This points at the offending `copy` call
+    /// through the synthesis error chain, instead of leaving it to be reported by
+    /// [`MockProver::verify`]. Copies whose cells are assigned afterwards are still only
+    /// checked by `verify`.
+    pub fn run_with_copy_checks<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+    ) -> Result<Self, MockProverError> {
+        let options = RunOptions {
+            check_copies: true,
+            ..RunOptions::default()
+        };
+        Self::run_with_options(k, circuit, instance, options).and_then(Self::fully_synthesized)
+    }
+
+    /// Behaves like [`MockProver::run`], but if synthesis fails partway through, returns
+    /// the `MockProver` holding whatever was assigned before the failure, along with
+    /// the synthesis error.
+    ///
+    /// This is useful for inspecting the partial witness of a chip that fails deep
+    /// inside its assignment code. If the failure happened inside a region, that region
+    /// is never exited; its selectors are reported by
+    /// [`MockProver::floating_selectors`].
+    ///
+    /// Errors that occur before synthesis starts (for example, if `k` is too small or
+    /// the instance columns don't match the circuit) are still returned as `Err`, as
+    /// there is no witness to inspect.
+    pub fn run_partial<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+    ) -> Result<(Self, Option<MockProverError>), MockProverError> {
+        Self::run_with_options(k, circuit, instance, RunOptions::default())
+    }
+
+    /// Returns a [`MockProverBuilder`] for the given constraint system, which can be
+    /// used to set cell values, selectors and copy constraints directly instead of
+    /// synthesizing a circuit.
+    ///
+    /// The resulting `MockProver` has the same internal layout as one created by
+    /// [`MockProver::run`].
+    pub fn builder(
+        k: u32,
+        cs: ConstraintSystem<F>,
+    ) -> Result<MockProverBuilder<F>, MockProverError> {
+        let instance = vec![vec![]; cs.num_instance_columns];
+        Self::empty(k, cs, instance).map(MockProverBuilder::new)
+    }
+
+    /// Configures `circuit`, and synthesizes it into a new `MockProver` with the given
+    /// options.
+    fn run_with_options<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+        options: RunOptions<F>,
+    ) -> Result<(Self, Option<MockProverError>), MockProverError> {
+        let mut cs = ConstraintSystem::default();
+        let config = ConcreteCircuit::configure(&mut cs);
+
+        Self::synthesize_with_config(k, cs, config, circuit, instance, options)
     }
 
-    /// Panics if the circuit being checked by this `MockProver` is not satisfied.
-    ///
-    /// Any verification failures will be pretty-printed to stderr before the function
-    /// panics.
-    ///
-    /// Apart from the stderr output, this method is equivalent to:
-    /// ```ignore
-    /// assert_eq!(prover.verify(), Ok(()));
-    /// ```
-    pub fn assert_satisfied(&self) {
-        if let Err(errs) = self.verify() {
-            for err in errs {
-                err.emit(self);
-                eprintln!();
+    /// Synthesizes `circuit` into a new `MockProver` with the given options, using a
+    /// constraint system and config produced by `ConcreteCircuit::configure`.
+    ///
+    /// If synthesis fails partway through, the `MockProver` holding whatever was
+    /// assigned before the failure is returned along with the error. Errors that occur
+    /// before synthesis starts are returned as `Err`.
+    fn synthesize_with_config<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        cs: ConstraintSystem<F>,
+        config: ConcreteCircuit::Config,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+        options: RunOptions<F>,
+    ) -> Result<(Self, Option<MockProverError>), MockProverError> {
+        if let Some(challenges) = &options.challenges {
+            if challenges.len() != cs.num_challenges {
+                return Err(MockProverError::InvalidChallenges {
+                    expected: cs.num_challenges,
+                    provided: challenges.len(),
+                });
+            }
+        }
+
+        // Circuits with several phases are synthesized again for each phase once the
+        // witness is complete, so keep what that needs.
+        let phase_passes = if cs.phases().count() > 1 {
+            Some((cs.clone(), config.clone(), instance.clone()))
+        } else {
+            None
+        };
+
+        let constants = cs.constants.clone();
+        let mut prover = Self::empty(k, cs, instance)?;
+        if let Some(challenges) = options.challenges {
+            prover.challenges = challenges;
+            prover.challenge_seed = None;
+        } else if let Some(seed) = options.seed {
+            prover.challenges = Self::derive_challenges(&seed, prover.cs.num_challenges);
+            prover.challenge_seed = Some(seed);
+        }
+        prover.check_copies = options.check_copies;
+        prover.region_check = options.region_check;
+        if options.record_events {
+            prover.events = Some(vec![]);
+        }
+
+        let result =
+            ConcreteCircuit::FloorPlanner::synthesize(&mut prover, circuit, config, constants);
+        let mut error = result.err().map(|error| prover.detailed_error(error));
+        prover.region_check = None;
+
+        if error.is_none() {
+            if let Some((cs, config, instance)) = phase_passes {
+                error = Self::phase_error(k, cs, config, circuit, instance, &prover.challenges)?;
+            }
+        }
+
+        Ok((prover.compress_selectors(), error))
+    }
+
+    /// Synthesizes `circuit` once for each of its phases, as the real prover does, and
+    /// returns the first error, if any.
+    ///
+    /// Each pass only assigns the advice columns of its phase, and only knows the
+    /// challenges of earlier phases. A witness that depends on anything else fails with
+    /// [`MockProverError::ValueUnavailableInPhase`].
+    fn phase_error<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        cs: ConstraintSystem<F>,
+        config: ConcreteCircuit::Config,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+        challenges: &[F],
+    ) -> Result<Option<MockProverError>, MockProverError> {
+        for phase in cs.phases() {
+            let mut prover = Self::empty(k, cs.clone(), instance.clone())?;
+            prover.challenges = challenges.to_vec();
+            prover.phase = Some(Advice { phase }.phase());
+
+            let constants = prover.cs.constants.clone();
+            if let Err(error) = ConcreteCircuit::FloorPlanner::synthesize(
+                &mut prover,
+                circuit,
+                config.clone(),
+                constants,
+            ) {
+                return Ok(Some(prover.detailed_error(error)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the `MockProver` from [`MockProver::synthesize_with_config`], or the
+    /// error if synthesis failed.
+    fn fully_synthesized(
+        (prover, error): (Self, Option<MockProverError>),
+    ) -> Result<Self, MockProverError> {
+        match error {
+            Some(error) => Err(error),
+            None => Ok(prover),
+        }
+    }
+
+    /// Derives `count` challenges from `seed`.
+    fn derive_challenges(seed: &[u8], count: usize) -> Vec<F> {
+        // Use hash chain to derive deterministic challenges for testing
+        let mut hash: [u8; 64] = blake2b(seed).as_bytes().try_into().unwrap();
+        iter::repeat_with(|| {
+            hash = blake2b(&hash).as_bytes().try_into().unwrap();
+            F::from_bytes_wide(&hash)
+        })
+        .take(count)
+        .collect()
+    }
+
+    /// Allocates a `MockProver` for the given constraint system, with every cell
+    /// unassigned and no copy constraints.
+    fn empty(
+        k: u32,
+        cs: ConstraintSystem<F>,
+        instance: Vec<Vec<F>>,
+    ) -> Result<Self, MockProverError> {
+        let n = 1 << k;
+
+        if n < cs.minimum_rows() {
+            // The permutation argument is what separates the rows reserved at the end of
+            // the circuit from the usable rows, so circuits using it get a more
+            // specific error.
+            if cs.permutation.get_columns().is_empty() {
+                return Err(Error::not_enough_rows_available(k).into());
+            }
+            return Err(MockProverError::NotEnoughRowsForPermutation {
+                current_k: k,
+                minimum_k: cs.minimum_rows().next_power_of_two().trailing_zeros(),
+            });
+        }
+
+        // Lookup arguments are built from `(input, table)` pairs, so their arities can
+        // only differ if the constraint system was modified by other means. The
+        // per-row lookup checks rely on them matching, so check up front.
+        if let Some(lookup_index) = cs
+            .lookups
+            .iter()
+            .position(|lookup| lookup.input_expressions.len() != lookup.table_expressions.len())
+        {
+            return Err(MockProverError::LookupArityMismatch { lookup_index });
+        }
+
+        let instance_lens = instance.iter().map(Vec::len).collect();
+        let instance = Self::pad_instance(&cs, n, instance)?;
+
+        // Fixed columns contain no blinding factors.
+        let fixed = vec![vec![CellValue::Unassigned; n]; cs.num_fixed_columns];
+        let selectors = vec![vec![false; n]; cs.num_selectors];
+        // Advice columns contain blinding factors.
+        let blinding_factors = cs.blinding_factors();
+        let usable_rows = n - (blinding_factors + 1);
+        let advice = vec![
+            {
+                let mut column = vec![CellValue::Unassigned; n];
+                // Poison unusable rows.
+                for (i, cell) in column.iter_mut().enumerate().skip(usable_rows) {
+                    *cell = CellValue::Poison(i);
+                }
+                column
+            };
+            cs.num_advice_columns
+        ];
+        let permutation = permutation::keygen::Assembly::new(n, &cs.permutation);
+
+        let challenges = Self::derive_challenges(DEFAULT_CHALLENGE_SEED, cs.num_challenges);
+
+        Ok(MockProver {
+            k,
+            n: n as u32,
+            cs,
+            regions: vec![],
+            current_region: None,
+            fixed,
+            advice,
+            instance,
+            instance_lens,
+            selectors,
+            challenges,
+            challenge_seed: Some(DEFAULT_CHALLENGE_SEED.to_vec()),
+            permutation,
+            usable_rows: 0..usable_rows,
+            unassigned_default: F::zero(),
+            floating_selectors: vec![],
+            strict: false,
+            check_copies: false,
+            report_instance_mismatches: false,
+            region_check: None,
+            phase: None,
+            failed_region: RegionFailure::default(),
+            lookup_selectors: vec![],
+            outputs: vec![],
+            events: None,
+            conflicting_assignments: vec![],
+            shared_cells: HashSet::new(),
+            instance_queried: Flag::default(),
+            pending_error: PendingError::default(),
+            selector_kind_conflicts: vec![],
+            lookup_normalizers: HashMap::new(),
+            lookup_scratch: LookupScratch::default(),
+            scan_order: ScanOrder::default(),
+            field_formatter: format_field,
+        })
+    }
+
+    /// Returns the fixed and advice cells whose values differ between this `MockProver`
+    /// and `other`, which must have been synthesized from the same circuit.
+    fn differing_cells(&self, other: &Self) -> Vec<(Column<Any>, usize)> {
+        let fixed = self
+            .fixed
+            .iter()
+            .zip(other.fixed.iter())
+            .enumerate()
+            .flat_map(|(index, (a, b))| {
+                let column = Column::new(index, Any::Fixed);
+                a.iter()
+                    .zip(b.iter())
+                    .enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(move |(row, _)| (column, row))
+            });
+        let advice = self
+            .advice
+            .iter()
+            .zip(other.advice.iter())
+            .enumerate()
+            .flat_map(|(index, (a, b))| {
+                let column = self.advice_column(index);
+                a.iter()
+                    .zip(b.iter())
+                    .enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(move |(row, _)| (column, row))
+            });
+
+        fixed.chain(advice).collect()
+    }
+
+    /// Checks that the provided instance columns fit the circuit, and pads each of them
+    /// with zeroes to `n` rows.
+    fn pad_instance(
+        cs: &ConstraintSystem<F>,
+        n: usize,
+        instance: Vec<Vec<F>>,
+    ) -> Result<Vec<Vec<F>>, Error> {
+        if instance.len() != cs.num_instance_columns {
+            return Err(Error::InvalidInstances);
+        }
+
+        instance
+            .into_iter()
+            .map(|mut instance| {
+                if instance.len() > n - (cs.blinding_factors() + 1) {
+                    return Err(Error::InstanceTooLarge);
+                }
+
+                instance.resize(n, F::zero());
+                Ok(instance)
+            })
+            .collect()
+    }
+
+    /// Compresses the selectors enabled during synthesis into fixed columns, in the
+    /// same way as the real keygen.
+    fn compress_selectors(mut self) -> Self {
+        let n = self.n as usize;
+        // Compression replaces selectors with fixed columns, so remember which selectors
+        // each lookup depends on while we still can.
+        self.lookup_selectors = self
+            .cs
+            .lookups
+            .iter()
+            .map(|lookup| {
+                lookup
+                    .input_expressions
+                    .iter()
+                    .flat_map(|expression| {
+                        expression.evaluate(
+                            &|_| vec![],
+                            &|selector| vec![selector],
+                            &|_| vec![],
+                            &|_| vec![],
+                            &|_| vec![],
+                            &|_| vec![],
+                            &|a| a,
+                            &|mut a, mut b| {
+                                a.append(&mut b);
+                                a
+                            },
+                            &|mut a, mut b| {
+                                a.append(&mut b);
+                                a
+                            },
+                            &|a, _| a,
+                        )
+                    })
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        self.selector_kind_conflicts = selector_kind_conflicts(&self.cs);
+        let (cs, selector_polys) = self.cs.compress_selectors(self.selectors.clone());
+        self.cs = cs;
+        self.fixed.extend(selector_polys.into_iter().map(|poly| {
+            let mut v = vec![CellValue::Unassigned; n];
+            for (v, p) in v.iter_mut().zip(&poly[..]) {
+                *v = CellValue::Assigned(*p);
+            }
+            v
+        }));
+        self
+    }
+
+    /// Returns the circuit's public outputs: every instance cell that is constrained to
+    /// equal an advice cell, as its instance column index, row, and value.
+    ///
+    /// This lets tests confirm that the public interface of a circuit is what they
+    /// expect. Outputs are ordered by instance column and then row.
+    pub fn public_outputs(&self) -> Vec<(usize, usize, F)> {
+        let columns = self.cs.permutation.get_columns();
+        let ids = self.equality_class_ids();
+
+        let advice_ids: HashSet<usize> = columns
+            .iter()
+            .zip(ids.iter())
+            .filter(|(column, _)| matches!(column.column_type(), Any::Advice(_)))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect();
+
+        let mut outputs: Vec<_> = columns
+            .iter()
+            .zip(ids.iter())
+            .filter(|(column, _)| column.column_type() == &Any::Instance)
+            .flat_map(|(column, ids)| {
+                ids.iter()
+                    .enumerate()
+                    .filter(|(_, id)| advice_ids.contains(*id))
+                    .map(move |(row, _)| (column.index(), row, self.instance[column.index()][row]))
+            })
+            .collect();
+        outputs.sort_unstable_by_key(|(column, row, _)| (*column, *row));
+        outputs
+    }
+
+    /// Returns a 32-byte hash of every assigned fixed cell, including the fixed columns
+    /// that selectors were compressed into.
+    ///
+    /// Chips that hardcode constant tables can have them silently changed by a
+    /// refactor. Asserting that the fingerprint is unchanged pins those tables. Cells
+    /// are hashed by column index, row and the canonical representation of their value,
+    /// in column and then row order; unassigned cells are skipped.
+    pub fn fixed_fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Blake2bParams::new()
+            .hash_length(32)
+            .personal(b"Halo2-FixedPrint")
+            .to_state();
+        for (column, values) in self.fixed.iter().enumerate() {
+            for (row, value) in values.iter().enumerate() {
+                if let CellValue::Assigned(value) = value {
+                    hasher.update(&(column as u64).to_le_bytes());
+                    hasher.update(&(row as u64).to_le_bytes());
+                    hasher.update(value.to_repr().as_ref());
+                }
+            }
+        }
+        hasher.finalize().as_bytes().try_into().unwrap()
+    }
+
+    /// Suggests pairs of advice cells that might be missing a copy constraint between
+    /// them.
+    ///
+    /// This is a best-effort lint, and its results are suggestions rather than errors.
+    /// A pair is suggested when both cells are in columns that have equality enabled,
+    /// were assigned in different regions, hold the same value, and are not connected
+    /// by the permutation. Values of zero and one are ignored, as they coincide far too
+    /// often to be meaningful. Cells assigned outside of any region are not considered.
+    pub fn suggest_missing_copies(&self) -> Vec<((Column<Any>, usize), (Column<Any>, usize))> {
+        let columns = self.cs.permutation.get_columns();
+        let classes = self.equality_class_ids();
+
+        // Group the copyable advice cells by value.
+        let mut by_value: BTreeMap<F, Vec<(usize, Column<Any>, usize, usize)>> = BTreeMap::new();
+        for (r_i, r) in self.regions.iter().enumerate() {
+            for (column, row) in r.cells.keys() {
+                let perm_index = match columns.iter().position(|c| c == column) {
+                    Some(perm_index) => perm_index,
+                    None => continue,
+                };
+                let value = match column.column_type() {
+                    Any::Advice(_) => match self.advice[column.index()][*row] {
+                        CellValue::Assigned(value) => value,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                if value == F::zero() || value == F::one() {
+                    continue;
+                }
+                by_value.entry(value).or_default().push((
+                    r_i,
+                    *column,
+                    *row,
+                    classes[perm_index][*row],
+                ));
+            }
+        }
+
+        let mut suggestions = vec![];
+        for mut cells in by_value.into_values() {
+            cells.sort_unstable_by_key(|(r_i, column, row, _)| (*r_i, *column, *row));
+            let (anchor_region, anchor_column, anchor_row, anchor_class) = cells[0];
+            let mut seen_classes = HashSet::new();
+            seen_classes.insert(anchor_class);
+            for (r_i, column, row, class) in cells.into_iter().skip(1) {
+                if r_i != anchor_region && seen_classes.insert(class) {
+                    suggestions.push(((anchor_column, anchor_row), (column, row)));
+                }
+            }
+        }
+        suggestions
+    }
+
+    /// Evaluates `expression` against the witness at the given row, in the same way that
+    /// [`MockProver::verify`] evaluates gate constraints.
+    ///
+    /// The expression's queries must refer to queries of this `MockProver`'s constraint
+    /// system (for example, an expression captured inside `Circuit::configure`). Queried
+    /// rotations wrap around the end of the circuit, and unassigned cells take the value
+    /// set with [`MockProver::set_unassigned_default`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression queries a selector (selectors are compressed into fixed
+    /// columns after synthesis), or reads a cell in one of the unusable rows.
+    pub fn eval_expression(&self, expression: &Expression<F>, row: usize) -> F {
+        match self.evaluate_at(expression, row) {
+            Value::Real(value) => value,
+            Value::Poison => panic!("expression reads an unusable row at row {}", row),
+        }
+    }
+
+    /// Returns every gate constraint that evaluates to exactly `target` on a usable
+    /// row, as its gate index, constraint index within the gate, and row.
+    ///
+    /// Constraints that are off by a known constant usually point to a systematic error:
+    /// a `target` of one, for example, suggests an off-by-one. Results are ordered by
+    /// gate, then constraint, then row. Evaluations that read an unusable row are
+    /// skipped. Note that a `target` of zero matches every satisfied constraint.
+    pub fn near_miss_constraints(&self, target: F) -> Vec<(usize, usize, usize)> {
+        self.cs
+            .gates
+            .iter()
+            .enumerate()
+            .flat_map(|(gate_index, gate)| {
+                gate.polynomials()
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(poly_index, poly)| {
+                        self.usable_rows
+                            .clone()
+                            .filter(move |row| self.evaluate_at(poly, *row) == Value::Real(target))
+                            .map(move |row| (gate_index, poly_index, row))
+                    })
+            })
+            .collect()
+    }
+
+    /// Renders a compact text grid of the circuit's assignments, with one column per
+    /// advice column, fixed column and selector, and one line per group of rows.
+    ///
+    /// Each cell is drawn as `#` if it was assigned (or, for selectors, `S` if it was
+    /// enabled), `x` if it lies in the rows reserved for blinding factors, and `.`
+    /// otherwise. To keep the output readable for large circuits, rows are bucketed so
+    /// that at most 64 lines are printed; a bucket is drawn as assigned if any of its
+    /// rows is.
+    ///
+    /// This is a lightweight alternative to `CircuitLayout` (available with the
+    /// `dev-graph` feature) that works in a terminal or CI log.
+    pub fn ascii_layout(&self) -> String {
+        use std::fmt::Write;
+
+        const MAX_LINES: usize = 64;
+
+        let n = self.n as usize;
+        let bucket_size = (n + MAX_LINES - 1) / MAX_LINES;
+
+        // Selectors have been compressed into fixed columns, which we render separately.
+        let selector_columns: HashSet<usize> =
+            self.cs.selector_map.iter().map(|c| c.index()).collect();
+        let cell_columns: Vec<_> = iter::empty()
+            .chain(
+                self.advice
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| (format!("A{}", i), column)),
+            )
+            .chain(
+                self.fixed
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !selector_columns.contains(i))
+                    .map(|(i, column)| (format!("F{}", i), column)),
+            )
+            .collect();
+        let selector_labels: Vec<_> = (0..self.selectors.len())
+            .map(|i| format!("S{}", i))
+            .collect();
+
+        let row_width = (n - 1).to_string().len();
+        let mut layout = format!(
+            "# assigned, . unassigned, x unusable, S selector enabled ({} row(s) per line)\n",
+            bucket_size
+        );
+
+        write!(layout, "{:>width$} |", "", width = row_width).unwrap();
+        for (label, _) in &cell_columns {
+            write!(layout, " {}", label).unwrap();
+        }
+        write!(layout, " |").unwrap();
+        for label in &selector_labels {
+            write!(layout, " {}", label).unwrap();
+        }
+        writeln!(layout).unwrap();
+
+        for start in (0..n).step_by(bucket_size) {
+            let rows = start..(start + bucket_size).min(n);
+
+            write!(layout, "{:>width$} |", start, width = row_width).unwrap();
+            for (label, column) in &cell_columns {
+                let cells = &column[rows.clone()];
+                let c = if cells.iter().any(|c| matches!(c, CellValue::Assigned(_))) {
+                    '#'
+                } else if rows.start >= self.usable_rows.end {
+                    'x'
+                } else {
+                    '.'
+                };
+                write!(layout, " {:<width$}", c, width = label.len()).unwrap();
+            }
+            write!(layout, " |").unwrap();
+            for (label, selector) in selector_labels.iter().zip(self.selectors.iter()) {
+                let c = if selector[rows.clone()].iter().any(|enabled| *enabled) {
+                    'S'
+                } else {
+                    '.'
+                };
+                write!(layout, " {:<width$}", c, width = label.len()).unwrap();
+            }
+            writeln!(layout).unwrap();
+        }
+
+        layout
+    }
+
+    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
+    /// the reasons that the circuit is not satisfied.
+    pub fn verify(&self) -> Result<(), Vec<VerifyFailure>> {
+        let mut failures = Vec::new();
+        self.verify_into(&mut failures);
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Behaves like [`MockProver::verify`], but writes the failures into `buf` instead
+    /// of allocating a new vector. The circuit is satisfied if `buf` is empty afterwards.
+    ///
+    /// `buf` is cleared first, and keeps its capacity across calls, which saves
+    /// reallocating the output when verifying repeatedly in a loop. The `MockProver`
+    /// likewise keeps the lookup tables that it builds, so that rebuilding them on the
+    /// next call reuses their allocations. The other checks allocate as `verify` does.
+    pub fn verify_into(&self, buf: &mut Vec<VerifyFailure>) {
+        buf.clear();
+        // Calls made concurrently from other threads build their own tables.
+        let mut tables = std::mem::take(&mut *self.lookup_scratch.0.lock().unwrap());
+
+        for check in Check::ALL {
+            match check {
+                Check::Lookups => {
+                    tables.resize_with(self.cs.lookups.len(), Vec::new);
+                    // Lookups sharing a table only build it once, as in `verify`.
+                    let mut built: Option<(_, usize)> = None;
+                    for lookup_index in 0..self.cs.lookups.len() {
+                        let fill_row = self.lookup_fill_row(lookup_index);
+                        let table_identifier = self.lookup_table_identifier(lookup_index);
+                        let table_index = match built {
+                            Some((ref identifier, index)) if *identifier == table_identifier => {
+                                index
+                            }
+                            _ => {
+                                self.fill_lookup_table(
+                                    lookup_index,
+                                    &fill_row,
+                                    &mut tables[lookup_index],
+                                );
+                                built = Some((table_identifier, lookup_index));
+                                lookup_index
+                            }
+                        };
+
+                        let inputs = self.sorted_lookup_inputs(
+                            lookup_index,
+                            &fill_row,
+                            self.usable_rows.clone(),
+                        );
+                        buf.extend(self.missing_lookup_inputs(
+                            lookup_index,
+                            inputs,
+                            &tables[table_index],
+                        ));
+                    }
+                }
+                _ => buf.extend(self.check_failures(
+                    check,
+                    self.usable_rows.clone(),
+                    self.usable_rows.clone(),
+                )),
+            }
+        }
+
+        *self.lookup_scratch.0.lock().unwrap() = tables;
+        dedup_poisoned(buf);
+    }
+
+    /// Behaves like [`MockProver::verify`], but stops after collecting `max` failures.
+    ///
+    /// The returned flag is `true` if verification stopped early, in which case there
+    /// are more failures than those returned. This keeps debugging usable on large,
+    /// badly broken circuits, where collecting every failure could exhaust memory.
+    /// Repeated `VerifyFailure::ConstraintPoisoned` failures are skipped before they
+    /// count towards `max`, as they are removed by `verify`. The failures are found
+    /// lazily, so apart from the returned failures, memory use is bounded as for
+    /// [`MockProver::iter_failures`].
+    pub fn verify_bounded(&self, max: usize) -> (Result<(), Vec<VerifyFailure>>, bool) {
+        let mut failures = skip_repeated_poisoned(
+            self.failures_at_rows(self.usable_rows.clone(), self.usable_rows.clone()),
+        );
+        let errors: Vec<_> = failures.by_ref().take(max).collect();
+        let truncated = failures.next().is_some();
+        (failures_to_result(errors), truncated)
+    }
+
+    /// Behaves like [`MockProver::verify`], but groups the failures by the index of the
+    /// region in which they occurred.
+    ///
+    /// Failures that occurred outside of any region (including
+    /// `VerifyFailure::ConstraintPoisoned`) are grouped under `None`.
+    pub fn verify_by_region(&self) -> Result<(), HashMap<Option<usize>, Vec<VerifyFailure>>> {
+        self.verify().map_err(|failures| {
+            let mut by_region: HashMap<_, Vec<_>> = HashMap::new();
+            for failure in failures {
+                by_region
+                    .entry(failure.region_index())
+                    .or_default()
+                    .push(failure);
+            }
+            by_region
+        })
+    }
+
+    /// Checks that every lookup's table contains the tuple its inputs evaluate to when
+    /// every cell is zero, independently of the actual inputs.
+    ///
+    /// Lookups conditioned on a complex selector (such as `q * a`) collapse to this
+    /// default tuple on rows where the selector is disabled, so it must be present in
+    /// the table. A `VerifyFailure::LookupDefault` naming the lookup is returned for each
+    /// table that is missing it.
+    pub fn verify_lookup_defaults(&self) -> Result<(), Vec<VerifyFailure>> {
+        let errors = (0..self.cs.lookups.len())
+            .filter_map(|lookup_index| {
+                let lookup = &self.cs.lookups[lookup_index];
+                let default: Vec<_> = lookup
+                    .input_expressions
+                    .iter()
+                    .map(|expression| {
+                        expression.evaluate(
+                            &|scalar| scalar,
+                            &|_| panic!("virtual selectors are removed during optimization"),
+                            &|_| F::zero(),
+                            &|_| F::zero(),
+                            &|_| F::zero(),
+                            &|challenge| self.challenges[challenge.index()],
+                            &|a| -a,
+                            &|a, b| a + b,
+                            &|a, b| a * b,
+                            &|a, scalar| a * scalar,
+                        )
+                    })
+                    .collect();
+
+                let fill_row = self.lookup_fill_row(lookup_index);
+                let table = self.lookup_table(lookup_index, &fill_row);
+                let default_row = self.normalize_lookup_tuple(
+                    lookup_index,
+                    default.iter().map(|v| Value::Real(*v)).collect(),
+                );
+                if default_row == fill_row || table.binary_search(&default_row).is_ok() {
+                    None
+                } else {
+                    Some(VerifyFailure::LookupDefault {
+                        name: lookup.name,
+                        lookup_index,
+                        default_values: default.into_iter().map(self.field_formatter).collect(),
+                    })
+                }
+            })
+            .collect();
+        failures_to_result(errors)
+    }
+
+    /// Checks that no usable row of a lookup's table is only partially assigned.
+    ///
+    /// Tables that mix fixed and advice columns, such as dynamic tables, are easy to
+    /// load inconsistently. A table row on which some table expressions read only
+    /// assigned cells, while others read an unassigned cell, usually means a table-load
+    /// bug. A `VerifyFailure::PartialLookupTableRow` naming the lookup and the row is
+    /// returned for each such row. Table expressions that read no fixed or advice cells
+    /// are ignored.
+    pub fn verify_lookup_tables(&self) -> Result<(), Vec<VerifyFailure>> {
+        let n = self.n as i32;
+        let is_assigned = |column: Column<Any>, row: usize| {
+            let cell = match column.column_type() {
+                Any::Advice(_) => self.advice[column.index()][row],
+                Any::Fixed => self.fixed[column.index()][row],
+                Any::Instance => unreachable!(),
+            };
+            matches!(cell, CellValue::Assigned(_))
+        };
+
+        let mut errors = vec![];
+        for (lookup_index, lookup) in self.cs.lookups.iter().enumerate() {
+            let expression_cells: Vec<_> = lookup
+                .table_expressions
+                .iter()
+                .map(|expression| self.expression_cells(expression))
+                .filter(|cells| !cells.is_empty())
+                .collect();
+
+            for row in self.usable_rows.clone() {
+                let unassigned: Vec<Vec<Column<Any>>> = expression_cells
+                    .iter()
+                    .map(|cells| {
+                        cells
+                            .iter()
+                            .filter(|(column, rotation)| {
+                                !is_assigned(*column, ((row as i32 + n + rotation) % n) as usize)
+                            })
+                            .map(|(column, _)| *column)
+                            .collect()
+                    })
+                    .collect();
+
+                let partial = unassigned.iter().any(|columns| columns.is_empty())
+                    && unassigned.iter().any(|columns| !columns.is_empty());
+                if partial {
+                    let columns: BTreeSet<_> = unassigned.into_iter().flatten().collect();
+                    errors.push(VerifyFailure::PartialLookupTableRow {
+                        name: lookup.name,
+                        lookup_index,
+                        location: FailureLocation::find_expressions(
+                            &self.cs,
+                            &self.regions,
+                            row,
+                            lookup.table_expressions.iter(),
+                        ),
+                        unassigned_columns: columns.into_iter().map(|c| c.into()).collect(),
+                    });
+                }
+            }
+        }
+        failures_to_result(errors)
+    }
+
+    /// Returns the distinct fixed and advice cells read by `expression`, as columns and
+    /// rotations.
+    fn expression_cells(&self, expression: &Expression<F>) -> Vec<(Column<Any>, i32)> {
+        let cells: BTreeSet<_> = expression
+            .evaluate(
+                &|_| vec![],
+                &|_| vec![],
+                &|query| {
+                    vec![(
+                        Column::new(query.column_index, Any::Fixed),
+                        query.rotation.0,
+                    )]
+                },
+                &|query| vec![(self.advice_column(query.column_index), query.rotation.0)],
+                &|_| vec![],
+                &|_| vec![],
+                &|a| a,
+                &|mut a, mut b| {
+                    a.append(&mut b);
+                    a
+                },
+                &|mut a, mut b| {
+                    a.append(&mut b);
+                    a
+                },
+                &|a, _| a,
+            )
+            .into_iter()
+            .collect();
+        cells.into_iter().collect()
+    }
+
+    /// Returns, for the lookup with the given index, how many usable rows' inputs matched
+    /// each table entry.
+    ///
+    /// Table entries are keyed by the concatenated byte representations of their
+    /// values. This reveals hot table entries, which helps when sizing or optimizing a
+    /// table: for example, a range-check table where the inputs on disabled rows all
+    /// match the same default entry. Entries that no input matched are omitted, as are
+    /// inputs that are not in the table.
+    ///
+    /// Panics if `lookup_index` is not the index of a lookup.
+    pub fn lookup_histogram(&self, lookup_index: usize) -> HashMap<Vec<u8>, usize> {
+        let fill_row = self.lookup_fill_row(lookup_index);
+        let table = self.lookup_table(lookup_index, &fill_row);
+
+        let mut histogram = HashMap::new();
+        for row in self.usable_rows.clone() {
+            let input = self.lookup_input(lookup_index, row);
+            if input != fill_row && table.binary_search(&input).is_err() {
+                continue;
+            }
+            let key = input
+                .iter()
+                .map(|value| match value {
+                    Value::Real(value) => Some(value.to_repr()),
+                    Value::Poison => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            if let Some(key) = key {
+                let key = key.iter().flat_map(|repr| repr.as_ref().to_vec()).collect();
+                *histogram.entry(key).or_default() += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Returns `Ok(())` if the copy constraints of this `MockProver` are satisfied, or a
+    /// list of errors indicating which cells are not equal to the cells they are
+    /// constrained to.
+    ///
+    /// Gates and lookups are not checked. This gives fast, focused feedback when a
+    /// `copy` is suspected of wiring the wrong cells together.
+    pub fn verify_permutations(&self) -> Result<(), Vec<VerifyFailure>> {
+        failures_to_result(
+            self.permutation_failures()
+                .chain(self.instance_failures())
+                .collect(),
+        )
+    }
+
+    /// Behaves like [`MockProver::verify`], but ignores the values of instance cells.
+    ///
+    /// Gates and lookups are still checked, as are the copy constraints between fixed
+    /// and advice cells, but copy constraints to instance cells are skipped. This
+    /// separates "is the witness internally consistent?" from "does it match the
+    /// claimed public inputs?", which are different bugs. Cells that are only related
+    /// through an instance cell must still be equal to each other.
+    pub fn verify_ignoring_instance(&self) -> Result<(), Vec<VerifyFailure>> {
+        let mut failures = vec![];
+        for check in Check::ALL {
+            match check {
+                Check::Permutation => failures.extend(self.witness_permutation_failures()),
+                Check::Instance => (),
+                _ => failures.extend(self.check_failures(
+                    check,
+                    self.usable_rows.clone(),
+                    self.usable_rows.clone(),
+                )),
+            }
+        }
+        failures_to_result(failures)
+    }
+
+    /// Replaces the instance columns with `instance`, and re-checks only the parts of
+    /// the circuit that depend on them, reusing the fixed and advice assignments.
+    ///
+    /// This speeds up sweeping many public inputs against the same witness. The gates
+    /// and lookups that query an instance column are checked, along with the copy
+    /// constraints; everything else is assumed to still pass, so the `MockProver`
+    /// should have been verified before the first call. Each column is padded with
+    /// zeroes, as in [`MockProver::run`].
+    ///
+    /// Returns [`MockProverError::WitnessDependsOnInstance`] if synthesis read any
+    /// instance values (for example with `Region::assign_advice_from_instance`), since
+    /// the advice assignments may then be stale, and [`Error::InstanceTooLarge`] if
+    /// `instance` doesn't fit. The instance is left unchanged if an error is returned.
+    pub fn rerun_instance_only(
+        &mut self,
+        instance: Vec<Vec<F>>,
+    ) -> Result<Result<(), Vec<VerifyFailure>>, MockProverError> {
+        if self.instance_queried.get() {
+            return Err(MockProverError::WitnessDependsOnInstance);
+        }
+        let instance_lens = instance.iter().map(Vec::len).collect();
+        self.instance = Self::pad_instance(&self.cs, self.n as usize, instance)?;
+        self.instance_lens = instance_lens;
+
+        let queries_instance = |expression: &Expression<F>| {
+            expression.evaluate(
+                &|_| false,
+                &|_| false,
+                &|_| false,
+                &|_| false,
+                &|_| true,
+                &|_| false,
+                &|a| a,
+                &|a, b| a || b,
+                &|a, b| a || b,
+                &|a, _| a,
+            )
+        };
+
+        let mut failures = vec![];
+        for check in Check::ALL {
+            match check {
+                Check::Gates => failures.extend(self.selected_gate_failures(
+                    |gate_index| {
+                        self.cs.gates[gate_index]
+                            .polynomials()
+                            .iter()
+                            .any(queries_instance)
+                    },
+                    self.usable_rows.clone().chain(self.unusable_rows()),
+                )),
+                Check::Lookups => failures.extend(self.selected_lookup_failures(
+                    |lookup_index| {
+                        let lookup = &self.cs.lookups[lookup_index];
+                        lookup
+                            .input_expressions
+                            .iter()
+                            .chain(lookup.table_expressions.iter())
+                            .any(queries_instance)
+                    },
+                    self.usable_rows.clone(),
+                )),
+                Check::Permutation | Check::Instance => failures.extend(self.check_failures(
+                    check,
+                    self.usable_rows.clone(),
+                    self.usable_rows.clone(),
+                )),
+                // The remaining checks don't depend on the instance.
+                Check::Selectors | Check::LookupCells | Check::RotatedCells => (),
+            }
+        }
+        Ok(failures_to_result(failures))
+    }
+
+    /// Behaves like [`MockProver::verify`], but keeps going if evaluating a gate panics.
+    ///
+    /// A malformed constraint system (for example, one with an unexpected query index)
+    /// can make gate evaluation panic, which aborts `verify`. Here, each gate is
+    /// evaluated separately, and a panic is converted into a `VerifyFailure::Internal`
+    /// failure so that the remaining gates are still checked. The panic message is
+    /// still printed by the panic hook. Panics in the other checks are propagated.
+    pub fn verify_resilient(&self) -> Result<(), Vec<VerifyFailure>> {
+        let rows = self.usable_rows.clone().chain(self.unusable_rows());
+        let gate_failures = |gate_index: usize| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                self.single_gate_failures(gate_index, rows.clone())
+                    .collect::<Vec<_>>()
+            }))
+            .unwrap_or_else(|payload| {
+                let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = payload.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    "unknown panic".to_string()
+                };
+                vec![VerifyFailure::Internal {
+                    gate_index,
+                    message,
+                }]
+            })
+        };
+
+        let mut failures = vec![];
+        for check in Check::ALL {
+            match check {
+                Check::Gates => failures.extend((0..self.cs.gates.len()).flat_map(&gate_failures)),
+                _ => failures.extend(self.check_failures(
+                    check,
+                    self.usable_rows.clone(),
+                    self.usable_rows.clone(),
+                )),
+            }
+        }
+        failures_to_result(failures)
+    }
+
+    /// Behaves like [`MockProver::verify`], but skips the gates with the given indices.
+    ///
+    /// Failures about cells that a skipped gate reads are skipped too. By excluding
+    /// different sets of gates, the gate that breaks a circuit can be found by binary
+    /// search, without editing `configure`.
+    pub fn verify_excluding(&self, gate_indices: &[usize]) -> Result<(), Vec<VerifyFailure>> {
+        self.verify_selected_gates(|gate_index| !gate_indices.contains(&gate_index))
+    }
+
+    /// Behaves like [`MockProver::verify`], but only checks the gates with the given
+    /// indices. Lookups and copy constraints are still checked.
+    ///
+    /// This is the inclusive counterpart of [`MockProver::verify_excluding`].
+    pub fn verify_gates(&self, gate_indices: &[usize]) -> Result<(), Vec<VerifyFailure>> {
+        self.verify_selected_gates(|gate_index| gate_indices.contains(&gate_index))
+    }
+
+    /// Behaves like [`MockProver::verify`], but only checks the gates for which
+    /// `selected` returns `true`.
+    fn verify_selected_gates(
+        &self,
+        selected: impl Fn(usize) -> bool,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let mut failures = vec![];
+        for check in Check::ALL {
+            match check {
+                Check::Gates => failures.extend(self.selected_gate_failures(
+                    &selected,
+                    self.usable_rows.clone().chain(self.unusable_rows()),
+                )),
+                _ => failures.extend(
+                    self.check_failures(check, self.usable_rows.clone(), self.usable_rows.clone())
+                        .filter(|failure| failure.gate_index().map_or(true, &selected)),
+                ),
+            }
+        }
+        failures_to_result(failures)
+    }
+
+    /// Returns an iterator over the reasons that the circuit is not satisfied, which
+    /// checks the circuit lazily as it is advanced.
+    ///
+    /// This is useful for tooling that only wants the first few failures of a badly
+    /// broken circuit, without collecting all of them as [`MockProver::verify`] does.
+    /// Unlike `verify`, a `VerifyFailure::ConstraintPoisoned` failure is yielded for
+    /// every unusable row on which its constraint is active. Within each check, rows
+    /// are visited in the order set with [`MockProver::set_scan_order`].
+    ///
+    /// Failures are never collected, but some checks need more than one row at a time:
+    /// each lookup's table and inputs are built and sorted before its first failure is
+    /// yielded, and in the stricter verification mode the set of assigned cells is
+    /// collected before the rotated cells are checked. Memory use is bounded by these,
+    /// rather than by the number of failures.
+    pub fn iter_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        let rows = self.scan_rows();
+        self.failures_at_rows(rows.clone().into_iter(), rows.into_iter())
+    }
+
+    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
+    /// the reasons that the circuit is not satisfied.
+    /// Constraints are only checked at `gate_row_ids`,
+    /// and lookup inputs are only checked at `lookup_input_row_ids`
+    pub fn verify_at_rows<I: Clone + Iterator<Item = usize>>(
+        &self,
+        gate_row_ids: I,
+        lookup_input_row_ids: I,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        self.check_row_ids(gate_row_ids.clone(), lookup_input_row_ids.clone());
+
+        failures_to_result(
+            self.failures_at_rows(gate_row_ids, lookup_input_row_ids)
+                .collect(),
+        )
+    }
+
+    /// Lazily runs every check, with constraints only checked at `gate_row_ids` and
+    /// lookup inputs only checked at `lookup_input_row_ids`.
+    fn failures_at_rows<'a, I: Clone + Iterator<Item = usize> + 'a>(
+        &'a self,
+        gate_row_ids: I,
+        lookup_input_row_ids: I,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        Check::ALL.into_iter().flat_map(move |check| {
+            self.check_failures(check, gate_row_ids.clone(), lookup_input_row_ids.clone())
+        })
+    }
+
+    /// Lazily runs the given check, with constraints only checked at `gate_row_ids` and
+    /// every unusable row, and lookup inputs only checked at `lookup_input_row_ids`.
+    ///
+    /// The checks only do their work once the returned iterator is advanced. See
+    /// [`MockProver::iter_failures`] for what is held in memory while it runs.
+    fn check_failures<'a, I: Clone + Iterator<Item = usize> + 'a>(
+        &'a self,
+        check: Check,
+        gate_row_ids: I,
+        lookup_input_row_ids: I,
+    ) -> Box<dyn Iterator<Item = VerifyFailure> + 'a> {
+        match check {
+            Check::Selectors => Box::new(self.selector_failures()),
+            Check::LookupCells => Box::new(self.lookup_cell_failures()),
+            Check::RotatedCells => Box::new(self.rotated_cell_failures()),
+            Check::Gates => Box::new(self.gate_failures(gate_row_ids.chain(self.unusable_rows()))),
+            Check::Lookups => Box::new(self.lookup_failures(lookup_input_row_ids)),
+            Check::Permutation => Box::new(self.permutation_failures()),
+            Check::Instance => Box::new(self.instance_failures()),
+        }
+    }
+
+    /// Returns the first failure found when checking the circuit row by row, or `None`
+    /// if the circuit is satisfied.
+    ///
+    /// Unlike [`MockProver::verify`], which collects every failure, this stops at the
+    /// earliest row on which any check fails. This makes it well suited to finding out
+    /// where witness generation first went wrong, after which
+    /// [`MockProver::verify_at_rows`] can be used to focus on the surrounding rows.
+    /// Rows are checked in the order set with [`MockProver::set_scan_order`], and the
+    /// checks on each row in the same order as by `verify`. This returns `None` exactly
+    /// when `verify` returns `Ok(())`.
+    ///
+    /// Gates that are active on unusable rows, and failures that aren't tied to a
+    /// usable row, are only reported if every usable row is satisfied.
+    pub fn first_failure(&self) -> Option<VerifyFailure> {
+        let rows = self.scan_rows();
+        let mut positions = vec![usize::MAX; self.n as usize];
+        for (position, row) in rows.iter().enumerate() {
+            positions[*row] = position;
+        }
+        let position = |failure: &VerifyFailure| {
+            failure
+                .row(&self.regions)
+                .map_or(usize::MAX, |row| positions[row])
+        };
+
+        // The checks that don't run row by row usually find very few failures, so find
+        // the earliest failure of each up front.
+        let mut earliest: Vec<Option<VerifyFailure>> = Check::ALL
+            .into_iter()
+            .map(|check| {
+                if check.is_row_wise() {
+                    None
+                } else {
+                    self.check_failures(check, iter::empty(), iter::empty())
+                        .min_by_key(position)
+                }
+            })
+            .collect();
+        let lookup_tables = self.lookup_tables();
+
+        for (row_position, row) in rows.into_iter().enumerate() {
+            for (check, earliest) in Check::ALL.into_iter().zip(earliest.iter_mut()) {
+                let failure = match check {
+                    Check::Gates => self.gate_failures(iter::once(row)).next(),
+                    Check::Lookups => lookup_tables.iter().enumerate().find_map(
+                        |(lookup_index, (fill_row, table))| {
+                            self.lookup_row_failure(lookup_index, fill_row, table, row)
+                        },
+                    ),
+                    Check::Permutation => (0..self.permutation.mapping.len())
+                        .find_map(|column| self.permutation_failure(column, row)),
+                    _ if earliest.as_ref().map(position) == Some(row_position) => earliest.take(),
+                    _ => None,
+                };
+                if failure.is_some() {
+                    return failure;
+                }
+            }
+        }
+
+        Check::ALL
+            .into_iter()
+            .zip(earliest)
+            .find_map(|(check, earliest)| match check {
+                Check::Gates => self.gate_failures(self.unusable_rows()).next(),
+                Check::Permutation => self.unusable_rows().find_map(|row| {
+                    (0..self.permutation.mapping.len())
+                        .find_map(|column| self.permutation_failure(column, row))
+                }),
+                _ => earliest,
+            })
+    }
+
+    /// Behaves like [`MockProver::verify`], but additionally records how long each of
+    /// the verification phases took.
+    ///
+    /// This is useful for finding out whether gates, lookups, or permutations dominate
+    /// the cost of verifying a large circuit.
+    pub fn verify_timed(&self) -> (Result<(), Vec<VerifyFailure>>, VerifyTimings) {
+        let report = self.verify_report();
+        let timings = report.timings;
+        (report.into_result(), timings)
+    }
+
+    /// Behaves like [`MockProver::verify`], but returns a [`VerificationReport`] that
+    /// also describes how verification went: how many rows were checked, how many
+    /// failures each phase found, and how long each phase took.
+    ///
+    /// This gives tooling a single structured result to render.
+    pub fn verify_report(&self) -> VerificationReport {
+        let mut failures = vec![];
+        let mut phase_failures = [
+            (VerifyPhase::Selectors, 0),
+            (VerifyPhase::Gates, 0),
+            (VerifyPhase::Lookups, 0),
+            (VerifyPhase::Permutations, 0),
+        ];
+        let mut timings = VerifyTimings::default();
+
+        for check in Check::ALL {
+            let start = Instant::now();
+            let mut found: Vec<_> = self
+                .check_failures(check, self.usable_rows.clone(), self.usable_rows.clone())
+                .collect();
+            dedup_poisoned(&mut found);
+            *timings.phase_mut(check.phase()) += start.elapsed();
+
+            phase_failures[check.phase() as usize].1 += found.len();
+            failures.extend(found);
+        }
+
+        VerificationReport {
+            failures,
+            rows_checked: self.n as usize,
+            usable_rows: self.usable_rows.len(),
+            phase_failures,
+            timings,
+            strict: self.strict,
+        }
+    }
+
+    /// Behaves like [`MockProver::verify`], but reports its progress to `cb` as it goes.
+    ///
+    /// This lets tools show a progress bar while verifying very large circuits. To keep
+    /// the overhead low, gates and lookups are checked in chunks of roughly 1% of the
+    /// usable rows, and `cb` is called after each chunk; every phase reports a fraction
+    /// of 1 when it finishes. The same failures are found as by `verify`, but they may
+    /// be returned in a different order.
+    pub fn verify_with_progress(
+        &self,
+        cb: impl Fn(VerifyProgress),
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let report = |phase, fraction| cb(VerifyProgress { phase, fraction });
+        let mut errors = vec![];
+
+        let (first, last) = (self.usable_rows.start, self.usable_rows.end);
+        let chunk_size = (self.usable_rows.len() / 100).max(1);
+        let chunks = move || {
+            (first..last).step_by(chunk_size).map(move |start| {
+                let end = (start + chunk_size).min(last);
+                (start..end, (end - first) as f64 / (last - first) as f64)
+            })
+        };
+
+        for (i, check) in Check::ALL.into_iter().enumerate() {
+            match check {
+                Check::Gates => {
+                    for (chunk, fraction) in chunks() {
+                        errors.extend(self.gate_failures(chunk));
+                        report(VerifyPhase::Gates, fraction);
+                    }
+                    errors.extend(self.gate_failures(self.unusable_rows()));
+                }
+                Check::Lookups => {
+                    // Build each lookup's table once, rather than once per chunk.
+                    let lookup_tables = self.lookup_tables();
+                    for (chunk, fraction) in chunks() {
+                        for (lookup_index, (fill_row, table)) in lookup_tables.iter().enumerate() {
+                            errors.extend(chunk.clone().filter_map(|row| {
+                                self.lookup_row_failure(lookup_index, fill_row, table, row)
+                            }));
+                        }
+                        report(VerifyPhase::Lookups, fraction);
+                    }
+                }
+                Check::Permutation => {
+                    let columns = self.permutation.mapping.len();
+                    for (column, values) in self.permutation.mapping.iter().enumerate() {
+                        errors.extend(
+                            (0..values.len())
+                                .filter_map(|row| self.permutation_failure(column, row)),
+                        );
+                        report(
+                            VerifyPhase::Permutations,
+                            (column + 1) as f64 / columns as f64,
+                        );
+                    }
+                }
+                _ => errors.extend(self.check_failures(
+                    check,
+                    self.usable_rows.clone(),
+                    self.usable_rows.clone(),
+                )),
+            }
+
+            // A phase is finished once its last check has run.
+            if Check::ALL
+                .get(i + 1)
+                .map_or(true, |next| next.phase() != check.phase())
+            {
+                report(check.phase(), 1.0);
+            }
+        }
+
+        failures_to_result(errors)
+    }
+
+    /// Panics if any of the given row ids is outside the usable rows.
+    fn check_row_ids<I: Iterator<Item = usize>>(&self, gate_row_ids: I, lookup_input_row_ids: I) {
+        // check all the row ids are valid
+        for row_id in gate_row_ids {
+            if !self.usable_rows.contains(&row_id) {
+                panic!("invalid gate row id {}", row_id)
+            }
+        }
+        for row_id in lookup_input_row_ids {
+            if !self.usable_rows.contains(&row_id) {
+                panic!("invalid lookup row id {}", row_id)
+            }
+        }
+    }
+
+    /// Checks that within each region, all cells used in instantiated gates have been
+    /// assigned to.
+    fn selector_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        let n = self.n as i32;
+
+        self.regions.iter().enumerate().flat_map(move |(r_i, r)| {
+            r.enabled_selectors
+                .iter()
+                // Complex selectors can be combined arbitrarily with other expressions
+                // (for example `q * a + (1 - q) * b`), so enabling one doesn't imply that
+                // every cell queried by the gates using it must be assigned. Simple
+                // selectors always multiply the entire constraint, so we only apply this
+                // check to them.
+                .filter(|(selector, _)| selector.is_simple())
+                .flat_map(move |(selector, at)| {
+                    // Find the gates enabled by this selector
+                    self.cs
+                        .gates
+                        .iter()
+                        // Assume that if a queried selector is enabled, the user wants to use the
+                        // corresponding gate in some way.
+                        //
+                        // TODO: This will trip up on the reverse case, where leaving a selector
+                        // un-enabled keeps a gate enabled. We could alternatively require that
+                        // every selector is explicitly enabled or disabled on every row? But that
+                        // seems messy and confusing.
+                        .enumerate()
+                        .filter(move |(_, g)| g.queried_selectors().contains(selector))
+                        .flat_map(move |(gate_index, gate)| {
+                            at.iter().flat_map(move |selector_row| {
+                                // Selectors are queried with no rotation.
+                                let gate_row = *selector_row as i32;
+
+                                gate.queried_cells().iter().filter_map(move |cell| {
+                                    // Determine where this cell should have been assigned.
+                                    let cell_row = ((gate_row + n + cell.rotation.0) % n) as usize;
+
+                                    // Check that it was assigned! Instance cells are
+                                    // provided by the verifier, so are always available.
+                                    if cell.column.column_type() == &Any::Instance
+                                        || r.cells.contains_key(&(cell.column, cell_row))
+                                    {
+                                        None
+                                    } else {
+                                        Some(VerifyFailure::CellNotAssigned {
+                                            gate: (gate_index, gate.name()).into(),
+                                            region: (r_i, r.name.clone()).into(),
+                                            gate_offset: *selector_row,
+                                            column: cell.column,
+                                            offset: cell_row as isize - r.rows.unwrap().0 as isize,
+                                        })
+                                    }
+                                })
+                            })
+                        })
+                })
+        })
+    }
+
+    /// In the stricter verification mode, checks that within each region, all advice
+    /// cells read by the inputs of enabled lookups have been assigned to.
+    ///
+    /// A lookup is considered enabled on the rows where any selector queried by its
+    /// inputs is enabled.
+    fn lookup_cell_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        let n = self.n as i32;
+        let strict = self.strict;
+
+        self.regions
+            .iter()
+            .enumerate()
+            .filter(move |_| strict)
+            .flat_map(move |(r_i, r)| {
+                r.enabled_selectors.iter().flat_map(move |(selector, at)| {
+                    self.cs
+                        .lookups
+                        .iter()
+                        .enumerate()
+                        .filter(move |(lookup_index, _)| {
+                            self.lookup_selectors[*lookup_index].contains(selector)
+                        })
+                        .flat_map(move |(lookup_index, lookup)| {
+                            let cells = self.lookup_advice_cells(lookup_index);
+                            at.iter().flat_map(move |selector_row| {
+                                // Selectors are queried with no rotation.
+                                let lookup_row = *selector_row as i32;
+
+                                cells
+                                    .clone()
+                                    .into_iter()
+                                    .filter_map(move |(column, rotation)| {
+                                        let cell_row = ((lookup_row + n + rotation) % n) as usize;
+
+                                        if r.cells.contains_key(&(column, cell_row)) {
+                                            None
+                                        } else {
+                                            Some(VerifyFailure::LookupCellNotAssigned {
+                                                name: lookup.name,
+                                                lookup_index,
+                                                region: (r_i, r.name.clone()).into(),
+                                                lookup_offset: *selector_row,
+                                                column,
+                                                offset: cell_row as isize
+                                                    - r.rows.unwrap().0 as isize,
+                                            })
+                                        }
+                                    })
+                            })
+                        })
+                })
+            })
+    }
+
+    /// In the stricter verification mode, checks that every cell read at a non-zero
+    /// rotation by a gate enabled by a complex selector was assigned by some region,
+    /// along with the checks of [`MockProver::wrapped_rotation_failures`] and
+    /// [`MockProver::conflicting_assignment_failures`].
+    ///
+    /// This catches gates that read a neighbouring row which the witness generator
+    /// never fills in, such as a `Rotation::prev()` query of a column that is only
+    /// assigned on every other row. Failures are found lazily, but the set of assigned
+    /// cells is collected up front.
+    fn rotated_cell_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        self.strict.then(|| self).into_iter().flat_map(|prover| {
+            prover
+                .unassigned_rotated_cell_failures()
+                .chain(prover.wrapped_rotation_failures())
+                .chain(prover.conflicting_assignment_failures())
+        })
+    }
+
+    /// Checks that every cell read at a non-zero rotation by a gate enabled by a
+    /// complex selector was assigned by some region.
+    fn unassigned_rotated_cell_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        let n = self.n as i32;
+        let assigned: Rc<HashSet<(Column<Any>, usize)>> = Rc::new(
+            self.regions
+                .iter()
+                .flat_map(|r| r.cells.keys().copied())
+                .collect(),
+        );
+
+        self.regions.iter().enumerate().flat_map(move |(r_i, r)| {
+            let assigned = assigned.clone();
+            r.enabled_selectors
+                .iter()
+                // Simple selectors are already covered by `selector_failures`.
+                .filter(|(selector, _)| !selector.is_simple())
+                .flat_map(move |(selector, at)| {
+                    let assigned = assigned.clone();
+                    self.cs
+                        .gates
+                        .iter()
+                        .enumerate()
+                        .filter(move |(_, gate)| gate.queried_selectors().contains(selector))
+                        .flat_map(move |(gate_index, gate)| {
+                            let assigned = assigned.clone();
+                            at.iter().flat_map(move |selector_row| {
+                                let assigned = assigned.clone();
+                                gate.queried_cells()
+                                    .iter()
+                                    .filter(|cell| {
+                                        cell.rotation.0 != 0
+                                            && cell.column.column_type() != &Any::Instance
+                                    })
+                                    .filter_map(move |cell| {
+                                        let cell_row =
+                                            ((*selector_row as i32 + n + cell.rotation.0) % n)
+                                                as usize;
+                                        if assigned.contains(&(cell.column, cell_row)) {
+                                            None
+                                        } else {
+                                            Some(VerifyFailure::RotatedCellNotAssigned {
+                                                gate: (gate_index, gate.name()).into(),
+                                                region: (r_i, r.name.clone()).into(),
+                                                gate_offset: *selector_row,
+                                                column: cell.column,
+                                                rotation: cell.rotation.0,
+                                            })
+                                        }
+                                    })
+                            })
+                        })
+                })
+        })
+    }
+
+    /// In the stricter verification mode, reports every cell that a later region
+    /// assigned a different value than an earlier region did, unless the cell was
+    /// allowed with [`MockProver::allow_shared_cell`].
+    fn conflicting_assignment_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        self.conflicting_assignments
+            .iter()
+            .filter(|(column, row, _, _)| !self.shared_cells.contains(&(*column, *row)))
+            .map(
+                |(column, row, earlier, later)| VerifyFailure::ConflictingAssignment {
+                    column: *column,
+                    row: *row,
+                    earlier_region: (*earlier, self.regions[*earlier].name.clone()).into(),
+                    region: (*later, self.regions[*later].name.clone()).into(),
+                },
+            )
+    }
+
+    /// In the stricter verification mode, checks that no constraint of a gate enabled
+    /// by a selector is satisfied only because of a cell that it reads at a rotation
+    /// which wraps around the end of the circuit.
+    ///
+    /// A wrapped cell is blamed if adding one to its value would make a satisfied
+    /// constraint fail.
+    fn wrapped_rotation_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        let n = self.n as i32;
+
+        self.regions.iter().enumerate().flat_map(move |(r_i, r)| {
+            self.cs
+                .gates
+                .iter()
+                .enumerate()
+                .flat_map(move |(gate_index, gate)| {
+                    let mut rows: Vec<usize> = gate
+                        .queried_selectors()
+                        .iter()
+                        .filter_map(|selector| r.enabled_selectors.get(selector))
+                        .flatten()
+                        .copied()
+                        .collect();
+                    rows.sort_unstable();
+                    rows.dedup();
+
+                    rows.into_iter().flat_map(move |row| {
+                        let wrapped: BTreeSet<(Column<Any>, i32)> = gate
+                            .queried_cells()
+                            .iter()
+                            .filter(|cell| !(0..n).contains(&(row as i32 + cell.rotation.0)))
+                            .map(|cell| (cell.column, cell.rotation.0))
+                            .collect();
+                        let polys: &[Expression<F>] = if wrapped.is_empty() {
+                            &[]
+                        } else {
+                            gate.polynomials()
+                        };
+
+                        polys
+                            .iter()
+                            .enumerate()
+                            .filter(move |(_, poly)| {
+                                self.evaluate_at(poly, row) == Value::Real(F::zero())
+                            })
+                            .flat_map(move |(poly_index, poly)| {
+                                wrapped
+                                    .clone()
+                                    .into_iter()
+                                    .filter(move |(column, rotation)| {
+                                        self.evaluate_perturbed(
+                                            poly,
+                                            row,
+                                            Some((*column, *rotation)),
+                                        ) != Value::Real(F::zero())
+                                    })
+                                    .map(move |(column, rotation)| VerifyFailure::WrappedRotation {
+                                        constraint: (
+                                            (gate_index, gate.name()).into(),
+                                            poly_index,
+                                            gate.constraint_name(poly_index),
+                                        )
+                                            .into(),
+                                        region: (r_i, r.name.clone()).into(),
+                                        gate_offset: row,
+                                        column,
+                                        rotation,
+                                    })
+                            })
+                    })
+                })
+        })
+    }
+
+    /// Returns the distinct advice cells read by the inputs of the given lookup, as
+    /// columns and rotations.
+    fn lookup_advice_cells(&self, lookup_index: usize) -> Vec<(Column<Any>, i32)> {
+        let cells: BTreeSet<_> = self.cs.lookups[lookup_index]
+            .input_expressions
+            .iter()
+            .flat_map(|expression| {
+                expression.evaluate(
+                    &|_| vec![],
+                    &|_| vec![],
+                    &|_| vec![],
+                    &|query| vec![(self.advice_column(query.column_index), query.rotation.0)],
+                    &|_| vec![],
+                    &|_| vec![],
+                    &|a| a,
+                    &|mut a, mut b| {
+                        a.append(&mut b);
+                        a
+                    },
+                    &|mut a, mut b| {
+                        a.append(&mut b);
+                        a
+                    },
+                    &|a, _| a,
+                )
+            })
+            .collect();
+        cells.into_iter().collect()
+    }
+
+    /// Checks that all gates are satisfied at the given rows.
+    ///
+    /// When given an unusable row, this checks that no gate is active on it.
+    fn gate_failures<'a, I: Clone + Iterator<Item = usize> + 'a>(
+        &'a self,
+        gate_row_ids: I,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        self.selected_gate_failures(|_| true, gate_row_ids)
+    }
+
+    /// Checks that the gates for which `selected` returns `true` are satisfied at the
+    /// given rows.
+    fn selected_gate_failures<'a, I: Clone + Iterator<Item = usize> + 'a>(
+        &'a self,
+        selected: impl Fn(usize) -> bool + 'a,
+        gate_row_ids: I,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        (0..self.cs.gates.len())
+            .filter(move |gate_index| selected(*gate_index))
+            .flat_map(move |gate_index| self.single_gate_failures(gate_index, gate_row_ids.clone()))
+    }
+
+    /// Checks that the gate with the given index is satisfied at the given rows.
+    fn single_gate_failures<'a, I: Iterator<Item = usize> + 'a>(
+        &'a self,
+        gate_index: usize,
+        gate_row_ids: I,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        let n = self.n as i32;
+        let gate = &self.cs.gates[gate_index];
+
+        gate_row_ids.flat_map(move |row| {
+            let row = row as i32 + n;
+            gate.polynomials()
+                .iter()
+                .enumerate()
+                .filter_map(move |(poly_index, poly)| {
+                    match poly.evaluate_lazy(
+                        &|scalar| Value::Real(scalar),
+                        &|_| panic!("virtual selectors are removed during optimization"),
+                        &util::load(
+                            n,
+                            row,
+                            &self.cs.fixed_queries,
+                            &self.fixed,
+                            self.unassigned_default,
+                        ),
+                        &util::load(
+                            n,
+                            row,
+                            &self.cs.advice_queries,
+                            &self.advice,
+                            self.unassigned_default,
+                        ),
+                        &util::load_instance(n, row, &self.cs.instance_queries, &self.instance),
+                        &|challenge| Value::Real(self.challenges[challenge.index()]),
+                        &|a| -a,
+                        &|a, b| a + b,
+                        &|a, b| a * b,
+                        &|a, scalar| a * scalar,
+                        &Value::Real(F::zero()),
+                    ) {
+                        Value::Real(x) if x.is_zero_vartime() => None,
+                        Value::Real(_) => Some(VerifyFailure::ConstraintNotSatisfied {
+                            constraint: (
+                                (gate_index, gate.name()).into(),
+                                poly_index,
+                                gate.constraint_name(poly_index),
+                            )
+                                .into(),
+                            location: FailureLocation::find_expressions(
+                                &self.cs,
+                                &self.regions,
+                                (row - n) as usize,
+                                Some(poly).into_iter(),
+                            ),
+                            cell_values: util::cell_values(
+                                gate,
+                                poly,
+                                &util::load(
+                                    n,
+                                    row,
+                                    &self.cs.fixed_queries,
+                                    &self.fixed,
+                                    self.unassigned_default,
+                                ),
+                                &util::load(
+                                    n,
+                                    row,
+                                    &self.cs.advice_queries,
+                                    &self.advice,
+                                    self.unassigned_default,
+                                ),
+                                &util::load_instance(
+                                    n,
+                                    row,
+                                    &self.cs.instance_queries,
+                                    &self.instance,
+                                ),
+                                self.field_formatter,
+                            ),
+                        }),
+                        Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
+                            constraint: (
+                                (gate_index, gate.name()).into(),
+                                poly_index,
+                                gate.constraint_name(poly_index),
+                            )
+                                .into(),
+                        }),
+                    }
+                })
+        })
+    }
+
+    /// Evaluates `expression` at the given row of the witness.
+    fn evaluate_at(&self, expression: &Expression<F>, row: usize) -> Value<F> {
+        self.evaluate_perturbed(expression, row, None)
+    }
+
+    /// Evaluates `expression` at the given row of the witness, as if one were added to
+    /// the value of the `perturbed` cell, given as a column and rotation, if any.
+    fn evaluate_perturbed(
+        &self,
+        expression: &Expression<F>,
+        row: usize,
+        perturbed: Option<(Column<Any>, i32)>,
+    ) -> Value<F> {
+        let n = self.n as i32;
+        let perturb = |column: Column<Any>, rotation: i32, value: Value<F>| {
+            if perturbed == Some((column, rotation)) {
+                value + Value::Real(F::one())
+            } else {
+                value
+            }
+        };
+
+        expression.evaluate_lazy(
+            &|scalar| Value::Real(scalar),
+            // Selectors are only left in expressions before they are compressed.
+            &|selector| {
+                Value::Real(if self.selectors[selector.0][row] {
+                    F::one()
+                } else {
+                    F::zero()
+                })
+            },
+            &|query| {
+                let query = self.cs.fixed_queries[query.index];
+                let column_index = query.0.index();
+                let rotation = query.1 .0;
+                perturb(
+                    query.0.into(),
+                    rotation,
+                    self.fixed[column_index][(row as i32 + n + rotation) as usize % n as usize]
+                        .with_default(self.unassigned_default),
+                )
+            },
+            &|query| {
+                let query = self.cs.advice_queries[query.index];
+                let column_index = query.0.index();
+                let rotation = query.1 .0;
+                perturb(
+                    query.0.into(),
+                    rotation,
+                    self.advice[column_index][(row as i32 + n + rotation) as usize % n as usize]
+                        .with_default(self.unassigned_default),
+                )
+            },
+            &|query| {
+                let query = self.cs.instance_queries[query.index];
+                let column_index = query.0.index();
+                let rotation = query.1 .0;
+                perturb(
+                    query.0.into(),
+                    rotation,
+                    Value::Real(self.instance_value(
+                        column_index,
+                        (row as i32 + n + rotation) as usize % n as usize,
+                    )),
+                )
+            },
+            &|challenge| Value::Real(self.challenges[challenge.index()]),
+            &|a| -a,
+            &|a, b| a + b,
+            &|a, b| a * b,
+            &|a, scalar| a * scalar,
+            &Value::Real(F::zero()),
+        )
+    }
+
+    /// Returns the value of the given instance cell.
+    ///
+    /// Instance columns are normally padded with zeroes to `n` rows, but a
+    /// `MockProverBuilder` may leave them shorter, so rows past the end are treated as
+    /// zero.
+    fn instance_value(&self, column: usize, row: usize) -> F {
+        self.instance[column]
+            .get(row)
+            .copied()
+            .unwrap_or_else(F::zero)
+    }
+
+    /// Returns the "fill row" of the given lookup's table.
+    ///
+    /// We optimize on the basis that the table might have been filled so that the last
+    /// usable row now has the fill contents (it doesn't matter if there was no filling).
+    /// Note that this "fill row" necessarily exists in the table, and we use that fact to
+    /// slightly simplify the optimization: we're only trying to check that all input rows
+    /// are contained in the table, and so we can safely just drop input rows that
+    /// match the fill row.
+    fn lookup_fill_row(&self, lookup_index: usize) -> Vec<Value<F>> {
+        let lookup = &self.cs.lookups[lookup_index];
+
+        assert!(lookup.table_expressions.len() == lookup.input_expressions.len());
+        assert!(self.usable_rows.end > 0);
+
+        self.normalize_lookup_tuple(
+            lookup_index,
+            lookup
+                .table_expressions
+                .iter()
+                .map(|c| self.evaluate_at(c, self.usable_rows.end - 1))
+                .collect(),
+        )
+    }
+
+    /// Returns the sorted rows of the given lookup's table, excluding those that match
+    /// `fill_row`.
+    fn lookup_table(&self, lookup_index: usize, fill_row: &[Value<F>]) -> Vec<Vec<Value<F>>> {
+        let mut table = vec![];
+        self.fill_lookup_table(lookup_index, fill_row, &mut table);
+        table
+    }
+
+    /// Replaces the contents of `table` with the sorted rows of the given lookup's
+    /// table, excluding those that match `fill_row`, reusing the allocations of the rows
+    /// that `table` already holds.
+    fn fill_lookup_table(
+        &self,
+        lookup_index: usize,
+        fill_row: &[Value<F>],
+        table: &mut Vec<Vec<Value<F>>>,
+    ) {
+        let lookup = &self.cs.lookups[lookup_index];
+
+        // In the real prover, the lookup expressions are never enforced on
+        // unusable rows, due to the (1 - (l_last(X) + l_blind(X))) term.
+        let mut len = 0;
+        for table_row in self.usable_rows.clone() {
+            if len == table.len() {
+                table.push(Vec::with_capacity(lookup.table_expressions.len()));
+            }
+            let t = &mut table[len];
+            t.clear();
+            t.extend(
+                lookup
+                    .table_expressions
+                    .iter()
+                    .map(|c| self.evaluate_at(c, table_row)),
+            );
+            *t = self.normalize_lookup_tuple(lookup_index, std::mem::take(t));
+
+            if t.as_slice() != fill_row {
+                len += 1;
+            }
+        }
+        table.truncate(len);
+        table.sort_unstable();
+    }
+
+    /// Returns a key that identifies the given lookup's table, so that lookups sharing
+    /// a table only build it once.
+    ///
+    /// Normalized tables depend on the lookup, and are never shared.
+    fn lookup_table_identifier(&self, lookup_index: usize) -> (Vec<String>, Option<usize>) {
+        (
+            self.cs.lookups[lookup_index]
+                .table_expressions
+                .iter()
+                .map(Expression::identifier)
+                .collect(),
+            self.lookup_normalizers
+                .contains_key(&lookup_index)
+                .then_some(lookup_index),
+        )
+    }
+
+    /// Returns the values of the given lookup's inputs at `row`.
+    fn lookup_input(&self, lookup_index: usize, row: usize) -> Vec<Value<F>> {
+        self.normalize_lookup_tuple(
+            lookup_index,
+            self.cs.lookups[lookup_index]
+                .input_expressions
+                .iter()
+                .map(|c| self.evaluate_at(c, row))
+                .collect(),
+        )
+    }
+
+    /// Constructs the failure for a lookup input at `row` that is not in its table.
+    fn lookup_failure(&self, lookup_index: usize, row: usize) -> VerifyFailure {
+        let lookup = &self.cs.lookups[lookup_index];
+        VerifyFailure::Lookup {
+            name: lookup.name,
+            lookup_index,
+            location: FailureLocation::find_expressions(
+                &self.cs,
+                &self.regions,
+                row,
+                lookup.input_expressions.iter(),
+            ),
+        }
+    }
+
+    /// Returns the fill row and sorted table of every lookup, by lookup index.
+    fn lookup_tables(&self) -> Vec<(Vec<Value<F>>, Vec<Vec<Value<F>>>)> {
+        (0..self.cs.lookups.len())
+            .map(|lookup_index| {
+                let fill_row = self.lookup_fill_row(lookup_index);
+                let table = self.lookup_table(lookup_index, &fill_row);
+                (fill_row, table)
+            })
+            .collect()
+    }
+
+    /// Checks that the given lookup's input at `row` exists in `table`, which was built
+    /// from `fill_row` by [`MockProver::lookup_table`].
+    fn lookup_row_failure(
+        &self,
+        lookup_index: usize,
+        fill_row: &[Value<F>],
+        table: &[Vec<Value<F>>],
+        row: usize,
+    ) -> Option<VerifyFailure> {
+        let input = self.lookup_input(lookup_index, row);
+        if input != fill_row && table.binary_search(&input).is_err() {
+            Some(self.lookup_failure(lookup_index, row))
+        } else {
+            None
+        }
+    }
+
+    /// Checks that all lookup inputs at the given rows exist in their respective tables.
+    fn lookup_failures<'a, I: Clone + Iterator<Item = usize> + 'a>(
+        &'a self,
+        lookup_input_row_ids: I,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        self.selected_lookup_failures(|_| true, lookup_input_row_ids)
+    }
+
+    /// Checks that the inputs at the given rows of the lookups for which `selected`
+    /// returns `true` exist in their respective tables.
+    fn selected_lookup_failures<'a, I: Clone + Iterator<Item = usize> + 'a>(
+        &'a self,
+        selected: impl Fn(usize) -> bool + 'a,
+        lookup_input_row_ids: I,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        let mut cached_table = Rc::new(Vec::new());
+        let mut cached_table_identifier = (Vec::new(), None);
+        (0..self.cs.lookups.len())
+            .filter(move |lookup_index| selected(*lookup_index))
+            .flat_map(move |lookup_index| {
+                let fill_row = self.lookup_fill_row(lookup_index);
+
+                let table_identifier = self.lookup_table_identifier(lookup_index);
+                if table_identifier != cached_table_identifier {
+                    cached_table_identifier = table_identifier;
+                    cached_table = Rc::new(self.lookup_table(lookup_index, &fill_row));
+                }
+
+                let inputs = self.sorted_lookup_inputs(
+                    lookup_index,
+                    &fill_row,
+                    lookup_input_row_ids.clone(),
+                );
+                self.missing_lookup_inputs(lookup_index, inputs, cached_table.clone())
+            })
+    }
+
+    /// Returns the given lookup's inputs at the given rows, along with their rows, in
+    /// sorted order, excluding those that match `fill_row`.
+    fn sorted_lookup_inputs(
+        &self,
+        lookup_index: usize,
+        fill_row: &[Value<F>],
+        lookup_input_row_ids: impl Iterator<Item = usize>,
+    ) -> Vec<(Vec<Value<F>>, usize)> {
+        let mut inputs: Vec<(Vec<_>, usize)> = lookup_input_row_ids
+            .filter_map(|input_row| {
+                let t = self.lookup_input(lookup_index, input_row);
+
+                if t != fill_row {
+                    // Also keep track of the original input row, since we're going to sort.
+                    Some((t, input_row))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        inputs.sort_unstable();
+        inputs
+    }
+
+    /// Returns a failure for each of the given lookup's sorted `inputs` that is not in
+    /// the sorted `table`, by walking through both in step.
+    fn missing_lookup_inputs<'a>(
+        &'a self,
+        lookup_index: usize,
+        inputs: Vec<(Vec<Value<F>>, usize)>,
+        table: impl Deref<Target = Vec<Vec<Value<F>>>> + 'a,
+    ) -> impl Iterator<Item = VerifyFailure> + 'a {
+        let mut i = 0;
+        inputs.into_iter().filter_map(move |(input, input_row)| {
+            while i < table.len() && table[i] < input {
+                i += 1;
+            }
+            if i == table.len() || table[i] > input {
+                assert!(table.binary_search(&input).is_err());
+
+                Some(self.lookup_failure(lookup_index, input_row))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the original value of a cell involved in the permutation, where `column`
+    /// is the index of the column within the permutation argument.
+    fn permutation_cell(&self, column: usize, row: usize) -> CellValue<F> {
+        self.cs
+            .permutation
+            .get_columns()
+            .get(column)
+            .map(|c: &Column<Any>| match c.column_type() {
+                Any::Advice(_) => self.advice[c.index()][row],
+                Any::Fixed => self.fixed[c.index()][row],
+                Any::Instance => CellValue::Assigned(self.instance_value(c.index(), row)),
+            })
+            .unwrap()
+    }
+
+    /// Checks that the permutation preserves the original value of the given cell.
+    fn permutation_failure(&self, column: usize, row: usize) -> Option<VerifyFailure> {
+        self.permutation_mismatch(column, row, self.permutation.mapping[column][row])
+    }
+
+    /// Checks that the given cell holds the same value as `other`, a cell in the same
+    /// equality class.
+    fn permutation_mismatch(
+        &self,
+        column: usize,
+        row: usize,
+        other: (usize, usize),
+    ) -> Option<VerifyFailure> {
+        let original_cell = self.permutation_cell(column, row);
+        let permuted_cell = self.permutation_cell(other.0, other.1);
+        if original_cell == permuted_cell {
+            None
+        } else {
+            let columns = self.cs.permutation.get_columns();
+            let column = columns.get(column).unwrap();
+            Some(VerifyFailure::Permutation {
+                column: (*column).into(),
+                location: FailureLocation::find(
+                    &self.regions,
+                    row,
+                    Some(column).into_iter().cloned().collect(),
+                ),
+            })
+        }
+    }
+
+    /// If enabled, checks that every advice cell constrained to equal an instance cell
+    /// holds the value provided for that instance cell.
+    fn instance_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        let instance_columns: Vec<_> = self
+            .cs
+            .permutation
+            .get_columns()
+            .iter()
+            .enumerate()
+            .filter(|_| self.report_instance_mismatches)
+            .filter(|(_, c)| matches!(c.column_type(), Any::Instance))
+            .map(|(instance_index, _)| instance_index)
+            .collect();
+
+        instance_columns
+            .into_iter()
+            .flat_map(move |instance_index| {
+                self.usable_rows.clone().flat_map(move |instance_row| {
+                    self.instance_mismatches(instance_index, instance_row)
+                })
+            })
+    }
+
+    /// Returns the advice cells that are constrained to equal the given instance cell,
+    /// but hold a different value. `instance_index` is the index of the instance column
+    /// within the permutation argument.
+    fn instance_mismatches(
+        &self,
+        instance_index: usize,
+        instance_row: usize,
+    ) -> Vec<VerifyFailure> {
+        let start = (instance_index, instance_row);
+        let mut cell = self.permutation.mapping[start.0][start.1];
+        if cell == start {
+            // This cell isn't involved in any copy constraints.
+            return vec![];
+        }
+
+        let columns = self.cs.permutation.get_columns();
+        let instance_column = columns[instance_index];
+        let instance_value = self.instance_value(instance_column.index(), instance_row);
+
+        // Walk the cycle of cells that are constrained to equal this one.
+        let mut failures = vec![];
+        while cell != start {
+            let column = columns[cell.0];
+            if let Any::Advice(_) = column.column_type() {
+                let advice_value = self.advice[column.index()][cell.1];
+                if advice_value != CellValue::Assigned(instance_value) {
+                    failures.push(VerifyFailure::InstanceMismatch {
+                        column: column.into(),
+                        location: FailureLocation::find(
+                            &self.regions,
+                            cell.1,
+                            Some(column).into_iter().collect(),
+                        ),
+                        advice_value: match advice_value {
+                            CellValue::Assigned(v) => (self.field_formatter)(v),
+                            _ => "unassigned".to_string(),
+                        },
+                        instance_column: instance_column.into(),
+                        instance_row,
+                        instance_value: (self.field_formatter)(instance_value),
+                    });
+                }
+            }
+            cell = self.permutation.mapping[cell.0][cell.1];
+        }
+
+        failures
+    }
+
+    /// Checks that permutations preserve the original values of the cells.
+    fn permutation_failures(&self) -> impl Iterator<Item = VerifyFailure> + '_ {
+        // Iterate over each column of the permutation
+        self.permutation
+            .mapping
+            .iter()
+            .enumerate()
+            .flat_map(move |(column, values)| {
+                // Iterate over each row of the column to check that the cell's
+                // value is preserved by the mapping.
+                (0..values.len()).filter_map(move |row| self.permutation_failure(column, row))
+            })
+    }
+
+    /// Checks that the permutation preserves the original value of every fixed and
+    /// advice cell, skipping over instance cells.
+    ///
+    /// Each cell is compared with the next non-instance cell in its equality class, so
+    /// the fixed and advice cells in a class must still all be equal to each other.
+    fn witness_permutation_failures(&self) -> Vec<VerifyFailure> {
+        let is_instance: Vec<bool> = self
+            .cs
+            .permutation
+            .get_columns()
+            .iter()
+            .map(|column| column.column_type() == &Any::Instance)
+            .collect();
+
+        let mut failures = vec![];
+        for (column, values) in self.permutation.mapping.iter().enumerate() {
+            if is_instance[column] {
+                continue;
+            }
+            for (row, cell) in values.iter().enumerate() {
+                let mut cell = *cell;
+                while is_instance[cell.0] {
+                    cell = self.permutation.mapping[cell.0][cell.1];
+                }
+                failures.extend(self.permutation_mismatch(column, row, cell));
+            }
+        }
+        failures
+    }
+
+    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
+    /// the reasons that the circuit is not satisfied.
+    /// Constraints and lookup are checked at `usable_rows`, parallelly.
+    pub fn verify_par(&self) -> Result<(), Vec<VerifyFailure>> {
+        self.verify_at_rows_par(self.usable_rows.clone(), self.usable_rows.clone())
+    }
+
+    /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
+    /// the reasons that the circuit is not satisfied.
+    /// Constraints are only checked at `gate_row_ids`,
+    /// and lookup inputs are only checked at `lookup_input_row_ids`, parallelly.
+    pub fn verify_at_rows_par<I: Clone + Iterator<Item = usize>>(
+        &self,
+        gate_row_ids: I,
+        lookup_input_row_ids: I,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        let n = self.n as i32;
+
+        let gate_row_ids = gate_row_ids.collect::<Vec<_>>();
+        let lookup_input_row_ids = lookup_input_row_ids.collect::<Vec<_>>();
+
+        // check all the row ids are valid
+        gate_row_ids.par_iter().for_each(|row_id| {
+            if !self.usable_rows.contains(row_id) {
+                panic!("invalid gate row id {}", row_id);
+            }
+        });
+        lookup_input_row_ids.par_iter().for_each(|row_id| {
+            if !self.usable_rows.contains(row_id) {
+                panic!("invalid gate row id {}", row_id);
+            }
+        });
+
+        // Check that within each region, all cells used in instantiated gates have been
+        // assigned to.
+        let selector_errors = || {
+            self.regions
+                .iter()
+                .enumerate()
+                .flat_map(|(r_i, r)| {
+                    r.enabled_selectors
+                        .iter()
+                        // Complex selectors can be combined arbitrarily with other expressions
+                        // (for example `q * a + (1 - q) * b`), so enabling one doesn't imply that
+                        // every cell queried by the gates using it must be assigned. Simple
+                        // selectors always multiply the entire constraint, so we only apply this
+                        // check to them.
+                        .filter(|(selector, _)| selector.is_simple())
+                        .flat_map(move |(selector, at)| {
+                            // Find the gates enabled by this selector
+                            self.cs
+                                .gates
+                                .iter()
+                                // Assume that if a queried selector is enabled, the user wants to use the
+                                // corresponding gate in some way.
+                                //
+                                // TODO: This will trip up on the reverse case, where leaving a selector
+                                // un-enabled keeps a gate enabled. We could alternatively require that
+                                // every selector is explicitly enabled or disabled on every row? But that
+                                // seems messy and confusing.
+                                .enumerate()
+                                .filter(move |(_, g)| g.queried_selectors().contains(selector))
+                                .flat_map(move |(gate_index, gate)| {
+                                    at.par_iter()
+                                        .flat_map(move |selector_row| {
+                                            // Selectors are queried with no rotation.
+                                            let gate_row = *selector_row as i32;
+
+                                            gate.queried_cells()
+                                                .iter()
+                                                .filter_map(move |cell| {
+                                                    // Determine where this cell should have been assigned.
+                                                    let cell_row =
+                                                        ((gate_row + n + cell.rotation.0) % n)
+                                                            as usize;
+
+                                                    // Check that it was assigned! Instance cells are
+                                                    // provided by the verifier, so are always available.
+                                                    if cell.column.column_type() == &Any::Instance
+                                                        || r.cells
+                                                            .contains_key(&(cell.column, cell_row))
+                                                    {
+                                                        None
+                                                    } else {
+                                                        Some(VerifyFailure::CellNotAssigned {
+                                                            gate: (gate_index, gate.name()).into(),
+                                                            region: (r_i, r.name.clone()).into(),
+                                                            gate_offset: *selector_row,
+                                                            column: cell.column,
+                                                            offset: cell_row as isize
+                                                                - r.rows.unwrap().0 as isize,
+                                                        })
+                                                    }
+                                                })
+                                                .collect::<Vec<_>>()
+                                        })
+                                        .collect::<Vec<_>>()
+                                })
+                        })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Check that all gates are satisfied for all rows.
+        let gate_errors = || {
+            self.cs
+                .gates
+                .iter()
+                .enumerate()
+                .flat_map(|(gate_index, gate)| {
+                    let blinding_rows =
+                        (self.n as usize - (self.cs.blinding_factors() + 1))..(self.n as usize);
+                    (gate_row_ids
+                        .clone()
+                        .into_par_iter()
+                        .chain(blinding_rows.into_par_iter()))
+                    .flat_map(move |row| {
+                        let row = row as i32 + n;
+                        gate.polynomials()
+                            .iter()
+                            .enumerate()
+                            .filter_map(move |(poly_index, poly)| {
+                                match poly.evaluate_lazy(
+                                    &|scalar| Value::Real(scalar),
+                                    &|_| {
+                                        panic!("virtual selectors are removed during optimization")
+                                    },
+                                    &util::load(
+                                        n,
+                                        row,
+                                        &self.cs.fixed_queries,
+                                        &self.fixed,
+                                        self.unassigned_default,
+                                    ),
+                                    &util::load(
+                                        n,
+                                        row,
+                                        &self.cs.advice_queries,
+                                        &self.advice,
+                                        self.unassigned_default,
+                                    ),
+                                    &util::load_instance(
+                                        n,
+                                        row,
+                                        &self.cs.instance_queries,
+                                        &self.instance,
+                                    ),
+                                    &|challenge| Value::Real(self.challenges[challenge.index()]),
+                                    &|a| -a,
+                                    &|a, b| a + b,
+                                    &|a, b| a * b,
+                                    &|a, scalar| a * scalar,
+                                    &Value::Real(F::zero()),
+                                ) {
+                                    Value::Real(x) if x.is_zero_vartime() => None,
+                                    Value::Real(_) => Some(VerifyFailure::ConstraintNotSatisfied {
+                                        constraint: (
+                                            (gate_index, gate.name()).into(),
+                                            poly_index,
+                                            gate.constraint_name(poly_index),
+                                        )
+                                            .into(),
+                                        location: FailureLocation::find_expressions(
+                                            &self.cs,
+                                            &self.regions,
+                                            (row - n) as usize,
+                                            Some(poly).into_iter(),
+                                        ),
+                                        cell_values: util::cell_values(
+                                            gate,
+                                            poly,
+                                            &util::load(
+                                                n,
+                                                row,
+                                                &self.cs.fixed_queries,
+                                                &self.fixed,
+                                                self.unassigned_default,
+                                            ),
+                                            &util::load(
+                                                n,
+                                                row,
+                                                &self.cs.advice_queries,
+                                                &self.advice,
+                                                self.unassigned_default,
+                                            ),
+                                            &util::load_instance(
+                                                n,
+                                                row,
+                                                &self.cs.instance_queries,
+                                                &self.instance,
+                                            ),
+                                            self.field_formatter,
+                                        ),
+                                    }),
+                                    Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
+                                        constraint: (
+                                            (gate_index, gate.name()).into(),
+                                            poly_index,
+                                            gate.constraint_name(poly_index),
+                                        )
+                                            .into(),
+                                    }),
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Check that all lookups exist in their respective tables.
+        let lookup_errors = || {
+            let mut cached_table = Vec::new();
+            let mut cached_table_identifier = (Vec::new(), None);
+            self.cs
+                .lookups
+                .iter()
+                .enumerate()
+                .flat_map(|(lookup_index, lookup)| {
+                    let load = |expression: &Expression<F>, row| {
+                        expression.evaluate_lazy(
+                            &|scalar| Value::Real(scalar),
+                            &|_| panic!("virtual selectors are removed during optimization"),
+                            &|query| {
+                                self.fixed[query.column_index]
+                                    [(row as i32 + n + query.rotation.0) as usize % n as usize]
+                                    .with_default(self.unassigned_default)
+                            },
+                            &|query| {
+                                self.advice[query.column_index]
+                                    [(row as i32 + n + query.rotation.0) as usize % n as usize]
+                                    .with_default(self.unassigned_default)
+                            },
+                            &|query| {
+                                Value::Real(self.instance_value(
+                                    query.column_index,
+                                    (row as i32 + n + query.rotation.0) as usize % n as usize,
+                                ))
+                            },
+                            &|challenge| Value::Real(self.challenges[challenge.index()]),
+                            &|a| -a,
+                            &|a, b| a + b,
+                            &|a, b| a * b,
+                            &|a, scalar| a * scalar,
+                            &Value::Real(F::zero()),
+                        )
+                    };
+
+                    assert!(lookup.table_expressions.len() == lookup.input_expressions.len());
+                    assert!(self.usable_rows.end > 0);
+
+                    // We optimize on the basis that the table might have been filled so that the last
+                    // usable row now has the fill contents (it doesn't matter if there was no filling).
+                    // Note that this "fill row" necessarily exists in the table, and we use that fact to
+                    // slightly simplify the optimization: we're only trying to check that all input rows
+                    // are contained in the table, and so we can safely just drop input rows that
+                    // match the fill row.
+                    let fill_row = self.normalize_lookup_tuple(
+                        lookup_index,
+                        lookup
+                            .table_expressions
+                            .iter()
+                            .map(move |c| load(c, self.usable_rows.end - 1))
+                            .collect(),
+                    );
+
+                    let table_identifier = self.lookup_table_identifier(lookup_index);
+                    if table_identifier != cached_table_identifier {
+                        cached_table_identifier = table_identifier;
+
+                        // In the real prover, the lookup expressions are never enforced on
+                        // unusable rows, due to the (1 - (l_last(X) + l_blind(X))) term.
+                        cached_table = self
+                            .usable_rows
+                            .clone()
+                            .into_par_iter()
+                            .filter_map(|table_row| {
+                                let t = self.normalize_lookup_tuple(
+                                    lookup_index,
+                                    lookup
+                                        .table_expressions
+                                        .iter()
+                                        .map(move |c| load(c, table_row))
+                                        .collect(),
+                                );
+
+                                if t != fill_row {
+                                    Some(t)
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect();
+                        cached_table.par_sort_unstable();
+                    }
+                    let table = &cached_table;
+
+                    let mut inputs: Vec<(Vec<_>, usize)> = lookup_input_row_ids
+                        .clone()
+                        .into_par_iter()
+                        .filter_map(|input_row| {
+                            let t = self.normalize_lookup_tuple(
+                                lookup_index,
+                                lookup
+                                    .input_expressions
+                                    .iter()
+                                    .map(move |c| load(c, input_row))
+                                    .collect(),
+                            );
+
+                            if t != fill_row {
+                                // Also keep track of the original input row, since we're going to sort.
+                                Some((t, input_row))
+                            } else {
+                                None
+                            }
+                        })
+                        .collect();
+                    inputs.par_sort_unstable();
+
+                    inputs
+                        .par_iter()
+                        .filter_map(move |(input, input_row)| {
+                            if table.binary_search(input).is_err() {
+                                Some(VerifyFailure::Lookup {
+                                    name: lookup.name,
+                                    lookup_index,
+                                    location: FailureLocation::find_expressions(
+                                        &self.cs,
+                                        &self.regions,
+                                        *input_row,
+                                        lookup.input_expressions.iter(),
+                                    ),
+                                })
+                            } else {
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Check that permutations preserve the original values of the cells.
+        let perm_errors = || {
+            // Original values of columns involved in the permutation.
+            let original = |column, row| {
+                self.cs
+                    .permutation
+                    .get_columns()
+                    .get(column)
+                    .map(|c: &Column<Any>| match c.column_type() {
+                        Any::Advice(_) => self.advice[c.index()][row],
+                        Any::Fixed => self.fixed[c.index()][row],
+                        Any::Instance => CellValue::Assigned(self.instance_value(c.index(), row)),
+                    })
+                    .unwrap()
+            };
+
+            // Iterate over each column of the permutation
+            self.permutation
+                .mapping
+                .iter()
+                .enumerate()
+                .flat_map(move |(column, values)| {
+                    // Iterate over each row of the column to check that the cell's
+                    // value is preserved by the mapping.
+                    values
+                        .par_iter()
+                        .enumerate()
+                        .filter_map(move |(row, cell)| {
+                            let original_cell = original(column, row);
+                            let permuted_cell = original(cell.0, cell.1);
+                            if original_cell == permuted_cell {
+                                None
+                            } else {
+                                let columns = self.cs.permutation.get_columns();
+                                let column = columns.get(column).unwrap();
+                                Some(VerifyFailure::Permutation {
+                                    column: (*column).into(),
+                                    location: FailureLocation::find(
+                                        &self.regions,
+                                        row,
+                                        Some(column).into_iter().cloned().collect(),
+                                    ),
+                                })
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut errors = vec![];
+        for check in Check::ALL {
+            match check {
+                Check::Selectors => errors.extend(selector_errors()),
+                Check::Gates => errors.extend(gate_errors()),
+                Check::Lookups => errors.extend(lookup_errors()),
+                Check::Permutation => errors.extend(perm_errors()),
+                _ => errors.extend(self.check_failures(
+                    check,
+                    gate_row_ids.iter().copied(),
+                    lookup_input_row_ids.iter().copied(),
+                )),
+            }
+        }
+        failures_to_result(errors)
+    }
+
+    /// Returns the instance columns that `failures` can be blamed on, if every one of
+    /// them involves an instance column.
+    ///
+    /// A constraint failure involves the instance columns its gate queries, a lookup
+    /// failure those its input and table expressions query, and a permutation failure
+    /// the instance column it occurred in. When every failure involves some instance
+    /// column, the public inputs passed to the `MockProver` are a more likely culprit
+    /// than the witness. Returns `None` if `failures` is empty, or if any failure doesn't
+    /// involve an instance column.
+    pub fn instance_blame(&self, failures: &[VerifyFailure]) -> Option<BTreeSet<usize>> {
+        let instance_queries = |expressions: &[Expression<F>]| -> BTreeSet<usize> {
+            expressions
+                .iter()
+                .flat_map(|expression| {
+                    expression.evaluate(
+                        &|_| vec![],
+                        &|_| vec![],
+                        &|_| vec![],
+                        &|_| vec![],
+                        &|query| vec![query.column_index],
+                        &|_| vec![],
+                        &|a| a,
+                        &|mut a, mut b| {
+                            a.append(&mut b);
+                            a
+                        },
+                        &|mut a, mut b| {
+                            a.append(&mut b);
+                            a
+                        },
+                        &|a, _| a,
+                    )
+                })
+                .collect()
+        };
+
+        let mut blamed = BTreeSet::new();
+        for failure in failures {
+            let columns: BTreeSet<usize> = match failure {
+                VerifyFailure::ConstraintNotSatisfied { cell_values, .. } => cell_values
+                    .iter()
+                    .filter(|(cell, _)| cell.column.column_type == Any::Instance)
+                    .map(|(cell, _)| cell.column.index)
+                    .collect(),
+                VerifyFailure::Lookup { lookup_index, .. } => {
+                    let lookup = &self.cs.lookups[*lookup_index];
+                    let mut columns = instance_queries(&lookup.input_expressions);
+                    columns.extend(instance_queries(&lookup.table_expressions));
+                    columns
+                }
+                VerifyFailure::Permutation { column, .. }
+                    if column.column_type == Any::Instance =>
+                {
+                    iter::once(column.index).collect()
+                }
+                VerifyFailure::InstanceMismatch {
+                    instance_column, ..
+                } => iter::once(instance_column.index).collect(),
+                _ => BTreeSet::new(),
+            };
+            if columns.is_empty() {
+                return None;
+            }
+            blamed.extend(columns);
+        }
+
+        if blamed.is_empty() {
+            None
+        } else {
+            Some(blamed)
+        }
+    }
+
+    /// Panics if the circuit being checked by this `MockProver` is not satisfied.
+    ///
+    /// Any verification failures will be pretty-printed to stderr before the function
+    /// panics. If every failure involves an instance column (see
+    /// [`MockProver::instance_blame`]), a hint pointing at those columns is printed too.
+    ///
+    /// Apart from the stderr output, this method is equivalent to:
+    /// ```ignore
+    /// assert_eq!(prover.verify(), Ok(()));
+    /// ```
+    pub fn assert_satisfied(&self) {
+        if let Err(errs) = self.verify() {
+            for err in &errs {
+                err.emit(self);
+                eprintln!();
+            }
+            if let Some(columns) = self.instance_blame(&errs) {
+                eprintln!(
+                    "Hint: every failure involves instance column(s) {:?}; check the public inputs.",
+                    columns
+                );
+                eprintln!();
+            }
+            panic!("circuit was not satisfied");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2curves::pasta::Fp;
+
+    use super::{
+        failure::queried_column_types, AssignedCellInfo, FailureLocation, MockProver,
+        MockProverError, ScanOrder, VerificationError, VerifyFailure,
+    };
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{
+            Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Error, Expression, Fixed,
+            SecondPhase, Selector, TableColumn,
+        },
+        poly::Rotation,
+    };
+
+    /// The number of entries in the table of [`RangeCircuit`].
+    const RANGE: u64 = 8;
+
+    #[derive(Clone)]
+    struct RangeConfig {
+        a: Column<Advice>,
+        q: Selector,
+        table: TableColumn,
+    }
+
+    /// A circuit that looks up each of `values` in a table holding `0..RANGE`.
+    struct RangeCircuit {
+        values: Vec<u64>,
+    }
+
+    impl Circuit<Fp> for RangeCircuit {
+        type Config = RangeConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let q = meta.complex_selector();
+            let table = meta.lookup_table_column();
+
+            meta.lookup("range", |cells| {
+                let a = cells.query_advice(a, Rotation::cur());
+                let q = cells.query_selector(q);
+
+                // When q is not enabled, this looks up 0 instead.
+                vec![(q * a, table)]
+            });
+
+            RangeConfig { a, q, table }
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self { values: vec![] }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "Range table",
+                |mut table| {
+                    for i in 0..RANGE {
+                        table.assign_cell(
+                            || format!("table[{}]", i),
+                            config.table,
+                            i as usize,
+                            || Value::known(Fp::from(i)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+
+            layouter.assign_region(
+                || "Values",
+                |mut region| {
+                    for (offset, value) in self.values.iter().enumerate() {
+                        config.q.enable(&mut region, offset)?;
+                        region.assign_advice(
+                            || format!("a[{}]", offset),
+                            config.a,
+                            offset,
+                            || Value::known(Fp::from(*value)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[derive(Clone)]
+    struct PinnedConfig {
+        a: Column<Advice>,
+        f: Column<Fixed>,
+        q: Selector,
+    }
+
+    /// A circuit that assigns `values` to both an advice and a fixed column, and
+    /// constrains them to be equal on every row if `constrained` is set.
+    struct PinnedCircuit {
+        values: Vec<u64>,
+        constrained: bool,
+    }
+
+    impl Circuit<Fp> for PinnedCircuit {
+        type Config = PinnedConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let f = meta.fixed_column();
+            let q = meta.selector();
+
+            meta.create_gate("Pinned", |cells| {
+                let a = cells.query_advice(a, Rotation::cur());
+                let f = cells.query_fixed(f, Rotation::cur());
+                let q = cells.query_selector(q);
+
+                vec![q * (a - f)]
+            });
+
+            PinnedConfig { a, f, q }
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                values: self.values.clone(),
+                constrained: self.constrained,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "Pinned values",
+                |mut region| {
+                    for (offset, value) in self.values.iter().enumerate() {
+                        if self.constrained {
+                            config.q.enable(&mut region, offset)?;
+                        }
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            offset,
+                            || Value::known(Fp::from(*value)),
+                        )?;
+                        region.assign_fixed(
+                            || "f",
+                            config.f,
+                            offset,
+                            || Value::known(Fp::from(*value)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[derive(Clone)]
+    struct NextConfig {
+        a: Column<Advice>,
+        f: Column<Fixed>,
+        q: Selector,
+    }
+
+    /// A circuit whose gate checks that the advice cell on the row after it is enabled
+    /// equals the fixed cell on that row. It assigns one region, in which the gate is
+    /// enabled at offset 0, `fixed` is assigned at offset 0 and `next` at offset 1.
+    struct NextCircuit {
+        next: u64,
+        fixed: u64,
+    }
+
+    impl Circuit<Fp> for NextCircuit {
+        type Config = NextConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let f = meta.fixed_column();
+            let q = meta.selector();
+
+            meta.create_gate("Next equals fixed", |cells| {
+                let a_next = cells.query_advice(a, Rotation::next());
+                let f = cells.query_fixed(f, Rotation::cur());
+                let q = cells.query_selector(q);
+
+                vec![q * (a_next - f)]
+            });
+
+            NextConfig { a, f, q }
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                next: self.next,
+                fixed: self.fixed,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "Region",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_fixed(
+                        || "f",
+                        config.f,
+                        0,
+                        || Value::known(Fp::from(self.fixed)),
+                    )?;
+                    region.assign_advice(
+                        || "a",
+                        config.a,
+                        1,
+                        || Value::known(Fp::from(self.next)),
+                    )?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn unassigned_cell() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct FaultyCircuitConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        struct FaultyCircuit {}
+
+        impl Circuit<Fp> for FaultyCircuit {
+            type Config = FaultyCircuitConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::prev());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    // If q is enabled, a and b must be assigned to.
+                    vec![q * (a - b)]
+                });
+
+                FaultyCircuitConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Faulty synthesis",
+                    |mut region| {
+                        // Enable the equality gate.
+                        config.q.enable(&mut region, 1)?;
+
+                        // Assign a = 0.
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))?;
+
+                        // BUG: Forget to assign b = 0! This could go unnoticed during
+                        // development, because cell values default to zero, which in this
+                        // case is fine, but for other assignments would be broken.
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &FaultyCircuit {}, vec![]).unwrap();
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::CellNotAssigned {
+                gate: (0, "Equality check").into(),
+                region: (0, "Faulty synthesis".to_owned()).into(),
+                gate_offset: 1,
+                column: Column::new(1, Any::advice()),
+                offset: 1,
+            }])
+        );
+    }
+
+    #[test]
+    fn bad_lookup() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct FaultyCircuitConfig {
+            a: Column<Advice>,
+            q: Selector,
+            table: TableColumn,
+        }
+
+        struct FaultyCircuit {}
+
+        impl Circuit<Fp> for FaultyCircuit {
+            type Config = FaultyCircuitConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q = meta.complex_selector();
+                let table = meta.lookup_table_column();
+
+                meta.lookup("lookup", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    // If q is enabled, a must be in the table.
+                    // When q is not enabled, lookup the default value instead.
+                    let not_q = Expression::Constant(Fp::one()) - q.clone();
+                    let default = Expression::Constant(Fp::from(2));
+                    vec![(q * a + not_q * default, table)]
+                });
+
+                FaultyCircuitConfig { a, q, table }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "Doubling table",
+                    |mut table| {
+                        (1..(1 << (K - 1)))
+                            .map(|i| {
+                                table.assign_cell(
+                                    || format!("table[{}] = {}", i, 2 * i),
+                                    config.table,
+                                    i - 1,
+                                    || Value::known(Fp::from(2 * i as u64)),
+                                )
+                            })
+                            .fold(Ok(()), |acc, res| acc.and(res))
+                    },
+                )?;
+
+                layouter.assign_region(
+                    || "Good synthesis",
+                    |mut region| {
+                        // Enable the lookup on rows 0 and 1.
+                        config.q.enable(&mut region, 0)?;
+                        config.q.enable(&mut region, 1)?;
+
+                        // Assign a = 2 and a = 6.
+                        region.assign_advice(
+                            || "a = 2",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(2)),
+                        )?;
+                        region.assign_advice(
+                            || "a = 6",
+                            config.a,
+                            1,
+                            || Value::known(Fp::from(6)),
+                        )?;
+
+                        Ok(())
+                    },
+                )?;
+
+                layouter.assign_region(
+                    || "Faulty synthesis",
+                    |mut region| {
+                        // Enable the lookup on rows 0 and 1.
+                        config.q.enable(&mut region, 0)?;
+                        config.q.enable(&mut region, 1)?;
+
+                        // Assign a = 4.
+                        region.assign_advice(
+                            || "a = 4",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(4)),
+                        )?;
+
+                        // BUG: Assign a = 5, which doesn't exist in the table!
+                        region.assign_advice(
+                            || "a = 5",
+                            config.a,
+                            1,
+                            || Value::known(Fp::from(5)),
+                        )?;
+
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &FaultyCircuit {}, vec![]).unwrap();
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::Lookup {
+                name: "lookup",
+                lookup_index: 0,
+                location: FailureLocation::InRegion {
+                    region: (2, "Faulty synthesis").into(),
+                    offset: 1,
+                }
+            }])
+        );
+    }
+
+    #[test]
+    fn unassigned_default_sentinel() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct EqualityConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct FaultyCircuit;
+
+        impl Circuit<Fp> for FaultyCircuit {
+            type Config = EqualityConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                EqualityConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Faulty synthesis",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        // BUG: `a` is never assigned, but the gate holds while it
+                        // defaults to zero.
+                        region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::zero()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &FaultyCircuit, vec![]).unwrap();
+        let is_unsatisfied = |failure: &VerifyFailure| {
+            matches!(failure, VerifyFailure::ConstraintNotSatisfied { .. })
+        };
+        assert!(!prover.verify().unwrap_err().iter().any(is_unsatisfied));
+
+        // With a sentinel default, the gate reading the unassigned cell fails.
+        prover.set_unassigned_default(Fp::from(0x5ca1ab1e));
+        assert!(prover.verify().unwrap_err().iter().any(is_unsatisfied));
+    }
+
+    #[test]
+    fn complex_selector_unassigned_cells() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SelectorConfig {
+            b: Column<Advice>,
+            simple: Selector,
+            complex: Selector,
+        }
+
+        /// A circuit with the same gate behind a simple and a complex selector, which
+        /// enables one of them without assigning the gate's `a` cell.
+        struct SelectorCircuit {
+            complex: bool,
+        }
+
+        impl Circuit<Fp> for SelectorCircuit {
+            type Config = SelectorConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let simple = meta.selector();
+                let complex = meta.complex_selector();
+                for (name, q) in [("Simple", simple), ("Complex", complex)] {
+                    meta.create_gate(name, |cells| {
+                        let a = cells.query_advice(a, Rotation::cur());
+                        let b = cells.query_advice(b, Rotation::cur());
+                        let q = cells.query_selector(q);
+
+                        vec![q * (a - b)]
+                    });
+                }
+
+                SelectorConfig { b, simple, complex }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    complex: self.complex,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        let q = if self.complex {
+                            config.complex
+                        } else {
+                            config.simple
+                        };
+                        q.enable(&mut region, 0)?;
+                        region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::zero()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = |complex| MockProver::run(K, &SelectorCircuit { complex }, vec![]).unwrap();
+
+        assert_eq!(
+            prover(false).verify(),
+            Err(vec![VerifyFailure::CellNotAssigned {
+                gate: (0, "Simple").into(),
+                region: (0, "Region".to_owned()).into(),
+                gate_offset: 0,
+                column: Column::new(0, Any::advice()),
+                offset: 0,
+            }])
+        );
+        assert_eq!(prover(true).verify(), Ok(()));
+    }
+
+    #[test]
+    fn lookup_queried_column_types() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let f = cs.fixed_column();
+        let i = cs.instance_column();
+        let table = cs.fixed_column();
+        cs.lookup_any("mixed", |cells| {
+            let a_cur = cells.query_advice(a, Rotation::cur());
+            let a_next = cells.query_advice(a, Rotation::next());
+            let f = cells.query_fixed(f, Rotation::cur());
+            let i = cells.query_instance(i, Rotation::cur());
+            let table = cells.query_fixed(table, Rotation::cur());
+
+            vec![
+                (a_cur + a_next * f, table.clone()),
+                (i, table.clone()),
+                (Expression::Constant(Fp::one()), table),
+            ]
+        });
+
+        let inputs = &cs.lookups[0].input_expressions;
+        assert_eq!(queried_column_types(&inputs[0]), "advice (2), fixed (1)");
+        assert_eq!(queried_column_types(&inputs[1]), "instance (1)");
+        assert_eq!(queried_column_types(&inputs[2]), "none (constant input)");
+    }
+
+    #[test]
+    fn verification_error() {
+        let failure = |row| VerifyFailure::Lookup {
+            name: "lookup",
+            lookup_index: 0,
+            location: FailureLocation::OutsideRegion { row },
+        };
+
+        let error = VerificationError::from(vec![failure(1), failure(2)]);
+        assert_eq!(error, VerificationError(vec![failure(1), failure(2)]));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "circuit was not satisfied (2 failures)\n- {}\n- {}",
+                failure(1),
+                failure(2)
+            )
+        );
+
+        // Both flow into generic error handling.
+        let verify = |result: Result<(), Vec<VerifyFailure>>| {
+            result.map_err(VerificationError::from)?;
+            Ok::<_, Box<dyn std::error::Error>>(())
+        };
+        assert!(verify(Ok(())).is_ok());
+        assert_eq!(
+            verify(Err(vec![failure(1)])).unwrap_err().to_string(),
+            format!("circuit was not satisfied (1 failures)\n- {}", failure(1))
+        );
+        let boxed: Box<dyn std::error::Error> = Box::new(failure(3));
+        assert_eq!(boxed.to_string(), failure(3).to_string());
+    }
+
+    #[test]
+    fn snapshot_restore() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct EqualityConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct EqualityCircuit {}
+
+        impl Circuit<Fp> for EqualityCircuit {
+            type Config = EqualityConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                EqualityConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Equal values",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
+                        region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::one()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &EqualityCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let snapshot = prover.snapshot();
+
+        // Break the witness by overwriting `a`.
+        prover
+            .assign_advice(
+                || "a",
+                Column::new(0, Advice::default()),
+                0,
+                || Value::known(Fp::from(2)),
+            )
+            .unwrap();
+        assert!(prover.verify().is_err());
+
+        // Rolling back restores the satisfying witness.
+        prover.restore(snapshot);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn not_enough_rows_with_permutation() {
+        #[derive(Clone)]
+        struct CopyConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        struct CopyCircuit {}
+
+        impl Circuit<Fp> for CopyCircuit {
+            type Config = CopyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                CopyConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Copy",
+                    |mut region| {
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(5)),
+                        )?;
+                        a.copy_advice(|| "b", &mut region, config.b, 0)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        CopyCircuit::configure(&mut cs);
+        let minimum_k = cs.minimum_rows().next_power_of_two().trailing_zeros();
+        // The rows reserved for blinding factors don't fit in a circuit with 2 rows.
+        assert!(minimum_k > 1);
+
+        let circuit = CopyCircuit {};
+        assert!(matches!(
+            MockProver::run_partial(1, &circuit, vec![]),
+            Err(MockProverError::NotEnoughRowsForPermutation { current_k: 1, minimum_k: k })
+                if k == minimum_k
+        ));
+        // `run` returns the closest `Error`.
+        assert!(matches!(
+            MockProver::run(1, &circuit, vec![]),
+            Err(Error::NotEnoughRowsAvailable { current_k: 1 })
+        ));
+
+        let prover = MockProver::run(minimum_k, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Without the permutation argument, the generic error is returned.
+        let circuit = PinnedCircuit {
+            values: vec![],
+            constrained: false,
+        };
+        assert!(matches!(
+            MockProver::run_partial(1, &circuit, vec![]),
+            Err(MockProverError::Plonk(Error::NotEnoughRowsAvailable {
+                current_k: 1
+            }))
+        ));
+    }
+
+    #[test]
+    fn cell_out_of_bounds() {
+        const K: u32 = 4;
+
+        /// Assigns a cell in a column from a different constraint system.
+        struct ForeignCircuit;
+
+        impl Circuit<Fp> for ForeignCircuit {
+            type Config = Column<Advice>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                meta.advice_column();
+                Column::new(1, Advice::default())
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        region.assign_advice(|| "a", config, 3, || Value::known(Fp::one()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let other = Column::<Advice>::new(1, Advice::default());
+        let (_, error) = MockProver::run_partial(K, &ForeignCircuit, vec![]).unwrap();
+        assert!(matches!(
+            error,
+            Some(MockProverError::CellOutOfBounds { column, row: 3 }) if column == other.into()
+        ));
+        // `run` returns the closest `Error`.
+        assert!(matches!(
+            MockProver::run(K, &ForeignCircuit, vec![]),
+            Err(Error::BoundsFailure)
+        ));
+    }
+
+    #[test]
+    fn builder() {
+        const K: u32 = 4;
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+        let q = cs.selector();
+        cs.create_gate("Equality check", |cells| {
+            let a = cells.query_advice(a, Rotation::cur());
+            let b = cells.query_advice(b, Rotation::cur());
+            let q = cells.query_selector(q);
+
+            vec![q * (a - b)]
+        });
+
+        let prover = MockProver::builder(K, cs.clone())
+            .unwrap()
+            .enable_selector(q, 0)
+            .unwrap()
+            .advice(a, 0, Fp::from(3))
+            .unwrap()
+            .advice(b, 0, Fp::from(3))
+            .unwrap()
+            .build();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = MockProver::builder(K, cs.clone())
+            .unwrap()
+            .region("Unequal values")
+            .enable_selector(q, 0)
+            .unwrap()
+            .advice(a, 0, Fp::from(3))
+            .unwrap()
+            .advice(b, 0, Fp::from(4))
+            .unwrap()
+            .build();
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::ConstraintNotSatisfied {
+                constraint: ((0, "Equality check").into(), 0, "").into(),
+                location: FailureLocation::InRegion {
+                    region: (0, "Unequal values").into(),
+                    offset: 0,
+                },
+                cell_values: vec![
+                    (((Any::advice(), 0).into(), 0).into(), "3".to_string()),
+                    (((Any::advice(), 1).into(), 0).into(), "4".to_string()),
+                ],
+            }])
+        );
+
+        // Regions are kept even if nothing is assigned in them.
+        let prover = MockProver::builder(K, cs)
+            .unwrap()
+            .region("Skipped")
+            .region("Equal values")
+            .advice(a, 0, Fp::from(3))
+            .unwrap()
+            .region("Also skipped")
+            .build();
+        assert_eq!(prover.empty_regions(), vec![0, 2]);
+        assert_eq!(prover.count_regions_named("Skipped"), 1);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn verify_bounded() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PoisonConfig {
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct PoisonCircuit {}
+
+        impl Circuit<Fp> for PoisonCircuit {
+            type Config = PoisonConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                // BUG: This gate has no selector, so it is active on every unusable row.
+                meta.create_gate("Boolean", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![a.clone() * (a - Expression::Constant(Fp::one()))]
+                });
+                meta.create_gate("Zero", |cells| {
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * b]
+                });
+
+                PoisonConfig { b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Nonzero",
+                    |mut region| {
+                        for offset in 0..3 {
+                            config.q.enable(&mut region, offset)?;
+                            region.assign_advice(
+                                || "b",
+                                config.b,
+                                offset,
+                                || Value::known(Fp::one()),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &PoisonCircuit {}, vec![]).unwrap();
+        // The "Boolean" gate is poisoned on every unusable row, but only reported once.
+        assert!(prover.unusable_rows().len() > 1);
+        let failures = prover.verify().unwrap_err();
+        assert_eq!(failures.len(), 4);
+        assert!(matches!(
+            failures[0],
+            VerifyFailure::ConstraintPoisoned { .. }
+        ));
+
+        // Repeated poisoned failures don't count towards the cap.
+        let (result, truncated) = prover.verify_bounded(2);
+        let bounded = result.unwrap_err();
+        assert_eq!(bounded.len(), 2);
+        assert_eq!(bounded[..], failures[..2]);
+        assert!(truncated);
+
+        let (result, truncated) = prover.verify_bounded(4);
+        assert_eq!(result, Err(failures));
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn iter_failures() {
+        const K: u32 = 5;
+
+        let circuit = RangeCircuit {
+            values: vec![RANGE, 1, RANGE + 1, RANGE + 2],
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert_eq!(failures.len(), 3);
+
+        // The same failures are found as by `verify`, and can be taken one at a time.
+        assert_eq!(prover.iter_failures().collect::<Vec<_>>(), failures);
+        assert_eq!(prover.iter_failures().next().as_ref(), failures.first());
+    }
+
+    #[test]
+    fn first_failure() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct EqualityConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit that checks that the two values of each of `pairs` are equal, one
+        /// pair per row.
+        struct PairsCircuit {
+            pairs: Vec<(u64, u64)>,
+        }
+
+        impl Circuit<Fp> for PairsCircuit {
+            type Config = EqualityConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                EqualityConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    pairs: self.pairs.clone(),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Rows",
+                    |mut region| {
+                        for (row, (a, b)) in self.pairs.iter().enumerate() {
+                            config.q.enable(&mut region, row)?;
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                row,
+                                || Value::known(Fp::from(*a)),
+                            )?;
+                            region.assign_advice(
+                                || "b",
+                                config.b,
+                                row,
+                                || Value::known(Fp::from(*b)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // BUG: b differs from a on rows 1 and 3.
+        let circuit = PairsCircuit {
+            pairs: vec![(0, 0), (1, 7), (2, 2), (3, 7)],
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+
+        assert_eq!(prover.verify().unwrap_err().len(), 2);
+        assert!(matches!(
+            prover.first_failure(),
+            Some(VerifyFailure::ConstraintNotSatisfied {
+                location: FailureLocation::InRegion { offset: 1, .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn first_failure_strict() {
+        const K: u32 = 4;
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+
+        // The second region reassigns both cells with different values. A floor planner
+        // never lays out overlapping regions, so this needs the builder.
+        let mut prover = MockProver::builder(K, cs)
+            .unwrap()
+            .region("first")
+            .advice(a, 0, Fp::from(1))
+            .unwrap()
+            .advice(a, 1, Fp::from(1))
+            .unwrap()
+            .region("second")
+            .advice(a, 0, Fp::from(2))
+            .unwrap()
+            .advice(a, 1, Fp::from(2))
+            .unwrap()
+            .build();
+        assert_eq!(prover.first_failure(), None);
+
+        prover.set_strict(true);
+        assert_eq!(prover.verify().unwrap_err().len(), 2);
+        assert_eq!(
+            prover.first_failure(),
+            Some(VerifyFailure::ConflictingAssignment {
+                column: a.into(),
+                row: 0,
+                earlier_region: (0, "first").into(),
+                region: (1, "second").into(),
+            })
+        );
+
+        prover.set_scan_order(ScanOrder::Reverse);
+        assert!(matches!(
+            prover.first_failure(),
+            Some(VerifyFailure::ConflictingAssignment { row: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn row_gaps() {
+        const K: u32 = 5;
+
+        #[derive(Clone)]
+        struct GapsConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        struct GapsCircuit;
+
+        impl Circuit<Fp> for GapsCircuit {
+            type Config = GapsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                GapsConfig {
+                    a: meta.advice_column(),
+                    b: meta.advice_column(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let regions = [
+                    ("First", config.a, &[0, 1][..]),
+                    ("Second", config.a, &[2][..]),
+                    ("Third", config.b, &[3, 6][..]),
+                    ("Fourth", config.a, &[5][..]),
+                ];
+                for (name, column, offsets) in regions {
+                    layouter.assign_region(
+                        || name,
+                        |mut region| {
+                            for offset in offsets {
+                                region.assign_advice(
+                                    || "cell",
+                                    column,
+                                    *offset,
+                                    || Value::known(Fp::one()),
+                                )?;
+                            }
+                            Ok(())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        // The regions occupy rows 0..=1, 4..=4, 3..=6 and 10..=10, since a region only
+        // occupies the rows from its first assigned cell to its last one. That leaves
+        // rows 2 and 7..=9 unoccupied.
+        let prover = MockProver::run(K, &GapsCircuit, vec![]).unwrap();
+        prover.assert_region_rows(1, 4..5);
+        prover.assert_region_rows(2, 3..7);
+        assert_eq!(prover.row_gaps(), vec![2..3, 7..10]);
+    }
+
+    #[test]
+    fn instance_mismatch() {
+        use crate::plonk::Instance;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct OutputConfig {
+            a: Column<Advice>,
+            i: Column<Instance>,
+        }
+
+        /// A circuit that assigns `5` to an advice cell, and exposes it as its public
+        /// output.
+        struct OutputCircuit;
+
+        impl Circuit<Fp> for OutputCircuit {
+            type Config = OutputConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                meta.enable_equality(a);
+                meta.enable_equality(i);
+
+                OutputConfig { a, i }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let cell = layouter.assign_region(
+                    || "Output",
+                    |mut region| {
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(5)))
+                    },
+                )?;
+                layouter.constrain_instance(cell.cell(), config.i, 0)
+            }
+        }
+
+        // BUG: The claimed public output doesn't match the witness.
+        let mut prover = MockProver::run(K, &OutputCircuit, vec![vec![Fp::from(6)]]).unwrap();
+
+        let is_mismatch = |e: &VerifyFailure| matches!(e, VerifyFailure::InstanceMismatch { .. });
+        let errors = prover.verify().unwrap_err();
+        assert!(!errors.is_empty());
+        assert!(!errors.iter().any(is_mismatch));
+
+        prover.set_report_instance_mismatches(true);
+        let errors = prover.verify().unwrap_err();
+        assert_eq!(
+            errors.into_iter().filter(is_mismatch).collect::<Vec<_>>(),
+            vec![VerifyFailure::InstanceMismatch {
+                column: (Any::advice(), 0).into(),
+                location: FailureLocation::InRegion {
+                    region: (0, "Output").into(),
+                    offset: 0,
+                },
+                advice_value: "5".to_string(),
+                instance_column: (Any::Instance, 0).into(),
+                instance_row: 0,
+                instance_value: "6".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn floating_selectors() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![1, 2],
+            constrained: true,
+        };
+        let mut prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(prover.floating_selectors(), vec![]);
+
+        // Enabling a selector outside of any region is recorded rather than panicking.
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = PinnedCircuit::configure(&mut cs);
+        prover.enable_selector(|| "", &config.q, 3).unwrap();
+        assert_eq!(prover.floating_selectors(), vec![(config.q, 3)]);
+    }
+
+    #[test]
+    fn instance_rotation_past_end() {
+        const K: u32 = 4;
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let i = cs.instance_column();
+        let q = cs.selector();
+        cs.create_gate("Next instance", |cells| {
+            let a = cells.query_advice(a, Rotation::cur());
+            let i = cells.query_instance(i, Rotation::next());
+            let q = cells.query_selector(q);
+
+            vec![q * (a - i)]
+        });
+
+        // `MockProver::run` pads the instance columns to `n` rows, but the builder
+        // doesn't, so the gate on the last usable row queries an instance cell past the
+        // end of the (empty) instance column.
+        let last_row = (1 << K) - cs.blinding_factors() - 2;
+        let prover = MockProver::builder(K, cs)
+            .unwrap()
+            .enable_selector(q, last_row)
+            .unwrap()
+            .advice(a, last_row, Fp::zero())
+            .unwrap()
+            .build();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.verify_par(), Ok(()));
+    }
+
+    #[test]
+    fn is_copied() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ChainConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            c: Column<Advice>,
+        }
+
+        struct ChainCircuit;
+
+        impl Circuit<Fp> for ChainCircuit {
+            type Config = ChainConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let c = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                ChainConfig { a, b, c }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Chain",
+                    |mut region| {
+                        // a[0] == b[1] == a[2]
+                        let value = Value::known(Fp::one());
+                        let cell = region.assign_advice(|| "a", config.a, 0, || value)?;
+                        let cell = cell.copy_advice(|| "b", &mut region, config.b, 1)?;
+                        cell.copy_advice(|| "a", &mut region, config.a, 2)?;
+                        region.assign_advice(|| "c", config.c, 0, || value)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &ChainCircuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let ChainConfig { a, b, c } = ChainCircuit::configure(&mut cs);
+
+        assert!(prover.is_copied((a.into(), 0), (b.into(), 1)));
+        assert!(prover.is_copied((a.into(), 2), (a.into(), 0)));
+        assert!(!prover.is_copied((a.into(), 0), (b.into(), 0)));
+        // Columns without equality enabled are never copied.
+        assert!(!prover.is_copied((a.into(), 0), (c.into(), 0)));
+    }
+
+    #[test]
+    fn check_against_trace() {
+        const K: u32 = 4;
+
+        let prover = |values| {
+            let circuit = PinnedCircuit {
+                values,
+                constrained: true,
+            };
+            MockProver::run(K, &circuit, vec![]).unwrap()
+        };
+
+        let mut trace = vec![];
+        prover(vec![3, 4]).export_trace(&mut trace).unwrap();
+        assert_eq!(prover(vec![3, 4]).check_against_trace(&trace[..]), Ok(()));
+
+        // The changed value is assigned to both an advice and a fixed cell.
+        let diffs = prover(vec![3, 5])
+            .check_against_trace(&trace[..])
+            .unwrap_err();
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].column, (Any::advice(), 0).into());
+        assert_eq!(diffs[0].row, 1);
+        assert_eq!(diffs[0].expected, Some(format!("{:?}", Fp::from(4))));
+        assert_eq!(diffs[0].actual, Some(format!("{:?}", Fp::from(5))));
+        assert_eq!(diffs[1].column, (Any::Fixed, 0).into());
+        assert_eq!(diffs[1].row, 1);
+    }
+
+    #[test]
+    fn lookup_defaults() {
+        const K: u32 = 4;
+
+        /// A circuit whose lookup table holds `offset..offset + 4`, and which doesn't
+        /// look anything up.
+        struct TableCircuit {
+            offset: u64,
+        }
+
+        impl Circuit<Fp> for TableCircuit {
+            type Config = RangeConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                RangeCircuit::configure(meta)
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    offset: self.offset,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "Table",
+                    |mut table| {
+                        for i in 0..4 {
+                            table.assign_cell(
+                                || "table",
+                                config.table,
+                                i as usize,
+                                || Value::known(Fp::from(i + self.offset)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let build = |offset| MockProver::run(K, &TableCircuit { offset }, vec![]).unwrap();
+
+        // BUG: The table doesn't contain the zero that disabled inputs collapse to.
+        assert_eq!(
+            build(1).verify_lookup_defaults(),
+            Err(vec![VerifyFailure::LookupDefault {
+                name: "range",
+                lookup_index: 0,
+                default_values: vec!["0".to_string()],
+            }])
+        );
+        assert_eq!(build(0).verify_lookup_defaults(), Ok(()));
+    }
+
+    #[test]
+    fn strict_lookup_unassigned_cell() {
+        const K: u32 = 4;
+
+        /// A circuit that looks up `a` on two rows of a range check, but only assigns
+        /// it on the first.
+        struct ForgetfulCircuit;
+
+        impl Circuit<Fp> for ForgetfulCircuit {
+            type Config = RangeConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                RangeCircuit::configure(meta)
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "Range table",
+                    |mut table| {
+                        for i in 0..RANGE {
+                            table.assign_cell(
+                                || "table",
+                                config.table,
+                                i as usize,
+                                || Value::known(Fp::from(i)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Lookup",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        config.q.enable(&mut region, 1)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(3)))?;
+                        // BUG: a is never assigned at row 1, so the lookup reads zero.
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &ForgetfulCircuit, vec![]).unwrap();
+
+        // Zero is in the table, so this goes unnoticed by default.
+        assert_eq!(prover.verify(), Ok(()));
+
+        prover.set_strict(true);
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::LookupCellNotAssigned {
+                name: "range",
+                lookup_index: 0,
+                region: (1, "Lookup").into(),
+                lookup_offset: 1,
+                column: Column::new(0, Any::advice()),
+                offset: 1,
+            }])
+        );
+    }
+
+    #[test]
+    fn field_formatter() {
+        const K: u32 = 4;
+
+        let mut prover = MockProver::run(K, &NextCircuit { next: 7, fixed: 8 }, vec![]).unwrap();
+        let cell_values = |prover: &MockProver<Fp>| match &prover.verify().unwrap_err()[..] {
+            [VerifyFailure::ConstraintNotSatisfied { cell_values, .. }] => cell_values
+                .iter()
+                .map(|(_, value)| value.clone())
+                .collect::<Vec<_>>(),
+            failures => panic!("unexpected failures: {:?}", failures),
+        };
+        assert_eq!(cell_values(&prover), vec!["7", "8"]);
+
+        prover.set_field_formatter(|value| format!("{:?}", value));
+        assert_eq!(
+            cell_values(&prover),
+            vec![format!("{:?}", Fp::from(7)), format!("{:?}", Fp::from(8))]
+        );
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![3],
+            constrained: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let PinnedConfig { a, .. } = PinnedCircuit::configure(&mut cs);
+
+        // Mutating a clone leaves the original witness untouched.
+        let mut scenario = prover.clone();
+        scenario
+            .assign_advice(|| "a", a, 0, || Value::known(Fp::from(4)))
+            .unwrap();
+        assert!(scenario.verify().is_err());
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn strict_rotated_cell() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SumsConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct SumsCircuit;
+
+        impl Circuit<Fp> for SumsCircuit {
+            type Config = SumsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.complex_selector();
+
+                meta.create_gate("Running sum", |cells| {
+                    let a_prev = cells.query_advice(a, Rotation::prev());
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a + a_prev - b)]
+                });
+
+                SumsConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Sums",
+                    |mut region| {
+                        // BUG: a is only assigned on odd rows, so `a_prev` always reads
+                        // zero.
+                        for row in [1, 3] {
+                            let value = Value::known(Fp::from(row as u64));
+                            config.q.enable(&mut region, row)?;
+                            region.assign_advice(|| "a", config.a, row, || value)?;
+                            region.assign_advice(|| "b", config.b, row, || value)?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &SumsCircuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        prover.set_strict(true);
+        let failure = |gate_offset| VerifyFailure::RotatedCellNotAssigned {
+            gate: (0, "Running sum").into(),
+            region: (0, "Sums").into(),
+            gate_offset,
+            column: Column::new(0, Any::advice()),
+            rotation: -1,
+        };
+        assert_eq!(prover.verify(), Err(vec![failure(1), failure(3)]));
+    }
+
+    #[test]
+    fn region_cells() {
+        const K: u32 = 4;
+
+        let prover = MockProver::run(K, &NextCircuit { next: 7, fixed: 7 }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Cells are ordered by column, and advice columns come first.
+        let cells = prover.region_cells(0);
+        assert_eq!(
+            cells,
+            vec![
+                AssignedCellInfo {
+                    column: Column::new(0, Any::advice()),
+                    row: 1,
+                    value: Some(Fp::from(7)),
+                    region: Some(0),
+                },
+                AssignedCellInfo {
+                    column: Column::new(0, Any::Fixed),
+                    row: 0,
+                    value: Some(Fp::from(7)),
+                    region: Some(0),
+                },
+            ]
+        );
+        assert_eq!(cells[0].to_string(), "advice[0] row 1 = 7");
+        assert_eq!(cells[1].to_string(), "fixed[0] row 0 = 7");
+    }
+
+    #[test]
+    fn gate_inputs() {
+        const K: u32 = 4;
+
+        let prover = MockProver::run(K, &NextCircuit { next: 7, fixed: 8 }, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The cells are given in the order that the gate queries them, so the advice
+        // cell on the next row comes first.
+        let advice = Column::new(0, Any::advice());
+        let fixed = Column::new(0, Any::Fixed);
+        assert_eq!(
+            prover.gate_inputs(0, 0),
+            vec![
+                AssignedCellInfo {
+                    column: advice,
+                    row: 1,
+                    value: Some(Fp::from(7)),
+                    region: Some(0),
+                },
+                AssignedCellInfo {
+                    column: fixed,
+                    row: 0,
+                    value: Some(Fp::from(8)),
+                    region: Some(0),
+                },
+            ]
+        );
+
+        // Rotations wrap around the end of the circuit.
+        let last_row = (1 << K) - 1;
+        assert_eq!(
+            prover.gate_inputs(0, last_row),
+            vec![
+                AssignedCellInfo {
+                    column: advice,
+                    row: 0,
+                    value: None,
+                    region: None,
+                },
+                AssignedCellInfo {
+                    column: fixed,
+                    row: last_row,
+                    value: None,
+                    region: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn assert_fixed_loaded() {
+        const K: u32 = 4;
+
+        let mut cs = ConstraintSystem::default();
+        let config = NextCircuit::configure(&mut cs);
+        let prover = MockProver::run(K, &NextCircuit { next: 7, fixed: 7 }, vec![]).unwrap();
+
+        assert_eq!(prover.assert_fixed_loaded(config.f, &[]), Ok(()));
+        assert_eq!(prover.assert_fixed_loaded(config.f, &[Fp::from(7)]), Ok(()));
+        assert_eq!(
+            prover.assert_fixed_loaded(config.f, &[Fp::from(8)]),
+            Err(AssignedCellInfo {
+                column: config.f.into(),
+                row: 0,
+                value: Some(Fp::from(7)),
+                region: Some(0),
+            })
+        );
+
+        // An unassigned cell never matches, not even zero.
+        assert_eq!(
+            prover.assert_fixed_loaded(config.f, &[Fp::from(7), Fp::zero()]),
+            Err(AssignedCellInfo {
+                column: config.f.into(),
+                row: 1,
+                value: None,
+                region: None,
+            })
+        );
+    }
+
+    #[test]
+    fn gates_on_unusable_rows() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct UnselectedConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        struct UnselectedCircuit;
+
+        impl Circuit<Fp> for UnselectedCircuit {
+            type Config = UnselectedConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+
+                // BUG: This gate has no selector, so it is active on the blinding rows.
+                meta.create_gate("Unselected", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+
+                    vec![a - b]
+                });
+
+                UnselectedConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Equal values",
+                    |mut region| {
+                        let value = Value::known(Fp::one());
+                        region.assign_advice(|| "a", config.a, 0, || value)?;
+                        region.assign_advice(|| "b", config.b, 0, || value)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // The real prover enforces gates on every row, including those reserved for
+        // blinding factors, while lookups and copy constraints are only enforced on
+        // usable rows. A gate that is active on the blinding rows can't be satisfied by
+        // the random values there, so `verify` must report it.
+        let selected = PinnedCircuit {
+            values: vec![1, 2],
+            constrained: true,
+        };
+        let prover = MockProver::run(K, &selected, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = MockProver::run(K, &UnselectedCircuit, vec![]).unwrap();
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::ConstraintPoisoned {
+                constraint: ((0, "Unselected").into(), 0, "").into(),
+            }])
+        );
+    }
+
+    #[test]
+    fn explain_row() {
+        const K: u32 = 4;
+
+        /// A [`NextCircuit`] whose gate fails because the advice cell it checks is
+        /// copied from the one before it.
+        struct CopiedNextCircuit;
+
+        impl Circuit<Fp> for CopiedNextCircuit {
+            type Config = NextConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let config = NextCircuit::configure(meta);
+                meta.enable_equality(config.a);
+                config
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_fixed(|| "f", config.f, 0, || Value::known(Fp::from(7)))?;
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(6)),
+                        )?;
+                        a.copy_advice(|| "a", &mut region, config.a, 1)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &CopiedNextCircuit, vec![]).unwrap();
+
+        let report = prover.explain_row(0);
+        assert!(report.contains("In region 0 ('Region') at offset 0"));
+        assert!(report.contains("Enabled selectors: [0]"));
+        assert!(report.contains("Gate 0 ('Next equals fixed')"));
+        assert!(report.contains("Constraint 0 ('') = -1"));
+        assert!(report.contains("Column('Fixed', 0) = 7"));
+        assert!(report.contains("= 6 is constrained to equal 1 other cell(s)"));
+
+        // The gate is not active on the next row.
+        let report = prover.explain_row(1);
+        assert!(report.contains("Enabled selectors: []"));
+        assert!(!report.contains("Gate 0"));
+    }
+
+    #[test]
+    fn eager_copy_check() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct MismatchConfig {
+            a: Column<Advice>,
+        }
+
+        /// A circuit that constrains two cells holding different values to be equal.
+        struct MismatchCircuit;
+
+        impl Circuit<Fp> for MismatchCircuit {
+            type Config = MismatchConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                meta.enable_equality(a);
+
+                MismatchConfig { a }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        let left = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::one()),
+                        )?;
+                        let right = region.assign_advice(
+                            || "a",
+                            config.a,
+                            1,
+                            || Value::known(Fp::from(2)),
+                        )?;
+                        region.constrain_equal(left.cell(), right.cell())
+                    },
+                )
+            }
+        }
+
+        // By default, the mismatch is only reported by `verify`.
+        let prover = MockProver::run(K, &MismatchCircuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        let error = MockProver::run_with_copy_checks(K, &MismatchCircuit, vec![]).unwrap_err();
+        assert!(matches!(
+            error,
+            MockProverError::CopyMismatch {
+                left_row: 0,
+                right_row: 1,
+                ..
+            }
+        ));
+
+        // Cells that are not yet assigned can't be checked. A circuit can only copy
+        // cells it has assigned, so this needs the builder.
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let MismatchConfig { a } = MismatchCircuit::configure(&mut cs);
+        let builder = MockProver::builder(K, cs)
+            .unwrap()
+            .region("Region")
+            .advice(a, 0, Fp::from(1))
+            .unwrap();
+        assert!(builder.check_copies().copy(a, 0, a, 2).is_ok());
+    }
+
+    #[test]
+    fn verify_into_reuses_allocations() {
+        const K: u32 = 5;
+
+        let circuit = RangeCircuit {
+            values: vec![1, RANGE],
+        };
+        let mut prover = MockProver::run(K, &circuit, vec![]).unwrap();
+
+        let mut buf = Vec::with_capacity(4);
+        prover.verify_into(&mut buf);
+        assert_eq!(buf.len(), 1);
+        let table = prover.lookup_scratch.0.lock().unwrap()[0].as_ptr();
+
+        // The buffer is cleared on every call, and the lookup table is rebuilt in place.
+        prover.verify_into(&mut buf);
+        assert_eq!(buf.len(), 1);
+        assert_eq!(prover.lookup_scratch.0.lock().unwrap()[0].as_ptr(), table);
+        assert_eq!(Err(buf), prover.verify());
+
+        // The table is rebuilt from the current witness, so changes to it are seen.
+        let mut cs = ConstraintSystem::default();
+        let config = RangeCircuit::configure(&mut cs);
+        prover
+            .assign_fixed(
+                || "table[0]",
+                config.table.inner(),
+                0,
+                || Value::known(Fp::from(RANGE)),
+            )
+            .unwrap();
+        let mut buf = vec![];
+        prover.verify_into(&mut buf);
+        assert!(buf.is_empty());
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn gate_term_degrees() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CubicConfig {
+            a: Column<Advice>,
+            f: Column<Fixed>,
+            q: Selector,
+        }
+
+        struct CubicCircuit;
+
+        impl Circuit<Fp> for CubicCircuit {
+            type Config = CubicConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let f = meta.fixed_column();
+                let q = meta.selector();
+
+                meta.create_gate("Cubic", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let f = cells.query_fixed(f, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q.clone() * (a.clone() * a.clone() * a.clone() - f), q * a]
+                });
+                meta.create_gate("Boolean", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![a.clone() * (a - Expression::Constant(Fp::one()))]
+                });
+
+                CubicConfig { a, f, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_fixed(|| "f", config.f, 0, || Value::known(Fp::zero()))?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &CubicCircuit, vec![]).unwrap();
+        // The selector counts towards the degree of the constraints it multiplies.
+        assert_eq!(
+            prover.gate_term_degrees(),
+            vec![(0, 0, 4), (0, 1, 2), (1, 0, 2)]
+        );
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn boolean_fixed_columns() {
+        const K: u32 = 4;
+
+        let boolean_columns = |values: Vec<u64>| {
+            let circuit = PinnedCircuit {
+                values,
+                constrained: true,
+            };
+            MockProver::run(K, &circuit, vec![])
+                .unwrap()
+                .boolean_fixed_columns()
+        };
+
+        // The fixed column that `q` is compressed into only holds zeros and ones too,
+        // but isn't reported.
+        assert_eq!(boolean_columns(vec![0, 1, 1]), vec![0]);
+        assert_eq!(boolean_columns(vec![0, 2]), vec![]);
+        // A column without any assigned cells isn't reported either.
+        assert_eq!(boolean_columns(vec![]), vec![]);
+    }
+
+    #[test]
+    fn assert_in_range() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![255, 256],
+            constrained: false,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = PinnedCircuit::configure(&mut cs);
+
+        assert_eq!(prover.assert_in_range(config.a, 9), Ok(()));
+        assert_eq!(
+            prover.assert_in_range(config.a, 8),
+            Err(AssignedCellInfo {
+                column: config.a.into(),
+                row: 1,
+                value: Some(Fp::from(256)),
+                region: Some(0),
+            })
+        );
+        assert!(prover.assert_in_range(config.a, 0).is_err());
+    }
+
+    #[test]
+    fn instance_blame() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PublicConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit that checks that its witness is a boolean equal to the public
+        /// input.
+        struct PublicCircuit {
+            witness: u64,
+        }
+
+        impl Circuit<Fp> for PublicCircuit {
+            type Config = PublicConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                let q = meta.selector();
+
+                meta.create_gate("Public", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let i = cells.query_instance(i, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - i)]
+                });
+                meta.create_gate("Boolean", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a.clone() * (a - Expression::Constant(Fp::one()))]
+                });
+
+                PublicConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { witness: 0 }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(self.witness)),
+                        )?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = |public: u64, witness: u64| {
+            MockProver::run(K, &PublicCircuit { witness }, vec![vec![Fp::from(public)]]).unwrap()
+        };
+
+        // Only the gate that reads the public input fails.
+        let wrong_instance = prover(0, 1);
+        let failures = wrong_instance.verify().unwrap_err();
+        assert_eq!(
+            wrong_instance.instance_blame(&failures),
+            Some(std::iter::once(0).collect())
+        );
+
+        let satisfied = prover(1, 1);
+        assert_eq!(satisfied.instance_blame(&[]), None);
+
+        // A failure that doesn't involve the instance column can't be blamed on it.
+        let bad_witness = prover(2, 2);
+        let failures = bad_witness.verify().unwrap_err();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(bad_witness.instance_blame(&failures), None);
+    }
+
+    #[cfg(feature = "dev-serde")]
+    #[test]
+    fn serialization_roundtrip() {
+        use crate::plonk::Instance;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PublicConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            i: Column<Instance>,
+            q: Selector,
+        }
+
+        struct PublicCircuit {
+            b: u64,
+        }
+
+        impl Circuit<Fp> for PublicCircuit {
+            type Config = PublicConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let i = meta.instance_column();
+                let q = meta.selector();
+                meta.enable_equality(a);
+                meta.enable_equality(i);
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                PublicConfig { a, b, i, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { b: 0 }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let a = layouter.assign_region(
+                    || "Equal values",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(
+                            || "b",
+                            config.b,
+                            0,
+                            || Value::known(Fp::from(self.b)),
+                        )?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))
+                    },
+                )?;
+                layouter.constrain_instance(a.cell(), config.i, 0)
+            }
+        }
+
+        let roundtrip = |prover: &MockProver<Fp>| {
+            let mut bytes = vec![];
+            prover.write(&mut bytes).unwrap();
+            MockProver::read::<_, PublicCircuit>(&mut &bytes[..]).unwrap()
+        };
+
+        let satisfied = MockProver::run(K, &PublicCircuit { b: 1 }, vec![vec![Fp::one()]]).unwrap();
+        assert_eq!(roundtrip(&satisfied).verify(), Ok(()));
+
+        let unsatisfied =
+            MockProver::run(K, &PublicCircuit { b: 2 }, vec![vec![Fp::from(3)]]).unwrap();
+        let restored = roundtrip(&unsatisfied);
+        assert!(unsatisfied.verify().is_err());
+        assert_eq!(restored.verify(), unsatisfied.verify());
+        assert_eq!(restored.regions.len(), unsatisfied.regions.len());
+        assert_eq!(restored.fixed, unsatisfied.fixed);
+
+        assert!(MockProver::<Fp>::read::<_, PublicCircuit>(&mut &b"garbage"[..]).is_err());
+    }
+
+    #[test]
+    fn run_with_challenges() {
+        const K: u32 = 4;
+
+        struct EmptyCircuit {}
+
+        impl Circuit<Fp> for EmptyCircuit {
+            type Config = ();
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                meta.advice_column();
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        assert!(MockProver::run_with_challenges(K, &EmptyCircuit {}, vec![], vec![]).is_ok());
+        assert!(matches!(
+            MockProver::run_with_challenges(K, &EmptyCircuit {}, vec![], vec![Fp::from(1)]),
+            Err(MockProverError::InvalidChallenges {
+                expected: 0,
+                provided: 1,
+            })
+        ));
+    }
+
+    #[test]
+    fn advice_phase() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PhaseConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct PhaseCircuit {
+            // Whether to compute the first-phase cell from the second-phase one.
+            early_read: bool,
+        }
+
+        impl Circuit<Fp> for PhaseCircuit {
+            type Config = PhaseConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column_in(SecondPhase);
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                PhaseConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    early_read: self.early_read,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Phases",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        let b = region.assign_advice(
+                            || "b",
+                            config.b,
+                            0,
+                            || Value::known(Fp::from(5)),
+                        )?;
+                        let a = if self.early_read {
+                            b.value().copied()
+                        } else {
+                            Value::known(Fp::from(5))
+                        };
+                        region.assign_advice(|| "a", config.a, 0, || a)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &PhaseCircuit { early_read: false }, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = PhaseCircuit::configure(&mut cs);
+        assert_eq!(prover.advice_phase(config.a), 0);
+        assert_eq!(prover.advice_phase(config.b), 1);
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The real prover doesn't know the value of `b` while it assigns `a`.
+        let circuit = PhaseCircuit { early_read: true };
+        assert!(matches!(
+            MockProver::run(K, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+        let (prover, error) = MockProver::run_partial(K, &circuit, vec![]).unwrap();
+        assert!(matches!(
+            error,
+            Some(MockProverError::ValueUnavailableInPhase { column, row: 0, phase: 0 })
+                if column == config.a.into()
+        ));
+        // The single-pass witness is still complete, and satisfies the circuit.
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn run_checked() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct EqualityConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct EqualityCircuit {
+            pairs: Vec<(u64, u64)>,
+        }
+
+        impl Circuit<Fp> for EqualityCircuit {
+            type Config = EqualityConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                EqualityConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { pairs: vec![] }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                for (a, b) in self.pairs.iter() {
+                    layouter.assign_region(
+                        || "Pair",
+                        |mut region| {
+                            config.q.enable(&mut region, 0)?;
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                0,
+                                || Value::known(Fp::from(*a)),
+                            )?;
+                            region.assign_advice(
+                                || "b",
+                                config.b,
+                                0,
+                                || Value::known(Fp::from(*b)),
+                            )?;
+                            Ok(())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let circuit = EqualityCircuit {
+            pairs: vec![(1, 1), (2, 2)],
+        };
+        let (prover, failure) = MockProver::run_checked(K, &circuit, vec![]).unwrap();
+        assert_eq!(failure, None);
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Synthesis stops at the first region that fails.
+        let circuit = EqualityCircuit {
+            pairs: vec![(1, 1), (2, 3), (4, 4)],
+        };
+        let (prover, failure) = MockProver::run_checked(K, &circuit, vec![]).unwrap();
+        assert_eq!(
+            failure,
+            Some(VerifyFailure::ConstraintNotSatisfied {
+                constraint: ((0, "Equality check").into(), 0, "").into(),
+                location: FailureLocation::InRegion {
+                    region: (1, "Pair").into(),
+                    offset: 0,
+                },
+                cell_values: vec![
+                    (((Any::advice(), 0).into(), 0).into(), "2".to_string()),
+                    (((Any::advice(), 1).into(), 0).into(), "3".to_string()),
+                ],
+            })
+        );
+        assert_eq!(prover.regions.len(), 2);
+    }
+
+    #[test]
+    fn resolve_queries() {
+        const K: u32 = 4;
+
+        let prover = MockProver::run(K, &NextCircuit { next: 7, fixed: 7 }, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = NextCircuit::configure(&mut cs);
+
+        assert_eq!(
+            prover.resolve_advice_query(0),
+            Some((config.a, Rotation::next()))
+        );
+        assert_eq!(prover.resolve_advice_query(1), None);
+        assert_eq!(
+            prover.resolve_fixed_query(0),
+            Some((config.f, Rotation::cur()))
+        );
+        // The selector was compressed into a new fixed column, which is queried too.
+        assert_eq!(
+            prover.resolve_fixed_query(1),
+            Some((Column::new(1, Fixed), Rotation::cur()))
+        );
+        assert_eq!(prover.resolve_fixed_query(2), None);
+        assert_eq!(prover.resolve_instance_query(0), None);
+    }
+
+    #[test]
+    fn trace_cell() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CopyConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        struct CopyCircuit;
+
+        impl Circuit<Fp> for CopyCircuit {
+            type Config = CopyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                CopyConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Copy",
+                    |mut region| {
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::zero()),
+                        )?;
+                        a.copy_advice(|| "b", &mut region, config.b, 1)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &CopyCircuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = CopyCircuit::configure(&mut cs);
+        let cell = |column: Column<Advice>, row, value| AssignedCellInfo {
+            column: column.into(),
+            row,
+            value,
+            region: value.map(|_| 0),
+        };
+
+        // The cell reads as zero because it was copied from a cell assigned zero.
+        let trace = prover.trace_cell(config.b.into(), 1);
+        assert_eq!(trace.cell, cell(config.b, 1, Some(Fp::zero())));
+        assert_eq!(trace.value, Fp::zero());
+        assert_eq!(trace.equal_to, vec![cell(config.a, 0, Some(Fp::zero()))]);
+        assert_eq!(
+            trace.to_string(),
+            "advice[1] row 1 = 0 (assigned)\n  equal to advice[0] row 0 = 0"
+        );
+
+        // This one reads as zero because it was never assigned.
+        let trace = prover.trace_cell(config.a.into(), 2);
+        assert_eq!(trace.cell, cell(config.a, 2, None));
+        assert!(trace.equal_to.is_empty());
+        assert_eq!(
+            trace.to_string(),
+            "advice[0] row 2 = unassigned, so it takes the default value 0"
+        );
+    }
+
+    #[test]
+    fn selector_overlaps() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ExclusiveConfig {
+            a: Column<Advice>,
+            q_zero: Selector,
+            q_one: Selector,
+        }
+
+        /// A circuit that constrains its cell to zero on `zero_rows`, and to one on
+        /// `one_rows`.
+        struct ExclusiveCircuit {
+            zero_rows: Vec<usize>,
+            one_rows: Vec<usize>,
+        }
+
+        impl Circuit<Fp> for ExclusiveCircuit {
+            type Config = ExclusiveConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q_zero = meta.selector();
+                let q_one = meta.selector();
+
+                meta.create_gate("Zero", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q_zero = cells.query_selector(q_zero);
+
+                    vec![q_zero * a]
+                });
+                meta.create_gate("One", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q_one = cells.query_selector(q_one);
+
+                    vec![q_one * (a - Expression::Constant(Fp::one()))]
+                });
+
+                ExclusiveConfig { a, q_zero, q_one }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    zero_rows: self.zero_rows.clone(),
+                    one_rows: self.one_rows.clone(),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Region",
+                    |mut region| {
+                        for row in self.zero_rows.iter() {
+                            config.q_zero.enable(&mut region, *row)?;
+                        }
+                        for row in self.one_rows.iter() {
+                            config.q_one.enable(&mut region, *row)?;
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                *row,
+                                || Value::known(Fp::one()),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = ExclusiveCircuit::configure(&mut cs);
+        let overlaps = |zero_rows, one_rows, selectors: &[Selector]| {
+            let circuit = ExclusiveCircuit {
+                zero_rows,
+                one_rows,
+            };
+            MockProver::run(K, &circuit, vec![])
+                .unwrap()
+                .selector_overlaps(selectors)
+        };
+        let both = [config.q_zero, config.q_one];
+
+        assert!(overlaps(vec![0, 1], vec![2, 3], &both).is_empty());
+        assert_eq!(overlaps(vec![0, 1], vec![1, 2], &both), vec![1]);
+        assert!(overlaps(vec![0, 1], vec![1, 2], &[config.q_one]).is_empty());
+    }
+
+    #[test]
+    fn verify_across_fields() {
+        use crate::arithmetic::FieldExt;
+        use halo2curves::{bn256, pasta};
+        use std::marker::PhantomData;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PublicConfig {
+            a: Column<Advice>,
+            i: Column<crate::plonk::Instance>,
+            q: Selector,
+        }
+
+        struct PublicCircuit<F> {
+            a: u64,
+            _marker: PhantomData<F>,
+        }
+
+        impl<F: FieldExt> Circuit<F> for PublicCircuit<F> {
+            type Config = PublicConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                let q = meta.selector();
+
+                meta.create_gate("Public", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let i = cells.query_instance(i, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - i)]
+                });
+
+                PublicConfig { a, i, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    a: 0,
+                    _marker: PhantomData,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Public",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(F::from(self.a)),
+                        )?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let results = crate::verify_across_fields!(
+            K,
+            |F| (
+                PublicCircuit::<F> {
+                    a: 3,
+                    _marker: PhantomData,
+                },
+                vec![vec![F::from(3)]]
+            );
+            pasta::Fp, pasta::Fq, bn256::Fr
+        );
+        let names: Vec<_> = results.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["pasta::Fp", "pasta::Fq", "bn256::Fr"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        let results = crate::verify_across_fields!(
+            K,
+            |F| (
+                PublicCircuit::<F> {
+                    a: 3,
+                    _marker: PhantomData,
+                },
+                vec![vec![F::from(4)]]
+            );
+            pasta::Fp, bn256::Fr,
+        );
+        assert!(results.iter().all(|(_, result)| result.is_err()));
+    }
+
+    #[test]
+    fn region_columns() {
+        const K: u32 = 5;
+
+        let circuit = PinnedCircuit {
+            values: vec![1],
+            constrained: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = PinnedCircuit::configure(&mut cs);
+        assert_eq!(
+            prover.region_columns(0),
+            [Column::<Any>::from(config.a), config.f.into()]
+                .into_iter()
+                .collect()
+        );
+
+        // The selector that the values region enables isn't included.
+        let prover = MockProver::run(K, &RangeCircuit { values: vec![1, 2] }, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = RangeCircuit::configure(&mut cs);
+        assert_eq!(
+            prover.region_columns(0),
+            [Column::<Any>::from(config.table.inner())]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            prover.region_columns(1),
+            [Column::<Any>::from(config.a)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn outputs() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct OutputConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        struct OutputCircuit {}
+
+        impl Circuit<Fp> for OutputCircuit {
+            type Config = OutputConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                OutputConfig {
+                    a: meta.advice_column(),
+                    b: meta.advice_column(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Padding",
+                    |mut region| {
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(1)))?;
+                        Ok(())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Output",
+                    |mut region| {
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(2)))?;
+                        let b = region.assign_advice(
+                            || "b",
+                            config.b,
+                            1,
+                            || Value::known(Fp::from(3)),
+                        )?;
+                        region.mark_output(b.cell())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &OutputCircuit {}, vec![]).unwrap();
+        assert_eq!(
+            prover.outputs(),
+            vec![AssignedCellInfo {
+                column: Column::new(1, Any::advice()),
+                row: 2,
+                value: Some(Fp::from(3)),
+                region: Some(1),
+            }]
+        );
+    }
+
+    #[test]
+    fn wrapped_rotation() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PrevConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit whose gate checks that the advice cell on each of its first two
+        /// rows equals the instance cell on the row before it.
+        struct PrevCircuit;
+
+        impl Circuit<Fp> for PrevCircuit {
+            type Config = PrevConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                let q = meta.selector();
+
+                meta.create_gate("Previous instance", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let i = cells.query_instance(i, Rotation::prev());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - i)]
+                });
+
+                PrevConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Wrap",
+                    |mut region| {
+                        for (row, value) in [(0, 0), (1, 7)] {
+                            config.q.enable(&mut region, row)?;
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                row,
+                                || Value::known(Fp::from(value)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &PrevCircuit, vec![vec![Fp::from(7)]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // At row 0, the gate reads the last row of the instance column, which happens to
+        // be zero.
+        prover.set_strict(true);
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::WrappedRotation {
+                constraint: ((0, "Previous instance").into(), 0, "").into(),
+                region: (0, "Wrap").into(),
+                gate_offset: 0,
+                column: Column::new(0, Any::Instance),
+                rotation: -1,
+            }])
+        );
+    }
+
+    #[test]
+    fn verify_report() {
+        use super::VerifyPhase;
+
+        const K: u32 = 4;
+
+        let prover = MockProver::run(K, &NextCircuit { next: 3, fixed: 4 }, vec![]).unwrap();
+
+        let report = prover.verify_report();
+        assert!(!report.is_satisfied());
+        assert_eq!(report.rows_checked, 16);
+        assert!(report.usable_rows < 16);
+        assert_eq!(
+            report.phase_failures,
+            [
+                (VerifyPhase::Selectors, 0),
+                (VerifyPhase::Gates, 1),
+                (VerifyPhase::Lookups, 0),
+                (VerifyPhase::Permutations, 0),
+            ]
+        );
+        assert!(!report.strict);
+        assert_eq!(report.into_result(), prover.verify());
+    }
+
+    #[test]
+    fn partial_lookup_table_row() {
+        use super::metadata;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct DynamicTableConfig {
+            tag: Column<Fixed>,
+            value: Column<Advice>,
+        }
+
+        struct DynamicTableCircuit;
+
+        impl Circuit<Fp> for DynamicTableCircuit {
+            type Config = DynamicTableConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let tag = meta.fixed_column();
+                let value = meta.advice_column();
+
+                meta.lookup_any("dynamic table", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let tag = cells.query_fixed(tag, Rotation::cur());
+                    let value = cells.query_advice(value, Rotation::cur());
+
+                    vec![(a.clone(), tag), (a, value)]
+                });
+
+                DynamicTableConfig { tag, value }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Table",
+                    |mut region| {
+                        for row in 0..3 {
+                            let entry = Value::known(Fp::from(row as u64));
+                            region.assign_fixed(|| "tag", config.tag, row, || entry)?;
+                            // BUG: The advice column of the table is one row short.
+                            if row < 2 {
+                                region.assign_advice(|| "value", config.value, row, || entry)?;
+                            }
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &DynamicTableCircuit, vec![]).unwrap();
+
+        assert_eq!(
+            prover.verify_lookup_tables(),
+            Err(vec![VerifyFailure::PartialLookupTableRow {
+                name: "dynamic table",
+                lookup_index: 0,
+                location: FailureLocation::InRegion {
+                    region: (0, "Table").into(),
+                    offset: 2,
+                },
+                unassigned_columns: vec![metadata::Column::from((Any::advice(), 1))],
+            }])
+        );
+    }
+
+    #[test]
+    fn column_unused_in_region() {
+        const K: u32 = 5;
+
+        let prover = MockProver::run(K, &RangeCircuit { values: vec![1, 2] }, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let config = RangeCircuit::configure(&mut cs);
+
+        // The table region doesn't touch the values, and vice versa.
+        prover.assert_column_unused_in_region(0, config.a);
+        prover.assert_column_unused_in_region(1, config.table.inner());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            prover.assert_column_unused_in_region(1, config.a)
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn lookup_normalizer() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SquaresConfig {
+            a: Column<Advice>,
+            table: Column<Fixed>,
+        }
+
+        /// A circuit that looks up `-3` in a table holding `0..4`.
+        struct SquaresCircuit;
+
+        impl Circuit<Fp> for SquaresCircuit {
+            type Config = SquaresConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let table = meta.fixed_column();
+
+                meta.lookup_any("squares", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let table = cells.query_fixed(table, Rotation::cur());
+
+                    vec![(a, table)]
+                });
+
+                SquaresConfig { a, table }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Table",
+                    |mut region| {
+                        for row in 0..4 {
+                            region.assign_fixed(
+                                || "table",
+                                config.table,
+                                row,
+                                || Value::known(Fp::from(row as u64)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Input",
+                    |mut region| {
+                        region
+                            .assign_advice(|| "a", config.a, 0, || Value::known(-Fp::from(3)))
+                            .map(|_| ())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &SquaresCircuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // Compare values up to sign.
+        prover.set_lookup_normalizer(0, |tuple| tuple.iter().map(|x| *x * *x).collect());
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.verify_par(), Ok(()));
+    }
+
+    #[test]
+    fn is_trivial() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CopyConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        /// A circuit without any gates, which only copies a cell.
+        struct CopyCircuit;
+
+        impl Circuit<Fp> for CopyCircuit {
+            type Config = CopyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                CopyConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Copied",
+                    |mut region| {
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::one()),
+                        )?;
+                        a.copy_advice(|| "b", &mut region, config.b, 0)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let pinned = |constrained| {
+            let circuit = PinnedCircuit {
+                values: vec![1],
+                constrained,
+            };
+            MockProver::run(K, &circuit, vec![]).unwrap()
+        };
+        assert!(pinned(false).is_trivial());
+        assert!(!pinned(true).is_trivial());
+
+        let copied = MockProver::run(K, &CopyCircuit, vec![]).unwrap();
+        assert!(!copied.is_trivial());
+    }
+
+    #[test]
+    fn verify_excluding() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct EqualityConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit that checks that `3` equals itself, and that it is a boolean.
+        struct EqualityCircuit;
+
+        impl Circuit<Fp> for EqualityCircuit {
+            type Config = EqualityConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+                meta.create_gate("Boolean check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a.clone() * (Expression::Constant(Fp::one()) - a)]
+                });
+
+                EqualityConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Equal values",
+                    |mut region| {
+                        let value = Value::known(Fp::from(3));
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || value)?;
+                        region.assign_advice(|| "b", config.b, 0, || value)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &EqualityCircuit, vec![]).unwrap();
+
+        assert!(prover.verify().is_err());
+        assert!(prover.verify_gates(&[1]).is_err());
+        assert_eq!(prover.verify_gates(&[0]), Ok(()));
+        assert_eq!(prover.verify_excluding(&[1]), Ok(()));
+        assert_eq!(prover.verify_excluding(&[0]), prover.verify());
+    }
+
+    #[test]
+    fn instance_len() {
+        const K: u32 = 4;
+
+        /// A circuit with two instance columns, which doesn't use them.
+        struct InstancesCircuit;
+
+        impl Circuit<Fp> for InstancesCircuit {
+            type Config = ();
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                meta.instance_column();
+                meta.instance_column();
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        let instance = vec![vec![Fp::from(1), Fp::from(2)], vec![]];
+        let prover = MockProver::run(K, &InstancesCircuit, instance).unwrap();
+
+        assert_eq!(prover.instance_len(0), 2);
+        assert_eq!(prover.instance_len(1), 0);
+    }
+
+    #[test]
+    fn replay_trace() {
+        use super::{replay_trace, AssignmentEvent};
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CopyConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct CopyCircuit {}
+
+        impl Circuit<Fp> for CopyCircuit {
+            type Config = CopyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                CopyConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Copy",
+                    |mut region| {
+                        config.q.enable(&mut region, 1)?;
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(5)),
+                        )?;
+                        let b = a.copy_advice(|| "b", &mut region, config.b, 1)?;
+                        region.assign_advice(|| "a", config.a, 1, || Value::known(Fp::from(5)))?;
+                        region.mark_output(b.cell())
+                    },
+                )
+            }
+        }
+
+        let (prover, trace) = MockProver::run_recorded(K, &CopyCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert!(matches!(trace[0], AssignmentEvent::EnterRegion { .. }));
+        assert!(trace
+            .iter()
+            .any(|event| matches!(event, AssignmentEvent::Copy { .. })));
+
+        let mut cs = ConstraintSystem::default();
+        CopyCircuit::configure(&mut cs);
+        let replayed = replay_trace(K, &cs, vec![], &trace).unwrap();
+        assert_eq!(replayed.advice, prover.advice);
+        assert_eq!(replayed.fixed, prover.fixed);
+        assert_eq!(replayed.permutation.mapping, prover.permutation.mapping);
+        assert_eq!(replayed.outputs(), prover.outputs());
+        assert_eq!(replayed.outputs().len(), 1);
+        assert_eq!(replayed.verify(), Ok(()));
+    }
+
+    #[test]
+    fn table_too_large() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct TableConfig {
+            table: TableColumn,
+            other: Column<Fixed>,
+        }
+
+        /// A circuit with a lookup table of `entries` rows, which also assigns a
+        /// constant at `constant_offset`.
+        struct TableCircuit {
+            entries: usize,
+            constant_offset: usize,
+        }
+
+        impl Circuit<Fp> for TableCircuit {
+            type Config = TableConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let table = meta.lookup_table_column();
+                let other = meta.fixed_column();
+
+                meta.lookup("range", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![(a, table)]
+                });
+
+                TableConfig { table, other }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    entries: self.entries,
+                    constant_offset: self.constant_offset,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "Range table",
+                    |mut table| {
+                        for row in 0..self.entries {
+                            table.assign_cell(
+                                || "table",
+                                config.table,
+                                row,
+                                || Value::known(Fp::from(row as u64)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Constants",
+                    |mut region| {
+                        region
+                            .assign_fixed(
+                                || "other",
+                                config.other,
+                                self.constant_offset,
+                                || Value::known(Fp::one()),
+                            )
+                            .map(|_| ())
+                    },
+                )
+            }
+        }
+
+        let error = |entries, constant_offset| {
+            let circuit = TableCircuit {
+                entries,
+                constant_offset,
+            };
+            MockProver::run_partial(K, &circuit, vec![]).unwrap().1
+        };
+        let usable_rows = MockProver::run(
+            K,
+            &TableCircuit {
+                entries: 1,
+                constant_offset: 0,
+            },
+            vec![],
+        )
+        .unwrap()
+        .usable_rows
+        .len();
+
+        // Columns that aren't read by a lookup table keep the generic error.
+        assert!(matches!(
+            error(1, usable_rows),
+            Some(MockProverError::Plonk(Error::NotEnoughRowsAvailable {
+                current_k: K
+            }))
+        ));
+
+        match error(usable_rows + 1, 0) {
+            Some(MockProverError::TableTooLarge {
+                region,
+                entries,
+                usable_rows: rows,
+                current_k,
+                minimum_k,
+            }) => {
+                assert_eq!(region, "Range table");
+                assert_eq!(entries, usable_rows + 1);
+                assert_eq!(rows, usable_rows);
+                assert_eq!(current_k, K);
+                assert_eq!(minimum_k, K + 1);
+            }
+            _ => panic!("expected MockProverError::TableTooLarge"),
+        }
+    }
+
+    #[test]
+    fn lookup_active_rows() {
+        const K: u32 = 5;
+
+        /// A [`RangeCircuit`] that also looks up every row of its values column,
+        /// regardless of the selector.
+        struct UnselectedCircuit {
+            values: Vec<u64>,
+        }
+
+        impl Circuit<Fp> for UnselectedCircuit {
+            type Config = RangeConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let config = RangeCircuit::configure(meta);
+                meta.lookup("unselected", |cells| {
+                    let a = cells.query_advice(config.a, Rotation::cur());
+
+                    vec![(a, config.table)]
+                });
+                config
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { values: vec![] }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                RangeCircuit {
+                    values: self.values.clone(),
+                }
+                .synthesize(config, layouter)
+            }
+        }
+
+        let circuit = UnselectedCircuit { values: vec![3, 5] };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(prover.lookup_active_rows(0), vec![0, 1]);
+        assert_eq!(
+            prover.lookup_active_rows(1),
+            prover.usable_rows.clone().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn fixed_fingerprint() {
+        const K: u32 = 4;
+
+        let fingerprint = |values: &[u64]| {
+            let circuit = PinnedCircuit {
+                values: values.to_vec(),
+                constrained: false,
+            };
+            MockProver::run(K, &circuit, vec![])
+                .unwrap()
+                .fixed_fingerprint()
+        };
+
+        let expected = fingerprint(&[1, 2, 3]);
+        assert_eq!(fingerprint(&[1, 2, 3]), expected);
+        assert_ne!(fingerprint(&[1, 2, 4]), expected);
+        // Assigning zero is different from leaving the cell unassigned.
+        assert_ne!(fingerprint(&[1, 2, 3, 0]), expected);
+    }
+
+    #[test]
+    fn near_miss_constraints() {
+        const K: u32 = 4;
+
+        // Off by one.
+        let prover = MockProver::run(K, &NextCircuit { next: 6, fixed: 5 }, vec![]).unwrap();
+        assert_eq!(prover.near_miss_constraints(Fp::from(1)), vec![(0, 0, 0)]);
+        assert!(prover.near_miss_constraints(Fp::from(2)).is_empty());
+    }
+
+    #[test]
+    fn count_regions_named() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct OpsConfig {
+            a: Column<Advice>,
+        }
+
+        /// A circuit with one region per entry of `ops`, named after it.
+        struct OpsCircuit {
+            ops: Vec<&'static str>,
+        }
+
+        impl Circuit<Fp> for OpsCircuit {
+            type Config = OpsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                OpsConfig {
+                    a: meta.advice_column(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    ops: self.ops.clone(),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                for op in self.ops.iter() {
+                    layouter.assign_region(
+                        || *op,
+                        |mut region| {
+                            region
+                                .assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))
+                                .map(|_| ())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let circuit = OpsCircuit {
+            ops: vec!["xor op", "xor op", "and op", "xor op"],
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+
+        assert_eq!(prover.count_regions_named("xor op"), 3);
+        assert_eq!(prover.count_regions_named("and op"), 1);
+        assert_eq!(prover.count_regions_named("or op"), 0);
+    }
+
+    #[cfg(feature = "dev-graph")]
+    #[test]
+    fn permutation_dot_graph() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ChainConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        /// A circuit that copies `a[0]` to `b[1]`, and then to `a[2]`.
+        struct ChainCircuit;
+
+        impl Circuit<Fp> for ChainCircuit {
+            type Config = ChainConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                ChainConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Copies",
+                    |mut region| {
+                        let cell = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::one()),
+                        )?;
+                        let cell = cell.copy_advice(|| "b", &mut region, config.b, 1)?;
+                        cell.copy_advice(|| "a", &mut region, config.a, 2)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &ChainCircuit, vec![]).unwrap();
+
+        let graph = super::permutation_dot_graph(&prover);
+        assert!(graph.starts_with("digraph"));
+        assert_eq!(graph.matches("->").count(), 3);
+        assert!(graph.contains("row 2"));
+        assert!(!graph.contains("row 3"));
+    }
+
+    #[test]
+    fn all_enabled_selectors() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SelectorsConfig {
+            a: Column<Advice>,
+            q: Selector,
+            r: Selector,
+            unused: Selector,
+        }
+
+        struct SelectorsCircuit;
+
+        impl Circuit<Fp> for SelectorsCircuit {
+            type Config = SelectorsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                SelectorsConfig {
+                    a: meta.advice_column(),
+                    q: meta.selector(),
+                    r: meta.selector(),
+                    unused: meta.selector(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let value = Value::known(Fp::one());
+                layouter.assign_region(
+                    || "first",
+                    |mut region| {
+                        region.assign_advice(|| "a", config.a, 0, || value)?;
+                        config.q.enable(&mut region, 3)?;
+                        config.r.enable(&mut region, 0)
+                    },
+                )?;
+                layouter.assign_region(
+                    || "second",
+                    |mut region| {
+                        region.assign_advice(|| "a", config.a, 1, || value)?;
+                        config.q.enable(&mut region, 1)
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &SelectorsCircuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let SelectorsConfig { q, r, unused, .. } = SelectorsCircuit::configure(&mut cs);
+
+        // The second region starts after the first one's last selector, on row 4.
+        let selectors = prover.all_enabled_selectors();
+        assert_eq!(selectors.len(), 2);
+        assert_eq!(selectors[&q], vec![3, 5]);
+        assert_eq!(selectors[&r], vec![0]);
+        assert!(!selectors.contains_key(&unused));
+    }
+
+    #[test]
+    fn analyze_configuration() {
+        struct ShapeCircuit {}
+
+        impl Circuit<Fp> for ShapeCircuit {
+            type Config = ();
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let table = meta.lookup_table_column();
+                let q = meta.selector();
+                meta.enable_equality(a);
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+                meta.lookup("range", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![(a, table)]
+                });
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+                unreachable!("only configure is run")
+            }
+        }
+
+        let description = super::analyze_configuration::<Fp, ShapeCircuit>();
+        assert_eq!(description.num_gates(), 1);
+        assert_eq!(description.num_lookups(), 1);
+        assert_eq!(description.num_permutation_columns(), 1);
+        assert!(description
+            .to_string()
+            .contains("Columns: 2 advice, 1 fixed, 0 instance"));
+    }
+
+    #[test]
+    fn allow_shared_cell() {
+        const K: u32 = 4;
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+
+        // The regions share the cells in rows 0 and 1. A floor planner never lays out
+        // overlapping regions, so this needs the builder.
+        let mut prover = MockProver::builder(K, cs)
+            .unwrap()
+            .region("first")
+            .advice(a, 0, Fp::from(1))
+            .unwrap()
+            .advice(a, 1, Fp::from(1))
+            .unwrap()
+            .region("second")
+            .advice(a, 0, Fp::from(2))
+            .unwrap()
+            .advice(a, 1, Fp::from(1))
+            .unwrap()
+            .build();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Only the cell that was assigned a different value is reported.
+        prover.set_strict(true);
+        assert_eq!(
+            prover.verify(),
+            Err(vec![VerifyFailure::ConflictingAssignment {
+                column: a.into(),
+                row: 0,
+                earlier_region: (0, "first").into(),
+                region: (1, "second").into(),
+            }])
+        );
+
+        prover.allow_shared_cell(a, 0);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn dormant_gates() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct DormantConfig {
+            a: Column<Advice>,
+            q_used: Selector,
+        }
+
+        struct DormantCircuit;
+
+        impl Circuit<Fp> for DormantCircuit {
+            type Config = DormantConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q_used = meta.selector();
+                let q_unused = meta.selector();
+
+                for q in [q_used, q_unused] {
+                    meta.create_gate("Zero check", |cells| {
+                        let a = cells.query_advice(a, Rotation::cur());
+                        let q = cells.query_selector(q);
+
+                        vec![q * a]
+                    });
+                }
+                meta.create_gate("Always on", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![a.clone() * a]
+                });
+
+                DormantConfig { a, q_used }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Used",
+                    |mut region| {
+                        config.q_used.enable(&mut region, 0)?;
+                        region
+                            .assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))
+                            .map(|_| ())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &DormantCircuit, vec![]).unwrap();
+        assert_eq!(prover.dormant_gates(), vec![1]);
+    }
+
+    #[test]
+    fn set_scan_order() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ZeroConfig {
+            columns: [(Column<Advice>, Selector); 2],
+        }
+
+        /// A circuit that assigns one to two cells that must be zero, each in its own
+        /// region, column and gate.
+        struct ZeroCircuit;
+
+        impl Circuit<Fp> for ZeroCircuit {
+            type Config = ZeroConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let columns = [(); 2].map(|_| (meta.advice_column(), meta.selector()));
+                for (a, q) in columns {
+                    meta.create_gate("Zero check", |cells| {
+                        let a = cells.query_advice(a, Rotation::cur());
+                        let q = cells.query_selector(q);
+
+                        vec![q * a]
+                    });
+                }
+
+                ZeroConfig { columns }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                // The regions use disjoint columns, so they both start on row 0, and
+                // are completed in the opposite order to their rows.
+                for (name, (a, q), offset) in [
+                    ("Late", config.columns[0], 4),
+                    ("Early", config.columns[1], 1),
+                ] {
+                    layouter.assign_region(
+                        || name,
+                        |mut region| {
+                            q.enable(&mut region, offset)?;
+                            region
+                                .assign_advice(|| "a", a, offset, || Value::known(Fp::one()))
+                                .map(|_| ())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let mut prover = MockProver::run(K, &ZeroCircuit, vec![]).unwrap();
+
+        let first_region = |prover: &MockProver<Fp>| match prover.first_failure() {
+            Some(VerifyFailure::ConstraintNotSatisfied {
+                location: FailureLocation::InRegion { region, .. },
+                ..
+            }) => region.name,
+            failure => panic!("unexpected failure: {:?}", failure),
+        };
+        assert_eq!(first_region(&prover), "Early");
+        let failures = prover.verify();
+
+        prover.set_scan_order(ScanOrder::Reverse);
+        assert_eq!(first_region(&prover), "Late");
+
+        prover.set_scan_order(ScanOrder::RegionCompletion);
+        assert_eq!(first_region(&prover), "Late");
+        assert_eq!(prover.iter_failures().count(), 2);
+
+        // The failures that `verify` returns are always in row order.
+        assert_eq!(prover.verify(), failures);
+    }
+
+    #[test]
+    fn assert_instance_consistent() {
+        use crate::plonk::Instance;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct InputsConfig {
+            a: Column<Advice>,
+            i: Column<Instance>,
+            q: Selector,
+        }
+
+        /// A circuit that copies `copied` to the first instance row, and enables a gate
+        /// reading the instance row after `gate_row`.
+        struct InputsCircuit {
+            gate_row: usize,
+            copied: u64,
+        }
+
+        impl Circuit<Fp> for InputsCircuit {
+            type Config = InputsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                let q = meta.selector();
+                meta.enable_equality(a);
+                meta.enable_equality(i);
+
+                meta.create_gate("Public input", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let i = cells.query_instance(i, Rotation::next());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - i)]
+                });
+
+                InputsConfig { a, i, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    gate_row: self.gate_row,
+                    copied: self.copied,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let cell = layouter.assign_region(
+                    || "Inputs",
+                    |mut region| {
+                        config.q.enable(&mut region, self.gate_row)?;
+                        region.assign_advice(
+                            || "a",
+                            config.a,
+                            2,
+                            || Value::known(Fp::from(self.copied)),
+                        )
+                    },
+                )?;
+                layouter.constrain_instance(cell.cell(), config.i, 0)
+            }
+        }
+
+        let build = |gate_row, copied| {
+            MockProver::run(
+                K,
+                &InputsCircuit { gate_row, copied },
+                vec![vec![Fp::from(3), Fp::from(4)]],
+            )
+            .unwrap()
+        };
+
+        build(0, 3).assert_instance_consistent();
+
+        // The gate reads the instance beyond the provided values.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            build(1, 3).assert_instance_consistent()
+        }));
+        assert!(result.is_err());
+
+        // The copied cell disagrees with the instance.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            build(0, 5).assert_instance_consistent()
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rerun_instance_only() {
+        use crate::plonk::Instance;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct PublicConfig {
+            a: Column<Advice>,
+            i: Column<Instance>,
+        }
+
+        struct PublicCircuit {}
+
+        impl Circuit<Fp> for PublicCircuit {
+            type Config = PublicConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                meta.enable_equality(a);
+                meta.enable_equality(i);
+
+                PublicConfig { a, i }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Public input",
+                    |mut region| {
+                        region.assign_advice_from_instance(|| "a", config.i, 0, config.a, 0)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &PublicCircuit {}, vec![vec![Fp::from(1)]]).unwrap();
+        assert!(matches!(
+            prover.rerun_instance_only(vec![vec![Fp::from(2)]]),
+            Err(MockProverError::WitnessDependsOnInstance)
+        ));
+
+        #[derive(Clone)]
+        struct GateConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit whose witness doesn't depend on the instance, which is only read
+        /// by a gate.
+        struct GateCircuit;
+
+        impl Circuit<Fp> for GateCircuit {
+            type Config = GateConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let i = meta.instance_column();
+                let q = meta.selector();
+
+                meta.create_gate("Public input", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let i = cells.query_instance(i, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - i)]
+                });
+
+                GateConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Input",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region
+                            .assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(5)))
+                            .map(|_| ())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &GateCircuit, vec![vec![Fp::from(5)]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        assert!(prover
+            .rerun_instance_only(vec![vec![Fp::from(6)]])
+            .unwrap()
+            .is_err());
+        assert_eq!(prover.instance_len(0), 1);
+        assert_eq!(
+            prover.rerun_instance_only(vec![vec![Fp::from(5)]]).unwrap(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn dead_cells() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CellsConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct CellsCircuit;
+
+        impl Circuit<Fp> for CellsCircuit {
+            type Config = CellsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+                meta.enable_equality(b);
+
+                meta.create_gate("Zero check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a]
+                });
+
+                CellsConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Cells",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        for row in 0..2 {
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                row,
+                                || Value::known(Fp::zero()),
+                            )?;
+                        }
+                        let b = region.assign_advice(
+                            || "b",
+                            config.b,
+                            0,
+                            || Value::known(Fp::from(2)),
+                        )?;
+                        region.assign_advice(|| "b", config.b, 1, || Value::known(Fp::from(2)))?;
+                        b.copy_advice(|| "b", &mut region, config.b, 2)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &CellsCircuit, vec![]).unwrap();
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let CellsConfig { a, b, .. } = CellsCircuit::configure(&mut cs);
+
+        let dead: Vec<_> = prover
+            .dead_cells()
+            .into_iter()
+            .map(|cell| (cell.column, cell.row))
+            .collect();
+        assert_eq!(dead, vec![(a.into(), 1), (b.into(), 1)]);
+    }
+
+    #[test]
+    fn selector_kind_conflicts() {
+        use super::SelectorKindConflict;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ConflictsConfig {
+            a: Column<Advice>,
+            simple: Selector,
+            gate_only: Selector,
+            looked_up: Selector,
+            table: TableColumn,
+        }
+
+        /// Uses one selector of each problematic kind: a simple selector that isn't a
+        /// factor of its constraint, a complex selector that is only used in gates, and a
+        /// complex selector that is also used in a lookup.
+        struct ConflictsCircuit;
+
+        impl Circuit<Fp> for ConflictsCircuit {
+            type Config = ConflictsConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let simple = meta.selector();
+                let gate_only = meta.complex_selector();
+                let looked_up = meta.complex_selector();
+                let table = meta.lookup_table_column();
+
+                meta.create_gate("Inverted", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let simple = cells.query_selector(simple);
+
+                    vec![(Expression::Constant(Fp::one()) - simple) * a]
+                });
+                meta.create_gate("Zero check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let gate_only = cells.query_selector(gate_only);
+                    let looked_up = cells.query_selector(looked_up);
+
+                    vec![gate_only * a.clone(), looked_up * a]
+                });
+                meta.lookup("range", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let looked_up = cells.query_selector(looked_up);
+
+                    vec![(looked_up * a, table)]
+                });
+
+                ConflictsConfig {
+                    a,
+                    simple,
+                    gate_only,
+                    looked_up,
+                    table,
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "Range table",
+                    |mut table| {
+                        for i in 0..RANGE {
+                            table.assign_cell(
+                                || format!("table[{}]", i),
+                                config.table,
+                                i as usize,
+                                || Value::known(Fp::from(i)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )?;
+
+                layouter.assign_region(
+                    || "Zero",
+                    |mut region| {
+                        config.simple.enable(&mut region, 0)?;
+                        config.gate_only.enable(&mut region, 0)?;
+                        config.looked_up.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &ConflictsCircuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let ConflictsConfig {
+            simple, gate_only, ..
+        } = ConflictsCircuit::configure(&mut cs);
+        assert_eq!(
+            prover.selector_kind_conflicts(),
+            vec![
+                SelectorKindConflict::NotAFactor {
+                    selector: simple,
+                    gate_index: 0,
+                    constraint_index: 0,
+                },
+                SelectorKindConflict::CouldBeSimple {
+                    selector: gate_only
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn suggest_missing_copies() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct HandoffConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        /// Computes values in one region and reuses them in another, either copying them
+        /// or assigning them again.
+        struct HandoffCircuit {
+            copy: bool,
+        }
+
+        impl Circuit<Fp> for HandoffCircuit {
+            type Config = HandoffConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                HandoffConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { copy: self.copy }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let produced = layouter.assign_region(
+                    || "Producer",
+                    |mut region| {
+                        [7, 1, 0]
+                            .iter()
+                            .enumerate()
+                            .map(|(offset, value)| {
+                                region.assign_advice(
+                                    || "a",
+                                    config.a,
+                                    offset,
+                                    || Value::known(Fp::from(*value)),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Consumer",
+                    |mut region| {
+                        for (offset, cell) in produced.iter().enumerate() {
+                            if self.copy {
+                                cell.copy_advice(|| "b", &mut region, config.b, offset)?;
+                            } else {
+                                region.assign_advice(
+                                    || "b",
+                                    config.b,
+                                    offset,
+                                    || cell.value().copied(),
+                                )?;
+                            }
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let a = Column::new(0, Any::advice());
+        let b = Column::new(1, Any::advice());
+
+        // Only the 7 is suggested, as zero and one are ignored.
+        let prover = MockProver::run(K, &HandoffCircuit { copy: false }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.suggest_missing_copies(), vec![((a, 0), (b, 0))]);
+
+        let prover = MockProver::run(K, &HandoffCircuit { copy: true }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.suggest_missing_copies(), vec![]);
+    }
+
+    #[test]
+    fn expect_sound() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![2, 3, 5, 7],
+            constrained: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Every corruption of a fully constrained witness is rejected.
+        super::expect_sound(K, &circuit, vec![], rand_core::OsRng, 32);
+    }
+
+    #[test]
+    #[should_panic(expected = "was not detected")]
+    fn expect_sound_under_constrained() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![2, 3, 5, 7],
+            constrained: false,
+        };
+        super::expect_sound(K, &circuit, vec![], rand_core::OsRng, 1);
+    }
+
+    #[test]
+    fn verify_all() {
+        use super::MockCircuit;
+
+        const K: u32 = 5;
+
+        let pinned = PinnedCircuit {
+            values: vec![2, 3],
+            constrained: true,
+        };
+        let in_range = RangeCircuit { values: vec![1, 2] };
+        let out_of_range = RangeCircuit {
+            values: vec![1, RANGE],
+        };
+        let cases: Vec<(u32, &dyn MockCircuit<Fp>, Vec<Vec<Fp>>)> = vec![
+            (K, &pinned, vec![]),
+            (K, &out_of_range, vec![]),
+            (K, &in_range, vec![]),
+        ];
+
+        let results = super::verify_all(&cases);
+        let expected = MockProver::run(K, &out_of_range, vec![])
+            .unwrap()
+            .verify()
+            .unwrap_err();
+        assert_eq!(expected.len(), 1);
+        assert_eq!(results, vec![(0, vec![]), (1, expected), (2, vec![])]);
+    }
+
+    #[test]
+    fn verify_resilient() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ZeroConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct ZeroCircuit {}
+
+        impl Circuit<Fp> for ZeroCircuit {
+            type Config = ZeroConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("a is zero", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a]
+                });
+                meta.create_gate("b is zero", |cells| {
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * b]
+                });
+
+                ZeroConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Zeroes",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))?;
+                        region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::zero()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut prover = MockProver::run(K, &ZeroCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify_resilient(), Ok(()));
+
+        // Drop the query of b, so that evaluating the second gate indexes out of bounds.
+        assert_eq!(prover.cs.advice_queries.len(), 2);
+        prover.cs.advice_queries.truncate(1);
+        assert!(
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| prover.verify())).is_err()
+        );
+
+        let failures = prover.verify_resilient().unwrap_err();
+        assert_eq!(failures.len(), 1);
+        match &failures[0] {
+            VerifyFailure::Internal {
+                gate_index,
+                message,
+            } => {
+                assert_eq!(*gate_index, 1);
+                assert!(message.contains("index out of bounds"), "{}", message);
+            }
+            failure => panic!("expected VerifyFailure::Internal, got {:?}", failure),
+        }
+    }
+
+    #[test]
+    fn verify_with_progress() {
+        use super::{VerifyPhase, VerifyProgress};
+        use std::cell::RefCell;
+
+        const K: u32 = 5;
+
+        let prover = MockProver::run(
+            K,
+            &RangeCircuit {
+                values: vec![1, RANGE, 2],
+            },
+            vec![],
+        )
+        .unwrap();
+
+        let reports = RefCell::new(vec![]);
+        let result = prover.verify_with_progress(|progress| reports.borrow_mut().push(progress));
+        assert_eq!(result, prover.verify());
+        assert!(result.is_err());
+
+        // Each phase is reported in turn, in the order of the checks, and finishes with a
+        // fraction of 1.
+        let reports = reports.into_inner();
+        let mut phases: Vec<VerifyPhase> = reports.iter().map(|report| report.phase).collect();
+        phases.dedup();
+        assert_eq!(
+            phases,
+            vec![
+                VerifyPhase::Selectors,
+                VerifyPhase::Gates,
+                VerifyPhase::Lookups,
+                VerifyPhase::Permutations,
+            ]
+        );
+        for phase in phases {
+            let fractions: Vec<f64> = reports
+                .iter()
+                .filter(|report| report.phase == phase)
+                .map(|report| report.fraction)
+                .collect();
+            assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(fractions.iter().all(|f| *f > 0.0 && *f <= 1.0));
+            assert_eq!(fractions.last(), Some(&1.0));
+        }
+
+        // Gates and lookups are reported once per row here, as there are fewer than 100
+        // usable rows, and once more as they finish.
+        let usable_rows = prover.usable_rows.len();
+        for phase in [VerifyPhase::Gates, VerifyPhase::Lookups] {
+            assert_eq!(
+                reports
+                    .iter()
+                    .filter(|report| report.phase == phase)
+                    .count(),
+                usable_rows + 1
+            );
+        }
+        assert_eq!(
+            reports[0],
+            VerifyProgress {
+                phase: VerifyPhase::Selectors,
+                fraction: 1.0,
+            }
+        );
+    }
+
+    #[test]
+    fn used_rows() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SpreadConfig {
+            a: Column<Advice>,
+        }
+
+        /// A circuit with one region per offset, each assigning a single cell at that
+        /// offset.
+        struct SpreadCircuit {
+            offsets: Vec<usize>,
+        }
+
+        impl Circuit<Fp> for SpreadCircuit {
+            type Config = SpreadConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                SpreadConfig {
+                    a: meta.advice_column(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    offsets: self.offsets.clone(),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                for offset in &self.offsets {
+                    layouter.assign_region(
+                        || "Spread",
+                        |mut region| {
+                            region
+                                .assign_advice(
+                                    || "a",
+                                    config.a,
+                                    *offset,
+                                    || Value::known(Fp::one()),
+                                )
+                                .map(|_| ())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let prover = MockProver::run(K, &SpreadCircuit { offsets: vec![] }, vec![]).unwrap();
+        assert_eq!(prover.used_rows(), 0);
+        let usable_rows = prover.usable_rows.clone();
+
+        // The second region starts on row 1, so its cell is on row 6, and rows 1 to 5
+        // are unused.
+        let prover = MockProver::run(
+            K,
+            &SpreadCircuit {
+                offsets: vec![0, 5],
+            },
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(prover.row_gaps(), vec![1..6]);
+        assert_eq!(prover.used_rows(), 7);
+
+        // The rows reserved for blinding factors are never counted.
+        let prover = MockProver::run(
+            K,
+            &SpreadCircuit {
+                offsets: vec![usable_rows.end - 1],
+            },
+            vec![],
+        )
+        .unwrap();
+        assert!(usable_rows.end < 1 << K);
+        assert_eq!(prover.used_rows(), usable_rows.end);
+
+        // Lookup tables are only counted up to the end of their contents, rather than to
+        // the end of the padding added by the layouter.
+        let prover = MockProver::run(K, &RangeCircuit { values: vec![1, 2] }, vec![]).unwrap();
+        assert_eq!(prover.used_rows(), RANGE as usize);
+    }
+
+    #[test]
+    fn describe() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ShapeConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+            q_lookup: Selector,
+            tables: [TableColumn; 2],
+        }
+
+        struct ShapeCircuit {}
+
+        impl Circuit<Fp> for ShapeCircuit {
+            type Config = ShapeConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let instance = meta.instance_column();
+                let q = meta.selector();
+                let q_lookup = meta.complex_selector();
+                let tables = [meta.lookup_table_column(), meta.lookup_table_column()];
+                meta.enable_equality(a);
+                meta.enable_equality(instance);
+
+                meta.create_gate("Boolean equality", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q.clone() * (a.clone() - b), q * (a.clone() * a.clone() - a)]
+                });
+                meta.lookup("pairs", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q_lookup);
+
+                    vec![(q.clone() * a, tables[0]), (q * b, tables[1])]
+                });
+
+                ShapeConfig {
+                    a,
+                    b,
+                    q,
+                    q_lookup,
+                    tables,
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_table(
+                    || "Pairs",
+                    |mut table| {
+                        // Both (0, 0), looked up when q_lookup is disabled, and (1, 1).
+                        for row in 0..2 {
+                            for column in config.tables {
+                                table.assign_cell(
+                                    || "table",
+                                    column,
+                                    row,
+                                    || Value::known(Fp::from(row as u64)),
+                                )?;
+                            }
+                        }
+                        Ok(())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Pair",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        config.q_lookup.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
+                        region.assign_advice(|| "b", config.b, 0, || Value::known(Fp::one()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &ShapeCircuit {}, vec![vec![]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The selectors are compressed into two more fixed columns, and the lookup's
+        // inputs have degree 2, so it requires a degree of 2 + 2 + 1.
+        let description = prover.describe();
+        assert_eq!(description.num_gates(), 1);
+        assert_eq!(description.num_lookups(), 1);
+        assert_eq!(description.num_permutation_columns(), 2);
+        assert_eq!(
+            description.to_string(),
+            "Columns: 2 advice, 4 fixed, 1 instance\n\
+             Degree: 5\n\
+             Gates: 1\n\
+             - Boolean equality: 2 constraint(s), degree 3\n\
+             Lookups: 1\n\
+             - pairs: 2 input(s), 2 table column(s)\n\
+             Permutation columns: 2\n\
+             - Column('Advice', 0)\n\
+             - Column('Instance', 0)\n"
+        );
+
+        // Before compression, selectors are not counted as fixed columns.
+        assert!(super::analyze_configuration::<Fp, ShapeCircuit>()
+            .to_string()
+            .starts_with("Columns: 2 advice, 2 fixed, 1 instance\nDegree: 5\n"));
+    }
+
+    #[test]
+    fn verify_timed() {
+        use std::time::Instant;
+
+        const K: u32 = 5;
+
+        for values in [vec![1, 2, 3], vec![1, RANGE, 3]] {
+            let prover = MockProver::run(K, &RangeCircuit { values }, vec![]).unwrap();
+
+            let start = Instant::now();
+            let (result, timings) = prover.verify_timed();
+            let elapsed = start.elapsed();
+
+            assert_eq!(result, prover.verify());
+            assert_eq!(
+                timings.total(),
+                timings.selectors + timings.gates + timings.lookups + timings.permutations
+            );
+            assert!(timings.total() <= elapsed);
+        }
+    }
+
+    #[test]
+    fn run_with_config() {
+        const K: u32 = 5;
+
+        let mut cs = ConstraintSystem::default();
+        let config = RangeCircuit::configure(&mut cs);
+
+        for values in [vec![1, 2, 3], vec![RANGE], vec![]] {
+            let circuit = RangeCircuit { values };
+            let prover =
+                MockProver::run_with_config(K, &cs, config.clone(), &circuit, vec![]).unwrap();
+            let expected = MockProver::run(K, &circuit, vec![]).unwrap();
+
+            assert_eq!(prover.advice, expected.advice);
+            assert_eq!(prover.fixed, expected.fixed);
+            assert_eq!(prover.verify(), expected.verify());
+        }
+    }
+
+    #[test]
+    fn eval_expression() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SumConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+            /// `a + a_next - b`, which the gate constrains to be zero.
+            sum: Expression<Fp>,
+        }
+
+        struct SumCircuit {}
+
+        impl Circuit<Fp> for SumCircuit {
+            type Config = SumConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                let mut sum = None;
+                meta.create_gate("Sum", |cells| {
+                    let a_cur = cells.query_advice(a, Rotation::cur());
+                    let a_next = cells.query_advice(a, Rotation::next());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    let expression = a_cur + a_next - b;
+                    sum = Some(expression.clone());
+                    vec![q * expression]
+                });
+
+                SumConfig {
+                    a,
+                    b,
+                    q,
+                    sum: sum.unwrap(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Sums",
+                    |mut region| {
+                        for offset in 0..4 {
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                offset,
+                                || Value::known(Fp::from(offset as u64 + 1)),
+                            )?;
+                        }
+                        for offset in 0..3 {
+                            config.q.enable(&mut region, offset)?;
+                            region.assign_advice(
+                                || "b",
+                                config.b,
+                                offset,
+                                || Value::known(Fp::from(2 * offset as u64 + 3)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut cs = ConstraintSystem::default();
+        let config = SumCircuit::configure(&mut cs);
+        let mut prover = MockProver::run(K, &SumCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        for row in 0..3 {
+            assert_eq!(prover.eval_expression(&config.sum, row), Fp::zero());
+        }
+        // Unassigned cells read as zero, or as the configured default.
+        assert_eq!(prover.eval_expression(&config.sum, 3), Fp::from(4));
+        prover.set_unassigned_default(Fp::from(10));
+        assert_eq!(prover.eval_expression(&config.sum, 4), Fp::from(10));
+
+        let last_row = prover.usable_rows.end - 1;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            prover.eval_expression(&config.sum, last_row)
+        }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn ascii_layout() {
+        const K: u32 = 4;
+
+        let prover = MockProver::run(
+            K,
+            &PinnedCircuit {
+                values: vec![2, 3],
+                constrained: true,
+            },
+            vec![],
+        )
+        .unwrap();
+
+        // The fixed column that the selector was compressed into is not drawn.
+        let layout = prover.ascii_layout();
+        let lines: Vec<&str> = layout.lines().collect();
+        assert_eq!(lines.len(), 2 + (1 << K));
+        assert_eq!(
+            lines[0],
+            "# assigned, . unassigned, x unusable, S selector enabled (1 row(s) per line)"
+        );
+        assert_eq!(lines[1], "   | A0 F0 | S0");
+        assert_eq!(lines[2], " 0 | #  #  | S ");
+        assert_eq!(lines[3], " 1 | #  #  | S ");
+        assert_eq!(lines[4], " 2 | .  .  | . ");
+
+        let first_unusable = prover.usable_rows.end;
+        assert_eq!(
+            lines[2 + first_unusable],
+            format!("{:>2} | x  x  | . ", first_unusable)
+        );
+    }
+
+    #[test]
+    fn verify_permutations() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct WiringConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        /// Assigns `a` and `b` to two cells that are both copy-constrained and checked
+        /// by a gate to be equal.
+        struct WiringCircuit {
+            a: u64,
+            b: u64,
+        }
+
+        impl Circuit<Fp> for WiringCircuit {
+            type Config = WiringConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                meta.create_gate("Equality check", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - b)]
+                });
+
+                WiringConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { a: 0, b: 0 }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Wiring",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(self.a)),
+                        )?;
+                        let b = region.assign_advice(
+                            || "b",
+                            config.b,
+                            0,
+                            || Value::known(Fp::from(self.b)),
+                        )?;
+                        region.constrain_equal(a.cell(), b.cell())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &WiringCircuit { a: 5, b: 5 }, vec![]).unwrap();
+        assert_eq!(prover.verify_permutations(), Ok(()));
+
+        // Only the copy constraint is reported, and not the gate.
+        let prover = MockProver::run(K, &WiringCircuit { a: 5, b: 6 }, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert!(failures
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::ConstraintNotSatisfied { .. })));
+        let permutation_failures: Vec<_> = failures
+            .into_iter()
+            .filter(|failure| matches!(failure, VerifyFailure::Permutation { .. }))
+            .collect();
+        assert!(!permutation_failures.is_empty());
+        assert_eq!(prover.verify_permutations(), Err(permutation_failures));
+    }
+
+    #[test]
+    fn run_partial() {
+        use super::CellValue;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct OverflowConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit whose second region assigns a cell past the end of the circuit, if
+        /// `overflow` is set.
+        struct OverflowCircuit {
+            overflow: bool,
+        }
+
+        impl Circuit<Fp> for OverflowCircuit {
+            type Config = OverflowConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Square is four", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a.clone() * a - Expression::Constant(Fp::from(4)))]
+                });
+
+                OverflowConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    overflow: self.overflow,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Complete",
+                    |mut region| {
+                        region
+                            .assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(1)))
+                            .map(|_| ())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Overflowing",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::from(2)))?;
+                        if self.overflow {
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                1 << K,
+                                || Value::known(Fp::from(3)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let (prover, error) =
+            MockProver::run_partial(K, &OverflowCircuit { overflow: false }, vec![]).unwrap();
+        assert!(error.is_none());
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The witness is kept up to the failing assignment, and the region that failed
+        // is never exited.
+        let (prover, error) =
+            MockProver::run_partial(K, &OverflowCircuit { overflow: true }, vec![]).unwrap();
+        assert!(matches!(
+            error,
+            Some(MockProverError::Plonk(Error::NotEnoughRowsAvailable {
+                current_k: K
+            }))
+        ));
+        assert_eq!(prover.advice[0][0], CellValue::Assigned(Fp::from(1)));
+        assert_eq!(prover.advice[0][1], CellValue::Assigned(Fp::from(2)));
+        assert_eq!(prover.count_regions_named("Complete"), 1);
+        assert_eq!(prover.count_regions_named("Overflowing"), 0);
+        assert_eq!(prover.floating_selectors().len(), 1);
+
+        // Errors before synthesis are still returned as `Err`.
+        assert!(
+            MockProver::run_partial(K, &OverflowCircuit { overflow: false }, vec![vec![]]).is_err()
+        );
+    }
+
+    #[test]
+    fn check_determinism() {
+        use std::collections::BTreeSet;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CounterConfig {
+            a: Column<Advice>,
+        }
+
+        /// A circuit that assigns a constant, and then the number of times that it has
+        /// been synthesized so far.
+        struct CounterCircuit {
+            counter: AtomicU64,
+        }
+
+        impl Circuit<Fp> for CounterCircuit {
+            type Config = CounterConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                CounterConfig {
+                    a: meta.advice_column(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    counter: AtomicU64::new(0),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let count = self.counter.fetch_add(1, Ordering::Relaxed);
+                layouter.assign_region(
+                    || "Counter",
+                    |mut region| {
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
+                        region
+                            .assign_advice(|| "a", config.a, 1, || Value::known(Fp::from(count)))
+                            .map(|_| ())
+                    },
+                )
+            }
+        }
+
+        let circuit = PinnedCircuit {
+            values: vec![2, 3],
+            constrained: true,
+        };
+        assert_eq!(
+            super::check_determinism(K, &circuit, vec![], 3).unwrap(),
+            BTreeSet::new()
+        );
+
+        let circuit = CounterCircuit {
+            counter: AtomicU64::new(0),
+        };
+        assert_eq!(
+            super::check_determinism(K, &circuit, vec![], 3).unwrap(),
+            BTreeSet::from([(Column::new(0, Any::advice()), 1)])
+        );
+    }
+
+    #[test]
+    fn row_costs() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct CostConfig {
+            a: Column<Advice>,
+            q_two: Selector,
+            q_one: Selector,
+        }
+
+        struct CostCircuit {}
+
+        impl Circuit<Fp> for CostCircuit {
+            type Config = CostConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q_two = meta.selector();
+                let q_one = meta.selector();
+
+                meta.create_gate("Two constraints", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q_two);
+
+                    vec![q.clone() * a.clone(), q * a.clone() * a]
+                });
+                meta.create_gate("One constraint", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q_one);
+
+                    vec![q * a]
+                });
+                // Applies to every row, and so is not counted.
+                meta.create_gate("Unselected", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![a.clone() - a]
+                });
+
+                CostConfig { a, q_two, q_one }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Costs",
+                    |mut region| {
+                        for offset in 0..3 {
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                offset,
+                                || Value::known(Fp::zero()),
+                            )?;
+                        }
+                        config.q_two.enable(&mut region, 0)?;
+                        config.q_two.enable(&mut region, 1)?;
+                        config.q_one.enable(&mut region, 1)?;
+                        config.q_one.enable(&mut region, 2)?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &CostCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut expected = vec![0; 1 << K];
+        expected[..3].copy_from_slice(&[2, 3, 1]);
+        assert_eq!(prover.row_costs(), expected);
+        assert_eq!(prover.num_active_constraints(), 6);
+    }
+
+    #[test]
+    fn lookup_arity_mismatch() {
+        const K: u32 = 5;
+
+        let circuit = RangeCircuit { values: vec![1] };
+        let mut cs = ConstraintSystem::default();
+        let config = RangeCircuit::configure(&mut cs);
+        assert!(MockProver::run_with_config(K, &cs, config.clone(), &circuit, vec![]).is_ok());
+
+        // `ConstraintSystem::lookup` always pairs inputs with table columns, so the
+        // mismatch has to be introduced by hand.
+        let extra = cs.lookups[0].table_expressions[0].clone();
+        cs.lookups[0].table_expressions.push(extra);
+        assert!(matches!(
+            MockProver::run_with_config(K, &cs, config, &circuit, vec![]),
+            Err(MockProverError::Plonk(Error::LookupArityMismatch {
+                lookup_index: 0
+            }))
+        ));
+    }
+
+    #[test]
+    fn equality_classes() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ClassConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        struct ClassCircuit {}
+
+        impl Circuit<Fp> for ClassCircuit {
+            type Config = ClassConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                meta.enable_equality(a);
+                meta.enable_equality(b);
+
+                ClassConfig { a, b }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Classes",
+                    |mut region| {
+                        // The last cell only pads its column, and is not part of any
+                        // class.
+                        let cells = [
+                            (config.a, 0, 2),
+                            (config.a, 1, 3),
+                            (config.a, 2, 2),
+                            (config.b, 0, 3),
+                            (config.b, 1, 2),
+                            (config.b, 2, 4),
+                        ]
+                        .iter()
+                        .map(|(column, offset, value)| {
+                            region.assign_advice(
+                                || "cell",
+                                *column,
+                                *offset,
+                                || Value::known(Fp::from(*value)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                        region.constrain_equal(cells[0].cell(), cells[4].cell())?;
+                        region.constrain_equal(cells[4].cell(), cells[2].cell())?;
+                        region.constrain_equal(cells[1].cell(), cells[3].cell())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(K, &ClassCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let a = Column::new(0, Any::advice());
+        let b = Column::new(1, Any::advice());
+        assert_eq!(
+            prover.equality_classes(),
+            vec![vec![(a, 0), (a, 2), (b, 1)], vec![(a, 1), (b, 0)]]
+        );
+    }
+
+    #[test]
+    fn random_fill() {
+        use super::CellValue;
+
+        const K: u32 = 4;
+
+        let mut prover = MockProver::run(
+            K,
+            &PinnedCircuit {
+                values: vec![2, 3],
+                constrained: true,
+            },
+            vec![],
+        )
+        .unwrap();
+        let original = prover.clone();
+
+        super::random_fill(&mut prover, rand_core::OsRng);
+
+        let usable_rows = prover.usable_rows.clone();
+        for (column, original_column) in prover.advice.iter().zip(original.advice.iter()) {
+            assert!(column[usable_rows.clone()]
+                .iter()
+                .all(|cell| matches!(cell, CellValue::Assigned(_))));
+            assert_eq!(
+                column[usable_rows.end..],
+                original_column[usable_rows.end..]
+            );
+        }
+        assert_eq!(prover.fixed, original.fixed);
+
+        // The pinned advice cells no longer match the fixed ones.
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn selector_gate_map() {
+        use std::collections::HashMap;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SharedConfig {
+            a: Column<Advice>,
+            shared: Selector,
+            single: Selector,
+            unused: Selector,
+        }
+
+        struct SharedCircuit {}
+
+        impl Circuit<Fp> for SharedCircuit {
+            type Config = SharedConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let shared = meta.selector();
+                let single = meta.selector();
+                let unused = meta.selector();
+
+                meta.create_gate("Zero", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let shared = cells.query_selector(shared);
+
+                    vec![shared * a]
+                });
+                meta.create_gate("Zero square", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let shared_once = cells.query_selector(shared);
+                    let shared_twice = cells.query_selector(shared);
+                    let single = cells.query_selector(single);
+
+                    vec![
+                        shared_once * a.clone() * a.clone(),
+                        shared_twice * single * a,
+                    ]
+                });
+
+                SharedConfig {
+                    a,
+                    shared,
+                    single,
+                    unused,
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Zeroes",
+                    |mut region| {
+                        config.shared.enable(&mut region, 0)?;
+                        config.single.enable(&mut region, 0)?;
+                        config.unused.enable(&mut region, 1)?;
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut cs = ConstraintSystem::default();
+        let config = SharedCircuit::configure(&mut cs);
+        let prover = MockProver::run(K, &SharedCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(
+            prover.selector_gate_map(),
+            HashMap::from([(config.shared, vec![0, 1]), (config.single, vec![1])])
+        );
+    }
+
+    #[test]
+    fn verify_by_region() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct GroupConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        /// A circuit with one region per entry of `regions`, each assigning its values
+        /// to cells that are constrained to be zero.
+        struct GroupCircuit {
+            regions: Vec<Vec<u64>>,
+        }
+
+        impl Circuit<Fp> for GroupCircuit {
+            type Config = GroupConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Zero", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a]
+                });
+                // Without a selector, this gate reads the unusable rows, and is reported
+                // as poisoned outside of any region.
+                meta.create_gate("Boolean", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+
+                    vec![a.clone() * (a - Expression::Constant(Fp::one()))]
+                });
+
+                GroupConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    regions: self.regions.clone(),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                for values in &self.regions {
+                    layouter.assign_region(
+                        || "Values",
+                        |mut region| {
+                            for (offset, value) in values.iter().enumerate() {
+                                config.q.enable(&mut region, offset)?;
+                                region.assign_advice(
+                                    || "a",
+                                    config.a,
+                                    offset,
+                                    || Value::known(Fp::from(*value)),
+                                )?;
+                            }
+                            Ok(())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let prover = MockProver::run(
+            K,
+            &GroupCircuit {
+                regions: vec![vec![0, 1], vec![0], vec![1, 1]],
+            },
+            vec![],
+        )
+        .unwrap();
+        let failures = prover.verify().unwrap_err();
+        let by_region = prover.verify_by_region().unwrap_err();
+
+        let mut keys: Vec<_> = by_region.keys().copied().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![None, Some(0), Some(2)]);
+        assert_eq!(by_region[&Some(0)].len(), 1);
+        assert_eq!(by_region[&Some(2)].len(), 2);
+        assert!(by_region[&None]
+            .iter()
+            .all(|failure| matches!(failure, VerifyFailure::ConstraintPoisoned { .. })));
+
+        // Every failure is in exactly one group, in the order `verify` returned it.
+        assert_eq!(
+            by_region.values().map(Vec::len).sum::<usize>(),
+            failures.len()
+        );
+        for group in by_region.values() {
+            let in_order: Vec<_> = failures
+                .iter()
+                .filter(|failure| group.contains(failure))
+                .collect();
+            assert_eq!(in_order, group.iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn quotient_degree() {
+        const K: u32 = 5;
+
+        // The permutation argument alone needs a degree of 3, which dominates the
+        // degree-2 gate.
+        let prover = MockProver::run(
+            K,
+            &PinnedCircuit {
+                values: vec![2],
+                constrained: true,
+            },
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(prover.quotient_degree(), 2);
+
+        // The lookup's input `q * a` has degree 2, so it needs a degree of 2 + 2 + 1.
+        let prover = MockProver::run(K, &RangeCircuit { values: vec![1] }, vec![]).unwrap();
+        assert_eq!(prover.quotient_degree(), 4);
+    }
+
+    #[test]
+    fn public_outputs() {
+        use crate::plonk::Instance;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct ExposeConfig {
+            a: Column<Advice>,
+            instances: [Column<Instance>; 2],
+        }
+
+        /// A circuit that exposes 7 at row 1 of the first instance column, and 9 at row
+        /// 0 of the second.
+        struct ExposeCircuit {}
+
+        impl Circuit<Fp> for ExposeCircuit {
+            type Config = ExposeConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let instances = [meta.instance_column(), meta.instance_column()];
+                meta.enable_equality(a);
+                for instance in instances {
+                    meta.enable_equality(instance);
+                }
+
+                ExposeConfig { a, instances }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let (nine, seven) = layouter.assign_region(
+                    || "Outputs",
+                    |mut region| {
+                        let nine = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Value::known(Fp::from(9)),
+                        )?;
+                        let seven = region.assign_advice(
+                            || "a",
+                            config.a,
+                            1,
+                            || Value::known(Fp::from(7)),
+                        )?;
+                        Ok((nine, seven))
+                    },
+                )?;
+                layouter.constrain_instance(nine.cell(), config.instances[1], 0)?;
+                layouter.constrain_instance(seven.cell(), config.instances[0], 1)
+            }
+        }
+
+        // Row 0 of the first instance column is not exposed.
+        let instance = vec![vec![Fp::from(5), Fp::from(7)], vec![Fp::from(9)]];
+        let prover = MockProver::run(K, &ExposeCircuit {}, instance).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(
+            prover.public_outputs(),
+            vec![(0, 1, Fp::from(7)), (1, 0, Fp::from(9))]
+        );
+    }
+
+    #[test]
+    fn empty_regions() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct SkippingConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        struct SkippingCircuit {}
+
+        impl Circuit<Fp> for SkippingCircuit {
+            type Config = SkippingConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q = meta.selector();
+                meta.create_gate("Zero", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a]
+                });
+
+                SkippingConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Assigned",
+                    |mut region| {
+                        region
+                            .assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))
+                            .map(|_| ())
+                    },
+                )?;
+                layouter.assign_region(|| "Skipped", |_| Ok(()))?;
+                layouter.assign_region(
+                    || "Selector only",
+                    |mut region| config.q.enable(&mut region, 0),
+                )
+            }
+        }
+
+        // Enabling a selector doesn't count as an assignment.
+        let prover = MockProver::run(K, &SkippingCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.empty_regions(), vec![1, 2]);
+    }
+
+    #[test]
+    fn selector_pattern() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct StrideConfig {
+            a: Column<Advice>,
+            even: Selector,
+            odd: Selector,
+        }
+
+        struct StrideCircuit {}
+
+        impl Circuit<Fp> for StrideCircuit {
+            type Config = StrideConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let even = meta.selector();
+                let odd = meta.selector();
+                meta.create_gate("Even", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let even = cells.query_selector(even);
+
+                    vec![even * a]
+                });
+                meta.create_gate("Odd", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let odd = cells.query_selector(odd);
+
+                    vec![odd * (a - Expression::Constant(Fp::one()))]
+                });
+
+                StrideConfig { a, even, odd }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Padding",
+                    |mut region| {
+                        region
+                            .assign_advice(|| "a", config.a, 2, || Value::known(Fp::zero()))
+                            .map(|_| ())
+                    },
+                )?;
+                layouter.assign_region(
+                    || "Strided",
+                    |mut region| {
+                        // Enabled out of order, to check that offsets are sorted.
+                        for offset in (0..6).rev() {
+                            let selector = if offset % 2 == 0 {
+                                config.even
+                            } else {
+                                config.odd
+                            };
+                            selector.enable(&mut region, offset)?;
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                offset,
+                                || Value::known(Fp::from(offset as u64 % 2)),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut cs = ConstraintSystem::default();
+        let config = StrideCircuit::configure(&mut cs);
+
+        // The second region starts on row 3, after the first, and offsets are relative
+        // to that.
+        let prover = MockProver::run(K, &StrideCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.selector_pattern(0, config.even), vec![]);
+        assert_eq!(prover.selector_pattern(1, config.even), vec![0, 2, 4]);
+        assert_eq!(prover.selector_pattern(1, config.odd), vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn verify_ignoring_instance() {
+        use crate::plonk::Instance;
+
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct TwiceConfig {
+            a: Column<Advice>,
+            instance: Column<Instance>,
+        }
+
+        /// A circuit that exposes both of `values` as the first instance cell.
+        struct TwiceCircuit {
+            values: [u64; 2],
+        }
+
+        impl Circuit<Fp> for TwiceCircuit {
+            type Config = TwiceConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let instance = meta.instance_column();
+                meta.enable_equality(a);
+                meta.enable_equality(instance);
+
+                TwiceConfig { a, instance }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    values: self.values,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                for value in self.values {
+                    let cell = layouter.assign_region(
+                        || "Exposed",
+                        |mut region| {
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                0,
+                                || Value::known(Fp::from(value)),
+                            )
+                        },
+                    )?;
+                    layouter.constrain_instance(cell.cell(), config.instance, 0)?;
+                }
+                Ok(())
             }
-            panic!("circuit was not satisfied");
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use halo2curves::pasta::Fp;
+        let run = |values, instance: u64| {
+            MockProver::run(K, &TwiceCircuit { values }, vec![vec![Fp::from(instance)]]).unwrap()
+        };
 
-    use super::{FailureLocation, MockProver, VerifyFailure};
-    use crate::{
-        circuit::{Layouter, SimpleFloorPlanner, Value},
-        plonk::{
-            Advice, Any, Circuit, Column, ConstraintSystem, Error, Expression, Selector,
-            TableColumn,
-        },
-        poly::Rotation,
-    };
+        let prover = run([5, 5], 5);
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.verify_ignoring_instance(), Ok(()));
+
+        // A witness that is internally consistent, but doesn't match the instance.
+        let prover = run([5, 5], 6);
+        assert!(prover.verify().is_err());
+        assert_eq!(prover.verify_ignoring_instance(), Ok(()));
+
+        // Cells related only through the instance cell must still be equal.
+        let prover = run([5, 6], 5);
+        assert!(prover.verify_ignoring_instance().is_err());
+    }
 
     #[test]
-    fn unassigned_cell() {
+    fn max_rotation() {
         const K: u32 = 4;
 
         #[derive(Clone)]
-        struct FaultyCircuitConfig {
+        struct WindowConfig {
             a: Column<Advice>,
             q: Selector,
         }
 
-        struct FaultyCircuit {}
+        /// A circuit whose gate reads `a` from one row before to three rows after the
+        /// row it's enabled on.
+        struct WindowCircuit {}
 
-        impl Circuit<Fp> for FaultyCircuit {
-            type Config = FaultyCircuitConfig;
+        impl Circuit<Fp> for WindowCircuit {
+            type Config = WindowConfig;
             type FloorPlanner = SimpleFloorPlanner;
 
             fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
                 let a = meta.advice_column();
-                let b = meta.advice_column();
                 let q = meta.selector();
-
-                meta.create_gate("Equality check", |cells| {
-                    let a = cells.query_advice(a, Rotation::prev());
-                    let b = cells.query_advice(b, Rotation::cur());
+                meta.create_gate("Window", |cells| {
+                    let prev = cells.query_advice(a, Rotation::prev());
+                    let last = cells.query_advice(a, Rotation(3));
                     let q = cells.query_selector(q);
 
-                    // If q is enabled, a and b must be assigned to.
-                    vec![q * (a - b)]
+                    vec![q * (prev - last)]
                 });
 
-                FaultyCircuitConfig { a, q }
+                WindowConfig { a, q }
             }
 
             fn without_witnesses(&self) -> Self {
@@ -1374,74 +11590,109 @@ mod tests {
                 mut layouter: impl Layouter<Fp>,
             ) -> Result<(), Error> {
                 layouter.assign_region(
-                    || "Faulty synthesis",
+                    || "Window",
                     |mut region| {
-                        // Enable the equality gate.
                         config.q.enable(&mut region, 1)?;
-
-                        // Assign a = 0.
-                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::zero()))?;
-
-                        // BUG: Forget to assign b = 0! This could go unnoticed during
-                        // development, because cell values default to zero, which in this
-                        // case is fine, but for other assignments would be broken.
+                        for offset in 0..5 {
+                            region.assign_advice(
+                                || "a",
+                                config.a,
+                                offset,
+                                || Value::known(Fp::one()),
+                            )?;
+                        }
                         Ok(())
                     },
                 )
             }
         }
 
-        let prover = MockProver::run(K, &FaultyCircuit {}, vec![]).unwrap();
+        let prover = MockProver::run(K, &WindowCircuit {}, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert_eq!(prover.max_rotation(), (-1, 3));
+
+        // Circuits that only query the current row still include zero.
+        let prover = MockProver::run(
+            K,
+            &PinnedCircuit {
+                values: vec![2],
+                constrained: true,
+            },
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(prover.max_rotation(), (0, 0));
+    }
+
+    #[test]
+    fn lookup_histogram() {
+        use ff::PrimeField;
+        use std::collections::HashMap;
+
+        const K: u32 = 5;
+
+        let prover = MockProver::run(
+            K,
+            &RangeCircuit {
+                values: vec![3, 3, 5, RANGE],
+            },
+            vec![],
+        )
+        .unwrap();
+
+        // The rows on which the lookup is disabled all look up 0, and the input that
+        // isn't in the table is omitted.
+        let key = |value: u64| Fp::from(value).to_repr().as_ref().to_vec();
         assert_eq!(
-            prover.verify(),
-            Err(vec![VerifyFailure::CellNotAssigned {
-                gate: (0, "Equality check").into(),
-                region: (0, "Faulty synthesis".to_owned()).into(),
-                gate_offset: 1,
-                column: Column::new(1, Any::advice()),
-                offset: 1,
-            }])
+            prover.lookup_histogram(0),
+            HashMap::from([
+                (key(0), prover.usable_rows.len() - 4),
+                (key(3), 2),
+                (key(5), 1),
+            ])
         );
     }
 
     #[test]
-    fn bad_lookup() {
+    fn run_with_seed() {
+        use crate::plonk::{Challenge, FirstPhase};
+
         const K: u32 = 4;
 
         #[derive(Clone)]
-        struct FaultyCircuitConfig {
+        struct ScaleConfig {
             a: Column<Advice>,
+            b: Column<Advice>,
             q: Selector,
-            table: TableColumn,
+            c: Challenge,
         }
 
-        struct FaultyCircuit {}
+        struct ScaleCircuit;
 
-        impl Circuit<Fp> for FaultyCircuit {
-            type Config = FaultyCircuitConfig;
+        impl Circuit<Fp> for ScaleCircuit {
+            type Config = ScaleConfig;
             type FloorPlanner = SimpleFloorPlanner;
 
             fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
                 let a = meta.advice_column();
-                let q = meta.complex_selector();
-                let table = meta.lookup_table_column();
+                let c = meta.challenge_usable_after(FirstPhase);
+                let b = meta.advice_column_in(SecondPhase);
+                let q = meta.selector();
 
-                meta.lookup("lookup", |cells| {
+                meta.create_gate("Scaled by challenge", |cells| {
                     let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let c = cells.query_challenge(c);
                     let q = cells.query_selector(q);
 
-                    // If q is enabled, a must be in the table.
-                    // When q is not enabled, lookup the default value instead.
-                    let not_q = Expression::Constant(Fp::one()) - q.clone();
-                    let default = Expression::Constant(Fp::from(2));
-                    vec![(q * a + not_q * default, table)]
+                    vec![q * (b - a * c)]
                 });
 
-                FaultyCircuitConfig { a, q, table }
+                ScaleConfig { a, b, q, c }
             }
 
             fn without_witnesses(&self) -> Self {
-                Self {}
+                Self
             }
 
             fn synthesize(
@@ -1449,87 +11700,255 @@ mod tests {
                 config: Self::Config,
                 mut layouter: impl Layouter<Fp>,
             ) -> Result<(), Error> {
-                layouter.assign_table(
-                    || "Doubling table",
-                    |mut table| {
-                        (1..(1 << (K - 1)))
-                            .map(|i| {
-                                table.assign_cell(
-                                    || format!("table[{}] = {}", i, 2 * i),
-                                    config.table,
-                                    i - 1,
-                                    || Value::known(Fp::from(2 * i as u64)),
-                                )
-                            })
-                            .fold(Ok(()), |acc, res| acc.and(res))
-                    },
-                )?;
-
+                let c = layouter.get_challenge(config.c);
                 layouter.assign_region(
-                    || "Good synthesis",
+                    || "Scale",
                     |mut region| {
-                        // Enable the lookup on rows 0 and 1.
                         config.q.enable(&mut region, 0)?;
-                        config.q.enable(&mut region, 1)?;
-
-                        // Assign a = 2 and a = 6.
-                        region.assign_advice(
-                            || "a = 2",
-                            config.a,
-                            0,
-                            || Value::known(Fp::from(2)),
-                        )?;
-                        region.assign_advice(
-                            || "a = 6",
-                            config.a,
-                            1,
-                            || Value::known(Fp::from(6)),
-                        )?;
-
+                        let a = Value::known(Fp::from(3));
+                        region.assign_advice(|| "a", config.a, 0, || a)?;
+                        region.assign_advice(|| "b", config.b, 0, || a * c)?;
                         Ok(())
                     },
-                )?;
+                )
+            }
+        }
+
+        let run = |seed: &[u8]| MockProver::run_with_seed(K, &ScaleCircuit, vec![], seed).unwrap();
 
+        let prover = run(b"first");
+        assert_eq!(prover.challenge_seed(), Some(&b"first"[..]));
+        assert_eq!(prover.challenges().len(), 1);
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The same seed reproduces the challenges, and the witness computed from them.
+        let again = run(b"first");
+        assert_eq!(again.challenges(), prover.challenges());
+        assert_eq!(again.advice, prover.advice);
+
+        let other = run(b"second");
+        assert_ne!(other.challenges(), prover.challenges());
+        assert_eq!(other.verify(), Ok(()));
+
+        let default = MockProver::run(K, &ScaleCircuit, vec![]).unwrap();
+        assert_eq!(
+            default.challenges(),
+            run(super::DEFAULT_CHALLENGE_SEED).challenges()
+        );
+    }
+
+    #[test]
+    fn assert_region_rows() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct StackConfig {
+            a: Column<Advice>,
+        }
+
+        /// A circuit with one region per entry of `lengths`, each assigning that many
+        /// cells to the same column, so that the regions are stacked on top of each
+        /// other.
+        struct StackCircuit {
+            lengths: Vec<usize>,
+        }
+
+        impl Circuit<Fp> for StackCircuit {
+            type Config = StackConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                StackConfig {
+                    a: meta.advice_column(),
+                }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    lengths: self.lengths.clone(),
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                for length in self.lengths.iter() {
+                    layouter.assign_region(
+                        || "Block",
+                        |mut region| {
+                            for offset in 0..*length {
+                                region.assign_advice(
+                                    || "a",
+                                    config.a,
+                                    offset,
+                                    || Value::known(Fp::one()),
+                                )?;
+                            }
+                            Ok(())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let circuit = StackCircuit {
+            lengths: vec![2, 3],
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_region_rows(0, 0..2);
+        prover.assert_region_rows(1, 2..5);
+    }
+
+    #[test]
+    #[should_panic(expected = "region 0 ('Pinned values') spans unexpected rows")]
+    fn assert_region_rows_moved() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![1, 2],
+            constrained: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_region_rows(0, 1..3);
+    }
+
+    #[test]
+    #[should_panic(expected = "region 0 ('Pinned values') has no assigned cells")]
+    fn assert_region_rows_empty() {
+        const K: u32 = 4;
+
+        let circuit = PinnedCircuit {
+            values: vec![],
+            constrained: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        prover.assert_region_rows(0, 0..0);
+    }
+
+    #[test]
+    fn gate_constraint_names() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct NamedConfig {
+            a: Column<Advice>,
+            b: Column<Advice>,
+            q: Selector,
+        }
+
+        struct NamedCircuit {
+            b: u64,
+        }
+
+        impl Circuit<Fp> for NamedCircuit {
+            type Config = NamedConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let b = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Named", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let b = cells.query_advice(b, Rotation::cur());
+                    let q = cells.query_selector(q);
+                    let one = Expression::Constant(Fp::one());
+
+                    vec![
+                        ("a equals b", q.clone() * (a - b.clone())),
+                        ("b is boolean", q * b.clone() * (one - b)),
+                    ]
+                });
+                meta.create_gate("Unnamed", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * a.clone() * (Expression::Constant(Fp::one()) - a)]
+                });
+
+                NamedConfig { a, b, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { b: self.b }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
                 layouter.assign_region(
-                    || "Faulty synthesis",
+                    || "Region",
                     |mut region| {
-                        // Enable the lookup on rows 0 and 1.
                         config.q.enable(&mut region, 0)?;
-                        config.q.enable(&mut region, 1)?;
-
-                        // Assign a = 4.
+                        region.assign_advice(|| "a", config.a, 0, || Value::known(Fp::one()))?;
                         region.assign_advice(
-                            || "a = 4",
-                            config.a,
+                            || "b",
+                            config.b,
                             0,
-                            || Value::known(Fp::from(4)),
+                            || Value::known(Fp::from(self.b)),
                         )?;
-
-                        // BUG: Assign a = 5, which doesn't exist in the table!
-                        region.assign_advice(
-                            || "a = 5",
-                            config.a,
-                            1,
-                            || Value::known(Fp::from(5)),
-                        )?;
-
                         Ok(())
                     },
                 )
             }
         }
 
-        let prover = MockProver::run(K, &FaultyCircuit {}, vec![]).unwrap();
+        let prover = MockProver::run(K, &NamedCircuit { b: 1 }, vec![]).unwrap();
         assert_eq!(
-            prover.verify(),
-            Err(vec![VerifyFailure::Lookup {
-                name: "lookup",
-                lookup_index: 0,
-                location: FailureLocation::InRegion {
-                    region: (2, "Faulty synthesis").into(),
-                    offset: 1,
-                }
-            }])
+            prover.gate_constraint_names(0),
+            vec!["a equals b", "b is boolean"]
+        );
+        assert_eq!(prover.gate_constraint_names(1), vec![""]);
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The names identify the constraints that a failure refers to.
+        let prover = MockProver::run(K, &NamedCircuit { b: 2 }, vec![]).unwrap();
+        let names = prover.gate_constraint_names(0);
+        let failed: Vec<super::metadata::Constraint> = prover
+            .verify()
+            .unwrap_err()
+            .into_iter()
+            .map(|failure| match failure {
+                VerifyFailure::ConstraintNotSatisfied { constraint, .. } => constraint,
+                _ => panic!("unexpected failure: {:?}", failure),
+            })
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                ((0, "Named").into(), 0, names[0]).into(),
+                ((0, "Named").into(), 1, names[1]).into(),
+            ]
         );
     }
+
+    #[test]
+    fn num_active_constraints() {
+        const K: u32 = 5;
+
+        let active = |values: Vec<u64>, constrained| {
+            let circuit = PinnedCircuit {
+                values,
+                constrained,
+            };
+            MockProver::run(K, &circuit, vec![])
+                .unwrap()
+                .num_active_constraints()
+        };
+
+        assert_eq!(active(vec![1, 2, 3], true), 3);
+        assert_eq!(active(vec![1, 2, 3, 4, 5], true), 5);
+        assert_eq!(active(vec![1, 2, 3], false), 0);
+
+        // Lookups aren't gates, so they don't count either.
+        let circuit = RangeCircuit { values: vec![1, 2] };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(prover.num_active_constraints(), 0);
+    }
 }
@@ -0,0 +1,133 @@
+use ff::Field;
+
+use super::{MockProver, MockProverError};
+use crate::{
+    arithmetic::{FieldExt, Group},
+    circuit,
+    plonk::{Advice, Any, Assignment, Column, Fixed, Selector},
+};
+
+/// A builder for a [`MockProver`] whose witness is set programmatically, rather than by
+/// synthesizing a [`Circuit`](crate::plonk::Circuit).
+///
+/// This is useful for testing [`MockProver::verify`] itself, and for micro-benchmarks
+/// that don't need a real circuit. It is created with [`MockProver::builder`].
+///
+/// All assignments are made inside a region, exactly as if they had been made by a
+/// floor planner. Use [`MockProverBuilder::region`] to start a new region; cells assigned
+/// before the first call to it go into an unnamed region.
+#[derive(Debug)]
+pub struct MockProverBuilder<F: Group + Field> {
+    prover: MockProver<F>,
+}
+
+impl<F: FieldExt> MockProverBuilder<F> {
+    pub(super) fn new(prover: MockProver<F>) -> Self {
+        MockProverBuilder { prover }
+    }
+
+    /// Starts an unnamed region if no region has been started yet.
+    fn open_region(&mut self) {
+        if self.prover.current_region.is_none() {
+            self.prover.enter_region(|| "");
+        }
+    }
+
+    /// Closes the current region, if one has been started.
+    ///
+    /// The region is kept even if nothing was assigned in it, as when a floor planner
+    /// exits a region.
+    fn close_region(&mut self) {
+        if let Some(region) = self.prover.current_region.take() {
+            self.prover.regions.push(region);
+        }
+    }
+
+    /// Closes the current region and starts a new one with the given name.
+    pub fn region(mut self, name: &str) -> Self {
+        self.close_region();
+        self.prover.enter_region(|| name);
+        self
+    }
+
+    /// Makes every subsequent [`MockProverBuilder::copy`] fail with
+    /// [`MockProverError::CopyMismatch`] if both cells are already assigned different
+    /// values, as in [`MockProver::run_with_copy_checks`].
+    pub fn check_copies(mut self) -> Self {
+        self.prover.check_copies = true;
+        self
+    }
+
+    /// Sets the values of the instance columns.
+    ///
+    /// Each column is padded with zeroes, as in [`MockProver::run`].
+    pub fn instance(mut self, instance: Vec<Vec<F>>) -> Result<Self, MockProverError> {
+        let instance_lens = instance.iter().map(Vec::len).collect();
+        self.prover.instance =
+            MockProver::pad_instance(&self.prover.cs, self.prover.n as usize, instance)?;
+        self.prover.instance_lens = instance_lens;
+        Ok(self)
+    }
+
+    /// Assigns `value` to the advice cell at `row` in `column`.
+    pub fn advice(
+        mut self,
+        column: Column<Advice>,
+        row: usize,
+        value: F,
+    ) -> Result<Self, MockProverError> {
+        self.open_region();
+        self.prover
+            .assign_advice(|| "", column, row, || circuit::Value::known(value))
+            .map_err(|error| self.prover.detailed_error(error))?;
+        Ok(self)
+    }
+
+    /// Assigns `value` to the fixed cell at `row` in `column`.
+    pub fn fixed(
+        mut self,
+        column: Column<Fixed>,
+        row: usize,
+        value: F,
+    ) -> Result<Self, MockProverError> {
+        self.open_region();
+        self.prover
+            .assign_fixed(|| "", column, row, || circuit::Value::known(value))
+            .map_err(|error| self.prover.detailed_error(error))?;
+        Ok(self)
+    }
+
+    /// Enables `selector` at `row`.
+    pub fn enable_selector(
+        mut self,
+        selector: Selector,
+        row: usize,
+    ) -> Result<Self, MockProverError> {
+        self.open_region();
+        self.prover
+            .enable_selector(|| "", &selector, row)
+            .map_err(|error| self.prover.detailed_error(error))?;
+        Ok(self)
+    }
+
+    /// Constrains the cell at `left_row` in `left_column` to equal the cell at
+    /// `right_row` in `right_column`.
+    pub fn copy(
+        mut self,
+        left_column: impl Into<Column<Any>>,
+        left_row: usize,
+        right_column: impl Into<Column<Any>>,
+        right_row: usize,
+    ) -> Result<Self, MockProverError> {
+        self.prover
+            .copy(left_column.into(), left_row, right_column.into(), right_row)
+            .map_err(|error| self.prover.detailed_error(error))?;
+        Ok(self)
+    }
+
+    /// Finishes building, returning a `MockProver` ready to be verified.
+    pub fn build(mut self) -> MockProver<F> {
+        self.close_region();
+        self.prover.compress_selectors()
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use ff::PrimeField;
+
+use super::{format_field, CellValue, MockProver};
+use crate::{
+    arithmetic::FieldExt,
+    plonk::{Advice, Any, Column, Fixed},
+};
+
+/// A cell of a [`MockProver`], along with its value and the region it was assigned in.
+///
+/// This is returned by the cell inspection methods, such as
+/// [`MockProver::region_cells`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssignedCellInfo<F> {
+    /// The column containing the cell.
+    pub column: Column<Any>,
+    /// The row of the cell.
+    pub row: usize,
+    /// The value of the cell, or `None` if it is unassigned.
+    pub value: Option<F>,
+    /// The index of the region in which the cell was assigned, or `None` if it was
+    /// assigned outside of any region (or is an instance cell).
+    pub region: Option<usize>,
+}
+
+impl<F: FieldExt> fmt::Display for AssignedCellInfo<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column_type = match self.column.column_type() {
+            Any::Advice(_) => "advice",
+            Any::Fixed => "fixed",
+            Any::Instance => "instance",
+        };
+        write!(
+            f,
+            "{}[{}] row {} = ",
+            column_type,
+            self.column.index(),
+            self.row
+        )?;
+        match self.value {
+            Some(value) => write!(f, "{}", format_field(value)),
+            None => write!(f, "unassigned"),
+        }
+    }
+}
+
+/// An explanation of the value that a cell of a [`MockProver`] takes, as returned by
+/// [`MockProver::trace_cell`].
+///
+/// A cell can read as zero because it was never assigned (and so takes the unassigned
+/// default), because it was assigned zero explicitly, or because it was copied from
+/// another cell that is zero. This distinguishes between them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellTrace<F> {
+    /// The traced cell, along with its assigned value.
+    pub cell: AssignedCellInfo<F>,
+    /// The value that the cell takes during verification: its assigned value, or the
+    /// unassigned default if it was never assigned.
+    pub value: F,
+    /// The other cells that the cell is constrained to equal by the permutation, in
+    /// the order in which the permutation cycle visits them.
+    pub equal_to: Vec<AssignedCellInfo<F>>,
+}
+
+impl<F: FieldExt> fmt::Display for CellTrace<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cell.value {
+            Some(_) => write!(f, "{} (assigned)", self.cell)?,
+            None => write!(
+                f,
+                "{}, so it takes the default value {}",
+                self.cell,
+                format_field(self.value)
+            )?,
+        }
+        for other in &self.equal_to {
+            write!(f, "\n  equal to {}", other)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: FieldExt> MockProver<F> {
+    /// Returns the cells that were marked as outputs of the circuit with
+    /// [`Region::mark_output`], in the order in which they were marked.
+    ///
+    /// This makes the values that a chip intends to expose explicit and testable,
+    /// rather than having to infer them from copies to instance columns.
+    ///
+    /// [`Region::mark_output`]: crate::circuit::Region::mark_output
+    pub fn outputs(&self) -> Vec<AssignedCellInfo<F>> {
+        self.outputs
+            .iter()
+            .map(|(column, row)| self.cell_info(*column, *row))
+            .collect()
+    }
+
+    /// Returns the cells assigned in the region with the given index, ordered by column
+    /// and then row.
+    ///
+    /// Panics if `region_index` is not the index of a region.
+    pub fn region_cells(&self, region_index: usize) -> Vec<AssignedCellInfo<F>> {
+        let mut cells: Vec<_> = self.regions[region_index].cells.keys().copied().collect();
+        cells.sort_unstable();
+
+        cells
+            .into_iter()
+            .map(|(column, row)| self.cell_info(column, row))
+            .collect()
+    }
+
+    /// Returns the cells that feed the polynomials of the gate with the given index,
+    /// when it is evaluated at `row`, in the order in which the gate queries them.
+    ///
+    /// Rotations are resolved to concrete rows, wrapping around the end of the circuit.
+    /// When a constraint fails, these are exactly the cells to inspect.
+    ///
+    /// Panics if `gate_index` is not the index of a gate, or `row` is not a row of the
+    /// circuit.
+    pub fn gate_inputs(&self, gate_index: usize, row: usize) -> Vec<AssignedCellInfo<F>> {
+        let n = self.n as i32;
+        assert!((row as i32) < n, "row {} is out of bounds", row);
+
+        self.cs.gates[gate_index]
+            .queried_cells()
+            .iter()
+            .map(|cell| {
+                let cell_row = ((row as i32 + n + cell.rotation.0) % n) as usize;
+                self.cell_info(cell.column, cell_row)
+            })
+            .collect()
+    }
+
+    /// Checks that the first `expected.len()` rows of the fixed `column` hold the given
+    /// values, returning the first cell that doesn't.
+    ///
+    /// Fixed columns that back constant tables are easy to forget to load, which leaves
+    /// them unassigned. An unassigned cell never matches.
+    ///
+    /// Panics if `expected` is longer than the circuit.
+    pub fn assert_fixed_loaded(
+        &self,
+        column: Column<Fixed>,
+        expected: &[F],
+    ) -> Result<(), AssignedCellInfo<F>> {
+        assert!(
+            expected.len() <= self.n as usize,
+            "expected {} rows, but the circuit only has {}",
+            expected.len(),
+            self.n
+        );
+
+        let column = Column::<Any>::from(column);
+        match expected
+            .iter()
+            .enumerate()
+            .map(|(row, value)| (self.cell_info(column, row), value))
+            .find(|(cell, value)| cell.value.as_ref() != Some(*value))
+        {
+            Some((cell, _)) => Err(cell),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that every assigned cell in the advice `column` holds a value less than
+    /// `2^bits`, returning the first cell that doesn't.
+    ///
+    /// Each value is interpreted as an integer through its little-endian
+    /// representation. This checks the range assumptions of a decomposition circuit
+    /// directly, without having to construct a lookup. Unassigned cells are skipped.
+    pub fn assert_in_range(
+        &self,
+        column: Column<Advice>,
+        bits: u32,
+    ) -> Result<(), AssignedCellInfo<F>> {
+        let column = Column::<Any>::from(column);
+        match (0..self.n as usize)
+            .map(|row| self.cell_info(column, row))
+            .find(|cell| matches!(cell.value, Some(value) if !fits_in_bits(value, bits)))
+        {
+            Some(cell) => Err(cell),
+            None => Ok(()),
+        }
+    }
+
+    /// Explains the value that the given cell takes: whether it was assigned, and which
+    /// other cells it is constrained to equal by the permutation.
+    ///
+    /// Panics if `row` is not a row of the circuit.
+    pub fn trace_cell(&self, column: Column<Any>, row: usize) -> CellTrace<F> {
+        assert!(row < self.n as usize, "row {} is out of bounds", row);
+
+        let cell = self.cell_info(column, row);
+        let value = cell.value.unwrap_or(self.unassigned_default);
+
+        let columns = self.cs.permutation.get_columns();
+        let mut equal_to = vec![];
+        if let Some(start) = columns.iter().position(|c| *c == column) {
+            let mapping = &self.permutation.mapping;
+            let mut next = mapping[start][row];
+            while next != (start, row) {
+                equal_to.push(self.cell_info(columns[next.0], next.1));
+                next = mapping[next.0][next.1];
+            }
+        }
+
+        CellTrace {
+            cell,
+            value,
+            equal_to,
+        }
+    }
+
+    /// Returns the assigned advice cells that nothing reads: no gate on a row where it
+    /// is enabled, no lookup on a row where it is active, and no copy constraint.
+    ///
+    /// This is an optimization lint. Dead cells take up rows without constraining
+    /// anything, and can usually be removed. Cells are ordered by column and then row.
+    pub fn dead_cells(&self) -> Vec<AssignedCellInfo<F>> {
+        let n = self.n as i32;
+        let mut used: HashSet<(Column<Any>, usize)> = HashSet::new();
+        let mut mark = |cells: &[(Column<Any>, i32)], row: usize| {
+            for (column, rotation) in cells {
+                used.insert((*column, ((row as i32 + n + rotation) % n) as usize));
+            }
+        };
+
+        for gate in self.cs.gates.iter() {
+            let cells: Vec<_> = gate
+                .queried_cells()
+                .iter()
+                .map(|cell| (cell.column, cell.rotation.0))
+                .collect();
+            let selectors = gate.queried_selectors();
+            for row in self.usable_rows.clone().filter(|row| {
+                selectors.is_empty()
+                    || selectors
+                        .iter()
+                        .any(|selector| self.selectors[selector.0][*row])
+            }) {
+                mark(&cells, row);
+            }
+        }
+
+        for (lookup_index, lookup) in self.cs.lookups.iter().enumerate() {
+            let inputs = self.lookup_advice_cells(lookup_index);
+            for row in self.lookup_active_rows(lookup_index) {
+                mark(&inputs, row);
+            }
+            // Every usable row is part of the table.
+            let table: Vec<_> = lookup
+                .table_expressions
+                .iter()
+                .flat_map(|expression| self.expression_cells(expression))
+                .collect();
+            for row in self.usable_rows.clone() {
+                mark(&table, row);
+            }
+        }
+
+        let columns = self.cs.permutation.get_columns();
+        for (column, rows) in self.permutation.mapping.iter().enumerate() {
+            for (row, cell) in rows.iter().enumerate() {
+                if *cell != (column, row) {
+                    used.insert((columns[column], row));
+                }
+            }
+        }
+
+        (0..self.advice.len())
+            .map(|index| self.advice_column(index))
+            .flat_map(|column| self.usable_rows.clone().map(move |row| (column, row)))
+            .filter(|(column, row)| {
+                matches!(self.advice[column.index()][*row], CellValue::Assigned(_))
+                    && !used.contains(&(*column, *row))
+            })
+            .map(|(column, row)| self.cell_info(column, row))
+            .collect()
+    }
+
+    /// Returns the value of the given cell, and the region it was assigned in.
+    pub(super) fn cell_info(&self, column: Column<Any>, row: usize) -> AssignedCellInfo<F> {
+        let value = match column.column_type() {
+            Any::Advice(_) => match self.advice[column.index()][row] {
+                CellValue::Assigned(value) => Some(value),
+                CellValue::Unassigned | CellValue::Poison(_) => None,
+            },
+            Any::Fixed => match self.fixed[column.index()][row] {
+                CellValue::Assigned(value) => Some(value),
+                CellValue::Unassigned | CellValue::Poison(_) => None,
+            },
+            Any::Instance => Some(self.instance_value(column.index(), row)),
+        };
+        let region = self
+            .regions
+            .iter()
+            .position(|region| region.cells.contains_key(&(column, row)));
+
+        AssignedCellInfo {
+            column,
+            row,
+            value,
+            region,
+        }
+    }
+}
+
+/// Returns `true` if `value`, interpreted as an integer, is less than `2^bits`.
+fn fits_in_bits<F: PrimeField>(value: F, bits: u32) -> bool {
+    let repr = value.to_repr();
+    repr.as_ref().iter().enumerate().all(|(i, byte)| {
+        // The number of low bits of this byte that are within range.
+        let allowed = bits.saturating_sub(8 * i as u32);
+        allowed >= 8 || byte >> allowed == 0
+    })
+}
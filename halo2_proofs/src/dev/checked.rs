@@ -0,0 +1,141 @@
+use super::{
+    util, CellValue, FailureLocation, MockProver, MockProverError, RunOptions, Value, VerifyFailure,
+};
+use crate::{
+    arithmetic::FieldExt,
+    plonk::{Any, Circuit},
+};
+
+impl<F: FieldExt> MockProver<F> {
+    /// Behaves like [`MockProver::run`], but checks the gates enabled in each region as
+    /// soon as the region is exited, and stops synthesis at the first region that
+    /// fails.
+    ///
+    /// Returns the `MockProver` along with the first failure that was found, if any. A
+    /// failure is reported while the synthesis of the offending region is still fresh,
+    /// which tightens the debugging loop for large circuits where full synthesis is
+    /// slow. After a failure, the `MockProver` holds the witness up to and including the
+    /// failing region.
+    ///
+    /// Only gates enabled by a selector in the region are checked, and only on rows
+    /// where every cell they query has already been assigned, since later regions may
+    /// still assign the rest. Everything else is left to [`MockProver::verify`], which
+    /// should still be called if no failure is returned.
+    pub fn run_checked<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+    ) -> Result<(Self, Option<VerifyFailure>), MockProverError> {
+        let check: fn(&Self, usize) -> Option<VerifyFailure> =
+            |prover, region_index| prover.region_failure(region_index);
+        let options = RunOptions {
+            region_check: Some(check),
+            ..RunOptions::default()
+        };
+        let (mut prover, error) = Self::run_with_options(k, circuit, instance, options)?;
+
+        // Once a region has failed, synthesis is aborted with `Error::Synthesis`, which
+        // is superseded by the failure itself.
+        let failure = prover.failed_region.0.take();
+        match error {
+            Some(error) if failure.is_none() => Err(error),
+            _ => Ok((prover, failure)),
+        }
+    }
+
+    /// Returns the first constraint failure, in row order, of the gates enabled by the
+    /// selectors of the region with the given index, skipping rows on which a gate
+    /// queries a cell that hasn't been assigned yet.
+    ///
+    /// This must be called before selectors are compressed.
+    fn region_failure(&self, region_index: usize) -> Option<VerifyFailure> {
+        let n = self.n as i32;
+        let region = &self.regions[region_index];
+        let start = region
+            .enabled_selectors
+            .values()
+            .flatten()
+            .copied()
+            .chain(region.rows.map(|(start, _)| start))
+            .min()?;
+
+        let mut gate_rows: Vec<(usize, usize)> = self
+            .cs
+            .gates
+            .iter()
+            .enumerate()
+            .flat_map(|(gate_index, gate)| {
+                gate.queried_selectors()
+                    .iter()
+                    .filter_map(|selector| region.enabled_selectors.get(selector))
+                    .flatten()
+                    .map(move |row| (*row, gate_index))
+            })
+            .collect();
+        gate_rows.sort_unstable();
+        gate_rows.dedup();
+
+        gate_rows.into_iter().find_map(|(row, gate_index)| {
+            let gate = &self.cs.gates[gate_index];
+            let assigned = gate.queried_cells().iter().all(|cell| {
+                let cell_row = ((row as i32 + n + cell.rotation.0) % n) as usize;
+                let value = match cell.column.column_type() {
+                    Any::Advice(_) => self.advice[cell.column.index()][cell_row],
+                    Any::Fixed => self.fixed[cell.column.index()][cell_row],
+                    Any::Instance => return true,
+                };
+                value != CellValue::Unassigned
+            });
+            if !assigned {
+                return None;
+            }
+
+            gate.polynomials()
+                .iter()
+                .enumerate()
+                .find_map(|(poly_index, poly)| match self.evaluate_at(poly, row) {
+                    Value::Real(x) if !x.is_zero_vartime() => {
+                        let load_row = row as i32 + n;
+                        Some(VerifyFailure::ConstraintNotSatisfied {
+                            constraint: (
+                                (gate_index, gate.name()).into(),
+                                poly_index,
+                                gate.constraint_name(poly_index),
+                            )
+                                .into(),
+                            location: FailureLocation::InRegion {
+                                region: (region_index, region.name.as_str()).into(),
+                                offset: row - start,
+                            },
+                            cell_values: util::cell_values(
+                                gate,
+                                poly,
+                                util::load(
+                                    n,
+                                    load_row,
+                                    &self.cs.fixed_queries,
+                                    &self.fixed,
+                                    self.unassigned_default,
+                                ),
+                                util::load(
+                                    n,
+                                    load_row,
+                                    &self.cs.advice_queries,
+                                    &self.advice,
+                                    self.unassigned_default,
+                                ),
+                                util::load_instance(
+                                    n,
+                                    load_row,
+                                    &self.cs.instance_queries,
+                                    &self.instance,
+                                ),
+                                self.field_formatter,
+                            ),
+                        })
+                    }
+                    _ => None,
+                })
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use ff::Field;
+
+use super::metadata;
+use crate::plonk::{Circuit, ConstraintSystem};
+
+#[derive(Debug)]
+struct Gate {
+    name: &'static str,
+    constraints: usize,
+    degree: usize,
+}
+
+#[derive(Debug)]
+struct Lookup {
+    name: &'static str,
+    input_arity: usize,
+    table_arity: usize,
+}
+
+/// A summary of the structure of a circuit, independent of its witness.
+///
+/// This is created with [`MockProver::describe`](super::MockProver::describe) or
+/// [`analyze_configuration`], and is intended for documentation and review. Use its
+/// `Display` implementation for a readable dump.
+///
+/// When created with `MockProver::describe`, the summary is taken after selectors have
+/// been compressed, so selectors are counted as the fixed columns that they were
+/// compressed into, and gate degrees include the degree of the compressed selector
+/// expressions.
+#[derive(Debug)]
+pub struct CircuitDescription {
+    gates: Vec<Gate>,
+    lookups: Vec<Lookup>,
+    permutation_columns: Vec<metadata::Column>,
+    num_fixed_columns: usize,
+    num_advice_columns: usize,
+    num_instance_columns: usize,
+    degree: usize,
+}
+
+/// Returns a summary of the structure of `ConcreteCircuit`, by running only its
+/// `configure` method.
+///
+/// This is the `configure` half of [`MockProver::run`](super::MockProver::run): no
+/// witness is needed and nothing is synthesized, which makes it much faster when only
+/// the shape of the circuit matters. Since no selectors are enabled, they can't be
+/// compressed; they are not counted as fixed columns, and each contributes a degree of
+/// one to the gates that query it.
+pub fn analyze_configuration<F: Field, ConcreteCircuit: Circuit<F>>() -> CircuitDescription {
+    let mut cs = ConstraintSystem::default();
+    ConcreteCircuit::configure(&mut cs);
+    CircuitDescription::new(&cs)
+}
+
+impl CircuitDescription {
+    pub(super) fn new<F: Field>(cs: &ConstraintSystem<F>) -> Self {
+        CircuitDescription {
+            gates: cs
+                .gates
+                .iter()
+                .map(|gate| Gate {
+                    name: gate.name(),
+                    constraints: gate.polynomials().len(),
+                    degree: gate
+                        .polynomials()
+                        .iter()
+                        .map(|poly| poly.degree())
+                        .max()
+                        .unwrap_or(0),
+                })
+                .collect(),
+            lookups: cs
+                .lookups
+                .iter()
+                .map(|lookup| Lookup {
+                    name: lookup.name,
+                    input_arity: lookup.input_expressions.len(),
+                    table_arity: lookup.table_expressions.len(),
+                })
+                .collect(),
+            permutation_columns: cs
+                .permutation
+                .get_columns()
+                .into_iter()
+                .map(|column| column.into())
+                .collect(),
+            num_fixed_columns: cs.num_fixed_columns,
+            num_advice_columns: cs.num_advice_columns,
+            num_instance_columns: cs.num_instance_columns,
+            degree: cs.degree(),
+        }
+    }
+
+    /// Returns the number of gates in the circuit.
+    pub fn num_gates(&self) -> usize {
+        self.gates.len()
+    }
+
+    /// Returns the number of lookup arguments in the circuit.
+    pub fn num_lookups(&self) -> usize {
+        self.lookups.len()
+    }
+
+    /// Returns the number of columns involved in the permutation argument.
+    pub fn num_permutation_columns(&self) -> usize {
+        self.permutation_columns.len()
+    }
+}
+
+impl fmt::Display for CircuitDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Columns: {} advice, {} fixed, {} instance",
+            self.num_advice_columns, self.num_fixed_columns, self.num_instance_columns
+        )?;
+        writeln!(f, "Degree: {}", self.degree)?;
+
+        writeln!(f, "Gates: {}", self.gates.len())?;
+        for gate in &self.gates {
+            writeln!(
+                f,
+                "- {}: {} constraint(s), degree {}",
+                gate.name, gate.constraints, gate.degree
+            )?;
+        }
+
+        writeln!(f, "Lookups: {}", self.lookups.len())?;
+        for lookup in &self.lookups {
+            writeln!(
+                f,
+                "- {}: {} input(s), {} table column(s)",
+                lookup.name, lookup.input_arity, lookup.table_arity
+            )?;
+        }
+
+        writeln!(f, "Permutation columns: {}", self.permutation_columns.len())?;
+        for column in &self.permutation_columns {
+            writeln!(f, "- {}", column)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use std::error;
+use std::fmt;
+
+use crate::plonk::{Any, Column, Error};
+
+/// An error returned by the [`MockProver`] constructors and methods other than
+/// [`MockProver::run`].
+///
+/// These carry more context than [`Error`] about what went wrong. `MockProver::run`
+/// returns the closest [`Error`] instead, as key generation and proving would.
+///
+/// [`MockProver`]: super::MockProver
+/// [`MockProver::run`]: super::MockProver::run
+#[derive(Debug)]
+pub enum MockProverError {
+    /// An error that can also occur during key generation or proving.
+    Plonk(Error),
+    /// The number of provided challenge values does not match the number of challenges
+    /// used by the circuit.
+    InvalidChallenges {
+        /// The number of challenges used by the circuit.
+        expected: usize,
+        /// The number of challenge values that were provided.
+        provided: usize,
+    },
+    /// Two cells that were already assigned different values were constrained to be
+    /// equal. This is only checked by [`MockProver`]s that were asked to check copy
+    /// constraints eagerly.
+    ///
+    /// [`MockProver`]: super::MockProver
+    CopyMismatch {
+        /// The column of the left cell.
+        left_column: Column<Any>,
+        /// The row of the left cell.
+        left_row: usize,
+        /// The column of the right cell.
+        right_column: Column<Any>,
+        /// The row of the right cell.
+        right_row: usize,
+    },
+    /// A region loading a lookup table assigned a table cell on a row that is not
+    /// usable at the current `k`.
+    TableTooLarge {
+        /// The name of the region loading the table.
+        region: String,
+        /// The number of table rows needed so far, counted from the start of the
+        /// region. The table has at least this many entries.
+        entries: usize,
+        /// The number of usable rows at the current `k`.
+        usable_rows: usize,
+        /// The current value of `k` being used.
+        current_k: u32,
+        /// The smallest value of `k` with enough usable rows for the entries so far.
+        minimum_k: u32,
+    },
+    /// `k` is too small to hold the rows that the permutation argument reserves at the
+    /// end of the circuit, along with at least one usable row.
+    NotEnoughRowsForPermutation {
+        /// The current value of `k` being used.
+        current_k: u32,
+        /// The smallest value of `k` that can hold the permutation argument.
+        minimum_k: u32,
+    },
+    /// The witness was computed while reading instance values, so it can't be reused
+    /// with different ones. This is only reported by
+    /// [`MockProver::rerun_instance_only`].
+    ///
+    /// [`MockProver::rerun_instance_only`]: super::MockProver::rerun_instance_only
+    WitnessDependsOnInstance,
+    /// An advice or fixed cell outside the bounds of the circuit's columns was assigned.
+    /// This usually means that the layouter computed a bad offset, or that a column from
+    /// a different constraint system was used.
+    ///
+    /// Instance cells are read without recording any context, so reading one out of
+    /// bounds is reported as [`Error::BoundsFailure`].
+    CellOutOfBounds {
+        /// The column that was accessed.
+        column: Column<Any>,
+        /// The row that was accessed.
+        row: usize,
+    },
+    /// A lookup argument has a different number of input expressions than table
+    /// expressions.
+    LookupArityMismatch {
+        /// The index of the lookup argument, in the order in which it was configured.
+        lookup_index: usize,
+    },
+    /// An advice cell was assigned a value that isn't known in the phase in which
+    /// its column is assigned, so the real prover would fail to create a proof.
+    ///
+    /// This means that the column's witness was computed from a cell in another phase
+    /// (the real prover only knows the cells of the phase being synthesized), or from
+    /// a challenge that isn't usable until a later phase.
+    ValueUnavailableInPhase {
+        /// The column of the cell.
+        column: Column<Any>,
+        /// The row of the cell.
+        row: usize,
+        /// The phase in which the column is assigned.
+        phase: u8,
+    },
+}
+
+impl MockProverError {
+    /// Returns the [`Error`] that is returned in place of this one by
+    /// [`MockProver::run`], or `None` if this already is an `Error`.
+    ///
+    /// [`MockProver::run`]: super::MockProver::run
+    pub(super) fn closest_error(&self) -> Option<Error> {
+        match self {
+            MockProverError::Plonk(_) => None,
+            MockProverError::InvalidChallenges { .. } => Some(Error::Synthesis),
+            MockProverError::TableTooLarge { current_k, .. } => {
+                Some(Error::not_enough_rows_available(*current_k))
+            }
+            MockProverError::NotEnoughRowsForPermutation { current_k, .. } => {
+                Some(Error::not_enough_rows_available(*current_k))
+            }
+            MockProverError::CellOutOfBounds { .. } => Some(Error::BoundsFailure),
+            MockProverError::WitnessDependsOnInstance => Some(Error::Synthesis),
+            MockProverError::CopyMismatch { .. } => Some(Error::Synthesis),
+            MockProverError::LookupArityMismatch { .. } => Some(Error::ConstraintSystemFailure),
+            MockProverError::ValueUnavailableInPhase { .. } => Some(Error::Synthesis),
+        }
+    }
+}
+
+impl From<Error> for MockProverError {
+    fn from(error: Error) -> Self {
+        MockProverError::Plonk(error)
+    }
+}
+
+impl From<MockProverError> for Error {
+    fn from(error: MockProverError) -> Self {
+        match error {
+            MockProverError::Plonk(error) => error,
+            error => error
+                .closest_error()
+                .expect("only `MockProverError::Plonk` has no closest error"),
+        }
+    }
+}
+
+impl fmt::Display for MockProverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MockProverError::Plonk(error) => write!(f, "{}", error),
+            MockProverError::InvalidChallenges { expected, provided } => write!(
+                f,
+                "{} challenge values were provided, but the circuit uses {}",
+                provided, expected
+            ),
+            MockProverError::TableTooLarge {
+                region,
+                entries,
+                usable_rows,
+                current_k,
+                minimum_k,
+            } => write!(
+                f,
+                "Table region '{}' has at least {} entries, but only {} rows are usable at k = {}. Use k >= {}",
+                region, entries, usable_rows, current_k, minimum_k,
+            ),
+            MockProverError::NotEnoughRowsForPermutation {
+                current_k,
+                minimum_k,
+            } => write!(
+                f,
+                "k = {} is too small to hold the permutation argument. Use k >= {}",
+                current_k, minimum_k,
+            ),
+            MockProverError::WitnessDependsOnInstance => write!(
+                f,
+                "The witness depends on instance values, so the circuit must be synthesized again"
+            ),
+            MockProverError::CellOutOfBounds { column, row } => write!(
+                f,
+                "Cell in column {:?} at row {} is out of bounds",
+                column, row
+            ),
+            MockProverError::CopyMismatch {
+                left_column,
+                left_row,
+                right_column,
+                right_row,
+            } => write!(
+                f,
+                "Cell in column {:?} at row {} is copied to cell in column {:?} at row {}, but they have different values",
+                left_column, left_row, right_column, right_row
+            ),
+            MockProverError::LookupArityMismatch { lookup_index } => write!(
+                f,
+                "Lookup {} has a different number of input and table expressions",
+                lookup_index
+            ),
+            MockProverError::ValueUnavailableInPhase { column, row, phase } => write!(
+                f,
+                "Cell in column {:?} at row {} was assigned a value that is not known in phase {}",
+                column, row, phase
+            ),
+        }
+    }
+}
+
+impl error::Error for MockProverError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            MockProverError::Plonk(error) => error.source(),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use super::{metadata, CellValue, MockProver, Value};
+use crate::{
+    arithmetic::FieldExt,
+    plonk::{Any, Column},
+};
+
+impl<F: FieldExt> MockProver<F> {
+    /// Returns a human-readable report of everything that happens on `row`: the region
+    /// it belongs to, the selectors enabled on it, the gates active on it and what their
+    /// constraints evaluate to, the cells assigned on it, the values of each lookup's
+    /// inputs, and the copy constraints involving its cells.
+    ///
+    /// This is intended as a one-stop dump when debugging a failure on a particular row.
+    /// The format of the report is not stable.
+    ///
+    /// Panics if `row` is not a row of the circuit.
+    pub fn explain_row(&self, row: usize) -> String {
+        assert!(row < self.n as usize, "row {} is out of bounds", row);
+        let format = |value: Value<F>| match value {
+            Value::Real(value) => (self.field_formatter)(value),
+            Value::Poison => "poison".to_string(),
+        };
+
+        let mut report = String::new();
+        writeln!(report, "Row {}:", row).unwrap();
+
+        // Regions are identified by the rows they span, so they may overlap.
+        for (index, region) in self.regions.iter().enumerate() {
+            if let Some((start, end)) = region.rows {
+                if (start..=end).contains(&row) {
+                    writeln!(
+                        report,
+                        "  In region {} ('{}') at offset {}",
+                        index,
+                        region.name,
+                        row - start
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        if !self.usable_rows.contains(&row) {
+            writeln!(report, "  This row is reserved for blinding factors").unwrap();
+        }
+
+        let enabled: Vec<_> = (0..self.selectors.len())
+            .filter(|selector| self.selectors[*selector][row])
+            .collect();
+        writeln!(report, "  Enabled selectors: {:?}", enabled).unwrap();
+
+        writeln!(report, "  Active gates:").unwrap();
+        for (gate_index, gate) in self.cs.gates.iter().enumerate() {
+            let selectors = gate.queried_selectors();
+            if !selectors.is_empty() && !selectors.iter().any(|s| enabled.contains(&s.0)) {
+                continue;
+            }
+            writeln!(report, "  - Gate {} ('{}'):", gate_index, gate.name()).unwrap();
+            for (poly_index, poly) in gate.polynomials().iter().enumerate() {
+                writeln!(
+                    report,
+                    "    - Constraint {} ('{}') = {}",
+                    poly_index,
+                    gate.constraint_name(poly_index),
+                    format(self.evaluate_at(poly, row))
+                )
+                .unwrap();
+            }
+        }
+
+        // The columns that selectors were compressed into are described above.
+        let selector_columns: HashSet<usize> =
+            self.cs.selector_map.iter().map(|c| c.index()).collect();
+        writeln!(report, "  Assigned cells:").unwrap();
+        let columns = (0..self.advice.len())
+            .map(|index| self.advice_column(index))
+            .chain(
+                (0..self.fixed.len())
+                    .filter(|index| !selector_columns.contains(index))
+                    .map(|index| Column::new(index, Any::Fixed)),
+            )
+            .chain((0..self.instance.len()).map(|index| Column::new(index, Any::Instance)));
+        for column in columns {
+            let cell = self.cell_info(column, row);
+            if let Some(value) = cell.value {
+                writeln!(
+                    report,
+                    "  - {} = {}",
+                    metadata::Column::from(column),
+                    format(Value::Real(value))
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(report, "  Lookups:").unwrap();
+        for (lookup_index, lookup) in self.cs.lookups.iter().enumerate() {
+            let fill_row = self.lookup_fill_row(lookup_index);
+            let input = self.lookup_input(lookup_index, row);
+            let status = if input == fill_row {
+                "the default table row"
+            } else if self
+                .lookup_table(lookup_index, &fill_row)
+                .binary_search(&input)
+                .is_ok()
+            {
+                "in the table"
+            } else {
+                "NOT in the table"
+            };
+            let input: Vec<_> = input.into_iter().map(format).collect();
+            writeln!(
+                report,
+                "  - Lookup {} ('{}'): ({}) is {}",
+                lookup_index,
+                lookup.name,
+                input.join(", "),
+                status
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "  Copy constraints:").unwrap();
+        let ids = self.equality_class_ids();
+        let mut class_sizes: HashMap<usize, usize> = HashMap::new();
+        for id in ids.iter().flatten() {
+            *class_sizes.entry(*id).or_default() += 1;
+        }
+        for (index, column) in self.cs.permutation.get_columns().iter().enumerate() {
+            let size = class_sizes[&ids[index][row]];
+            if size > 1 {
+                let value = match self.permutation_cell(index, row) {
+                    CellValue::Assigned(value) => format(Value::Real(value)),
+                    _ => "unassigned".to_string(),
+                };
+                writeln!(
+                    report,
+                    "  - {} = {} is constrained to equal {} other cell(s)",
+                    metadata::Column::from(*column),
+                    value,
+                    size - 1
+                )
+                .unwrap();
+            }
+        }
+
+        report
+    }
+}
@@ -8,7 +8,7 @@ use halo2curves::FieldExt;
 use super::{
     metadata,
     util::{self, AnyQuery},
-    MockProver, Region,
+    FieldFormatter, MockProver, Region,
 };
 use crate::{
     dev::Value,
@@ -127,6 +127,88 @@ pub enum VerifyFailure {
         /// offset 0, but the gate uses `Rotation::prev()`).
         offset: isize,
     },
+    /// In the stricter verification mode, a cell read by the inputs of an enabled lookup
+    /// was not assigned a value.
+    LookupCellNotAssigned {
+        /// The name of the lookup.
+        name: &'static str,
+        /// The index of the lookup. These indices are assigned in the order in which
+        /// `ConstraintSystem::lookup` is called during `Circuit::configure`.
+        lookup_index: usize,
+        /// The region in which this cell should be assigned.
+        region: metadata::Region,
+        /// The offset (relative to the start of the region) at which the lookup is
+        /// enabled.
+        lookup_offset: usize,
+        /// The column in which this cell should be assigned.
+        column: Column<Any>,
+        /// The offset (relative to the start of the region) at which this cell should be
+        /// assigned. This may be negative, if the lookup uses `Rotation::prev()`.
+        offset: isize,
+    },
+    /// In the stricter verification mode, a cell that an enabled gate reads at a
+    /// non-zero rotation was not assigned a value by any region.
+    ///
+    /// This is only reported for gates enabled by complex selectors, as cells read by
+    /// gates enabled by simple selectors are already covered by
+    /// `VerifyFailure::CellNotAssigned`.
+    RotatedCellNotAssigned {
+        /// The index of the active gate.
+        gate: metadata::Gate,
+        /// The region in which the gate is enabled.
+        region: metadata::Region,
+        /// The offset (relative to the start of the region) at which the active gate
+        /// queries this cell.
+        gate_offset: usize,
+        /// The column in which this cell should be assigned.
+        column: Column<Any>,
+        /// The rotation at which the gate queries this cell.
+        rotation: i32,
+    },
+    /// In the stricter verification mode, a constraint of an enabled gate is only
+    /// satisfied because of the value of a cell that it reads at a rotation which wraps
+    /// around the end of the circuit.
+    ///
+    /// Rotations wrap modulo the number of rows, so a gate enabled near either end of
+    /// the circuit reads a row at the other end rather than a real neighbour. This is
+    /// reported if changing the value of the wrapped cell would make the constraint
+    /// fail, which usually means the constraint holds by coincidence.
+    WrappedRotation {
+        /// The polynomial constraint that depends on the wrapped cell.
+        constraint: metadata::Constraint,
+        /// The region in which the gate is enabled.
+        region: metadata::Region,
+        /// The row at which the gate is enabled.
+        gate_offset: usize,
+        /// The column of the wrapped cell.
+        column: Column<Any>,
+        /// The rotation at which the gate queries the wrapped cell.
+        rotation: i32,
+    },
+    /// A cell was assigned a different value by a region than by an earlier region.
+    ///
+    /// This is only reported by the stricter verification mode, for cells that were not
+    /// allowed with [`MockProver::allow_shared_cell`].
+    ConflictingAssignment {
+        /// The column of the cell.
+        column: Column<Any>,
+        /// The row of the cell.
+        row: usize,
+        /// The earlier region that assigned the cell.
+        earlier_region: metadata::Region,
+        /// The region that assigned the cell a different value.
+        region: metadata::Region,
+    },
+    /// Evaluating a gate panicked, which usually means that the constraint system is
+    /// malformed.
+    ///
+    /// This is only reported by [`MockProver::verify_resilient`].
+    Internal {
+        /// The index of the gate whose evaluation panicked.
+        gate_index: usize,
+        /// The panic message.
+        message: String,
+    },
     /// A constraint was not satisfied for a particular row.
     ConstraintNotSatisfied {
         /// The polynomial constraint that is not satisfied.
@@ -173,6 +255,59 @@ pub enum VerifyFailure {
         /// The location at which the permutation is not satisfied.
         location: FailureLocation,
     },
+    /// The tuple that a lookup's inputs evaluate to when every cell is zero (such as on
+    /// rows where the selectors controlling the lookup are disabled) does not exist in
+    /// its table.
+    ///
+    /// This usually means that the table is missing the row that disabled inputs
+    /// collapse to.
+    LookupDefault {
+        /// The name of the lookup.
+        name: &'static str,
+        /// The index of the lookup. These indices are assigned in the order in which
+        /// `ConstraintSystem::lookup` is called during `Circuit::configure`.
+        lookup_index: usize,
+        /// The values of the lookup inputs when every cell is zero.
+        default_values: Vec<String>,
+    },
+    /// A row of a lookup's table is only partially assigned: some of its table
+    /// expressions read cells that were assigned, while others read cells that were
+    /// not.
+    ///
+    /// This usually means that the table was loaded incorrectly, for example a dynamic
+    /// table whose advice columns were filled for fewer rows than its fixed columns.
+    PartialLookupTableRow {
+        /// The name of the lookup.
+        name: &'static str,
+        /// The index of the lookup. These indices are assigned in the order in which
+        /// `ConstraintSystem::lookup` is called during `Circuit::configure`.
+        lookup_index: usize,
+        /// The location of the partially assigned table row.
+        location: FailureLocation,
+        /// The columns read by the table expressions in which the row is not assigned.
+        unassigned_columns: Vec<metadata::Column>,
+    },
+    /// An advice cell that is constrained to equal an instance cell does not hold the
+    /// value provided for that instance cell.
+    ///
+    /// This is only reported if enabled with
+    /// [`MockProver::set_report_instance_mismatches`], in addition to the corresponding
+    /// `VerifyFailure::Permutation` failures. It pinpoints a mismatch between a claimed
+    /// public output and the witness.
+    InstanceMismatch {
+        /// The advice column containing the witness cell.
+        column: metadata::Column,
+        /// The location of the witness cell.
+        location: FailureLocation,
+        /// The value of the witness cell.
+        advice_value: String,
+        /// The instance column containing the public cell.
+        instance_column: metadata::Column,
+        /// The row of the public cell.
+        instance_row: usize,
+        /// The value provided for the public cell.
+        instance_value: String,
+    },
 }
 
 impl fmt::Display for VerifyFailure {
@@ -191,6 +326,64 @@ impl fmt::Display for VerifyFailure {
                     region, gate, gate_offset, column, offset
                 )
             }
+            Self::LookupCellNotAssigned {
+                name,
+                lookup_index,
+                region,
+                lookup_offset,
+                column,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "{} uses lookup {}(index: {}) at offset {}, which requires cell in column {:?} at offset {} to be assigned.",
+                    region, name, lookup_index, lookup_offset, column, offset
+                )
+            }
+            Self::RotatedCellNotAssigned {
+                gate,
+                region,
+                gate_offset,
+                column,
+                rotation,
+            } => {
+                write!(
+                    f,
+                    "{} uses {} at offset {}, which reads column {:?} at rotation {}, but no region assigned that cell.",
+                    region, gate, gate_offset, column, rotation
+                )
+            }
+            Self::WrappedRotation {
+                constraint,
+                region,
+                gate_offset,
+                column,
+                rotation,
+            } => {
+                write!(
+                    f,
+                    "{} in {} at row {} is only satisfied because of column {:?} at rotation {}, which wraps around the end of the circuit.",
+                    constraint, region, gate_offset, column, rotation
+                )
+            }
+            Self::ConflictingAssignment {
+                column,
+                row,
+                earlier_region,
+                region,
+            } => {
+                write!(
+                    f,
+                    "Column {:?} at row {} was assigned in {} and then assigned a different value in {}.",
+                    column, row, earlier_region, region
+                )
+            }
+            Self::Internal {
+                gate_index,
+                message,
+            } => {
+                write!(f, "Evaluating gate {} panicked: {}", gate_index, message)
+            }
             Self::ConstraintNotSatisfied {
                 constraint,
                 location,
@@ -227,10 +420,124 @@ impl fmt::Display for VerifyFailure {
                     column, location
                 )
             }
+            Self::LookupDefault {
+                name,
+                lookup_index,
+                default_values,
+            } => {
+                write!(
+                    f,
+                    "Lookup {}(index: {}) table does not contain its default input ({})",
+                    name,
+                    lookup_index,
+                    default_values.join(", ")
+                )
+            }
+            Self::PartialLookupTableRow {
+                name,
+                lookup_index,
+                location,
+                unassigned_columns,
+            } => {
+                write!(
+                    f,
+                    "Lookup {}(index: {}) table row is only partially assigned {}; unassigned columns: {}",
+                    name,
+                    lookup_index,
+                    location,
+                    unassigned_columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            Self::InstanceMismatch {
+                column,
+                location,
+                advice_value,
+                instance_column,
+                instance_row,
+                instance_value,
+            } => {
+                write!(
+                    f,
+                    "Cell ({:?}, {}) = {} is constrained to equal instance cell ({:?}, row {}) = {}",
+                    column, location, advice_value, instance_column, instance_row, instance_value
+                )
+            }
         }
     }
 }
 
+impl std::error::Error for VerifyFailure {}
+
+/// Asserts that at least one of `failures` satisfies `predicate`, ignoring any other
+/// failures.
+///
+/// Tests for intentionally buggy circuits can use this to check for a particular
+/// failure, without matching the entire list of failures (which is brittle as the
+/// checks made by [`MockProver`] evolve).
+///
+/// ```ignore
+/// let failures = prover.verify().unwrap_err();
+/// assert_contains_failure(&failures, |failure| {
+///     matches!(
+///         failure,
+///         VerifyFailure::ConstraintNotSatisfied {
+///             location: FailureLocation::InRegion { offset: 0, .. },
+///             ..
+///         }
+///     )
+/// });
+/// ```
+///
+/// # Panics
+///
+/// Panics, listing every failure, if none of them satisfy `predicate`.
+#[track_caller]
+pub fn assert_contains_failure(
+    failures: &[VerifyFailure],
+    predicate: impl Fn(&VerifyFailure) -> bool,
+) {
+    if !failures.iter().any(predicate) {
+        panic!(
+            "no matching failure among {} failure(s): {:#?}",
+            failures.len(),
+            failures
+        );
+    }
+}
+
+/// The failures returned by [`MockProver::verify`], as a single error.
+///
+/// This allows the result of verification to be propagated with `?` through code that
+/// uses `std::error::Error` (for example, with `anyhow`):
+///
+/// ```ignore
+/// prover.verify().map_err(VerificationError::from)?;
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct VerificationError(pub Vec<VerifyFailure>);
+
+impl From<Vec<VerifyFailure>> for VerificationError {
+    fn from(failures: Vec<VerifyFailure>) -> Self {
+        VerificationError(failures)
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circuit was not satisfied ({} failures)", self.0.len())?;
+        for failure in &self.0 {
+            write!(f, "\n- {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
 /// Renders `VerifyFailure::CellNotAssigned`.
 ///
 /// ```text
@@ -309,8 +616,8 @@ fn render_cell_not_assigned<F: Field>(
 ///     x1 + x1 * 0x100 + x1 * 0x10000 + x1 * 0x100_0000 - x0 = 0
 ///
 ///   Assigned cell values:
-///     x0 = 0x5
-///     x1 = 0x5
+///     x0 = 5
+///     x1 = 5
 /// ```
 fn render_constraint_not_satisfied<F: Field>(
     gates: &[Gate<F>],
@@ -374,8 +681,10 @@ fn render_constraint_not_satisfied<F: Field>(
 ///     |   |    1   | x0 | x1 | <--{ Lookup inputs queried here
 ///     |
 ///     | Assigned cell values:
-///     |   x0 = 0x5
+///     |   x0 = 5
 ///     |   x1 = 1
+///     |
+///     | Queried column types: advice (1), fixed (1)
 /// ```
 fn render_lookup<F: FieldExt>(
     prover: &MockProver<F>,
@@ -396,15 +705,16 @@ fn render_lookup<F: FieldExt>(
         FailureLocation::OutsideRegion { row } => *row,
     } as i32;
 
-    // Recover the fixed columns from the table expressions. We don't allow composite
-    // expressions for the table side of lookups.
+    // Recover the columns from the table expressions. We don't allow composite
+    // expressions for the table side of lookups, but tables created with
+    // `ConstraintSystem::lookup_any` may be in any type of column.
     let table_columns = lookup.table_expressions.iter().map(|expr| {
         expr.evaluate(
             &|_| panic!("no constants in table expressions"),
             &|_| panic!("no selectors in table expressions"),
             &|query| format!("F{}", query.column_index),
-            &|_| panic!("no advice columns in table expressions"),
-            &|_| panic!("no instance columns in table expressions"),
+            &|query| format!("A{}", query.column_index),
+            &|query| format!("I{}", query.column_index),
             &|_| panic!("no challenges in table expressions"),
             &|_| panic!("no negations in table expressions"),
             &|_, _| panic!("no sums in table expressions"),
@@ -415,6 +725,7 @@ fn render_lookup<F: FieldExt>(
 
     fn cell_value<'a, F: FieldExt, Q: Into<AnyQuery> + Copy>(
         load: impl Fn(Q) -> Value<F> + 'a,
+        format: FieldFormatter<F>,
     ) -> impl Fn(Q) -> BTreeMap<metadata::VirtualCell, String> + 'a {
         move |query| {
             let AnyQuery {
@@ -426,7 +737,7 @@ fn render_lookup<F: FieldExt>(
             Some((
                 ((column_type, column_index).into(), rotation.0).into(),
                 match load(query) {
-                    Value::Real(v) => util::format_value(v),
+                    Value::Real(v) => format(v),
                     Value::Poison => unreachable!(),
                 },
             ))
@@ -453,14 +764,30 @@ fn render_lookup<F: FieldExt>(
         let cell_values = input.evaluate(
             &|_| BTreeMap::default(),
             &|_| panic!("virtual selectors are removed during optimization"),
-            &cell_value(&util::load(n, row, &cs.fixed_queries, &prover.fixed)),
-            &cell_value(&util::load(n, row, &cs.advice_queries, &prover.advice)),
-            &cell_value(&util::load_instance(
-                n,
-                row,
-                &cs.instance_queries,
-                &prover.instance,
-            )),
+            &cell_value(
+                &util::load(
+                    n,
+                    row,
+                    &cs.fixed_queries,
+                    &prover.fixed,
+                    prover.unassigned_default,
+                ),
+                prover.field_formatter,
+            ),
+            &cell_value(
+                &util::load(
+                    n,
+                    row,
+                    &cs.advice_queries,
+                    &prover.advice,
+                    prover.unassigned_default,
+                ),
+                prover.field_formatter,
+            ),
+            &cell_value(
+                &util::load_instance(n, row, &cs.instance_queries, &prover.instance),
+                prover.field_formatter,
+            ),
             &|_| BTreeMap::default(),
             &|a| a,
             &|mut a, mut b| {
@@ -510,10 +837,123 @@ fn render_lookup<F: FieldExt>(
         for (i, (_, value)) in cell_values.iter().enumerate() {
             eprintln!("    |   x{} = {}", i, value);
         }
+
+        // Show which types of columns this input depends on, to help distinguish a
+        // table that wasn't loaded from an input that is wrong.
+        eprintln!("    |");
+        eprintln!(
+            "    | Queried column types: {}",
+            queried_column_types(input)
+        );
+    }
+}
+
+/// Describes the types of columns queried by `expression`, along with the number of
+/// queries of each type, with the most-queried type first.
+pub(super) fn queried_column_types<F: Field>(expression: &Expression<F>) -> String {
+    let queries = expression.evaluate(
+        &|_| vec![],
+        &|_| vec![],
+        &|_| vec!["fixed"],
+        &|_| vec!["advice"],
+        &|_| vec!["instance"],
+        &|_| vec![],
+        &|a| a,
+        &|mut a, mut b| {
+            a.append(&mut b);
+            a
+        },
+        &|mut a, mut b| {
+            a.append(&mut b);
+            a
+        },
+        &|a, _| a,
+    );
+
+    let mut counts = BTreeMap::<&str, usize>::default();
+    for column_type in queries {
+        *counts.entry(column_type).or_default() += 1;
     }
+    if counts.is_empty() {
+        return "none (constant input)".to_string();
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    // Sort is stable, so ties stay in alphabetical order.
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    counts
+        .into_iter()
+        .map(|(column_type, count)| format!("{} ({})", column_type, count))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl VerifyFailure {
+    /// Returns the index of the gate that this failure is about, or `None` if it isn't
+    /// about a gate.
+    pub(super) fn gate_index(&self) -> Option<usize> {
+        match self {
+            Self::CellNotAssigned { gate, .. } | Self::RotatedCellNotAssigned { gate, .. } => {
+                Some(gate.index)
+            }
+            Self::ConstraintNotSatisfied { constraint, .. }
+            | Self::ConstraintPoisoned { constraint }
+            | Self::WrappedRotation { constraint, .. } => Some(constraint.gate.index),
+            Self::Internal { gate_index, .. } => Some(*gate_index),
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the region in which this failure occurred, or `None` if it
+    /// occurred outside of any region.
+    pub(super) fn region_index(&self) -> Option<usize> {
+        let location = match self {
+            Self::CellNotAssigned { region, .. }
+            | Self::LookupCellNotAssigned { region, .. }
+            | Self::RotatedCellNotAssigned { region, .. }
+            | Self::WrappedRotation { region, .. }
+            | Self::ConflictingAssignment { region, .. } => return Some(region.index),
+            Self::ConstraintPoisoned { .. }
+            | Self::LookupDefault { .. }
+            | Self::Internal { .. } => return None,
+            Self::ConstraintNotSatisfied { location, .. }
+            | Self::Lookup { location, .. }
+            | Self::PartialLookupTableRow { location, .. }
+            | Self::Permutation { location, .. }
+            | Self::InstanceMismatch { location, .. } => location,
+        };
+        match location {
+            FailureLocation::InRegion { region, .. } => Some(region.index),
+            FailureLocation::OutsideRegion { .. } => None,
+        }
+    }
+
+    /// Returns the row at which this failure occurred, or `None` if it isn't tied to a
+    /// single row.
+    pub(super) fn row(&self, regions: &[Region]) -> Option<usize> {
+        let location = match self {
+            Self::CellNotAssigned { gate_offset, .. }
+            | Self::RotatedCellNotAssigned { gate_offset, .. }
+            | Self::WrappedRotation { gate_offset, .. } => return Some(*gate_offset),
+            Self::LookupCellNotAssigned { lookup_offset, .. } => return Some(*lookup_offset),
+            Self::ConflictingAssignment { row, .. } => return Some(*row),
+            Self::ConstraintPoisoned { .. }
+            | Self::LookupDefault { .. }
+            | Self::Internal { .. } => return None,
+            Self::ConstraintNotSatisfied { location, .. }
+            | Self::Lookup { location, .. }
+            | Self::PartialLookupTableRow { location, .. }
+            | Self::Permutation { location, .. }
+            | Self::InstanceMismatch { location, .. } => location,
+        };
+        match location {
+            FailureLocation::InRegion { region, offset } => {
+                regions[region.index].rows.map(|(start, _)| start + offset)
+            }
+            FailureLocation::OutsideRegion { row } => Some(*row),
+        }
+    }
+
     /// Emits this failure in pretty-printed format to stderr.
     pub(super) fn emit<F: FieldExt>(&self, prover: &MockProver<F>) {
         match self {
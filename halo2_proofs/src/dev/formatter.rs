@@ -0,0 +1,40 @@
+use crate::arithmetic::FieldExt;
+
+/// A function that renders a field element as a string, for use in the values shown by
+/// [`VerifyFailure`](super::VerifyFailure).
+///
+/// Set one on a [`MockProver`](super::MockProver) with
+/// [`MockProver::set_field_formatter`](super::MockProver::set_field_formatter). The
+/// default is [`format_field`].
+pub type FieldFormatter<F> = fn(F) -> String;
+
+/// Renders a field element as a decimal integer if it or its negation fits in a `u64`,
+/// and as hex otherwise.
+///
+/// Witness values are usually either small integers (such as running sums or bits), the
+/// negations of small integers, or uniformly random elements. This shows the first two
+/// as `5` and `-5` rather than as 64 hex digits.
+pub fn format_field<F: FieldExt>(value: F) -> String {
+    if let Some(v) = as_u64(value) {
+        v.to_string()
+    } else if let Some(v) = as_u64(-value) {
+        format!("-{}", v)
+    } else {
+        // Format value as hex.
+        let s = format!("{:?}", value);
+        // Remove leading zeroes.
+        let s = s.strip_prefix("0x").unwrap();
+        let s = s.trim_start_matches('0');
+        format!("0x{}", s)
+    }
+}
+
+/// Returns `value` as a `u64`, if it fits in one.
+fn as_u64<F: FieldExt>(value: F) -> Option<u64> {
+    let lower = value.get_lower_128();
+    if lower <= u64::MAX as u128 && F::from_u128(lower) == value {
+        Some(lower as u64)
+    } else {
+        None
+    }
+}
@@ -1,7 +1,11 @@
+use std::collections::HashMap;
+
 use ff::Field;
 use tabbycat::{AttrList, Edge, GraphBuilder, GraphType, Identity, StmtList};
 
+use super::{metadata, MockProver};
 use crate::{
+    arithmetic::Group,
     circuit::Value,
     plonk::{
         Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
@@ -65,6 +69,58 @@ pub fn circuit_dot_graph<F: Field, ConcreteCircuit: Circuit<F>>(
         .to_string()
 }
 
+/// Builds a dot graph string representing the copy constraints of the given
+/// [`MockProver`].
+///
+/// Each node is a cell that is constrained to be equal to at least one other cell, so
+/// every connected component is one of the [`MockProver::equality_classes`]. Edges
+/// follow the cycles of the permutation argument, so a class of `m` cells is drawn as a
+/// cycle of `m` edges. Cells that aren't copied to any other cell are omitted.
+pub fn permutation_dot_graph<F: Group + Field>(prover: &MockProver<F>) -> String {
+    let columns = prover.cs.permutation.get_columns();
+
+    // Number the cells in class order, so that the nodes of each class are adjacent.
+    let cells: Vec<_> = prover.equality_classes().into_iter().flatten().collect();
+    let ids: HashMap<_, _> = cells
+        .iter()
+        .enumerate()
+        .map(|(id, cell)| (*cell, id))
+        .collect();
+
+    // Construct the node labels. As in `circuit_dot_graph`, these must outlive the
+    // statements that refer to them.
+    let node_labels: Vec<_> = cells
+        .iter()
+        .map(|(column, row)| format!("{}, row {}", metadata::Column::from(*column), row))
+        .collect();
+
+    let mut stmts = StmtList::new();
+    for (id, label) in node_labels.iter().enumerate() {
+        stmts = stmts.add_node(
+            id.into(),
+            None,
+            Some(AttrList::new().add_pair(tabbycat::attributes::label(label))),
+        );
+    }
+    for (column, row) in cells.iter() {
+        let index = columns.iter().position(|c| c == column).unwrap();
+        let (next_index, next_row) = prover.permutation.mapping[index][*row];
+        let next = ids[&(columns[next_index], next_row)];
+        stmts = stmts.add_edge(
+            Edge::head_node(ids[&(*column, *row)].into(), None).arrow_to_node(next.into(), None),
+        );
+    }
+
+    GraphBuilder::default()
+        .graph_type(GraphType::DiGraph)
+        .strict(false)
+        .id(Identity::id("permutation").unwrap())
+        .stmts(stmts)
+        .build()
+        .unwrap()
+        .to_string()
+}
+
 #[derive(Default)]
 struct Graph {
     /// Graph nodes in the namespace, structured as `(name, gadget_name)`.
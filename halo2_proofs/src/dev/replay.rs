@@ -0,0 +1,142 @@
+use super::{MockProver, MockProverError, RunOptions};
+use crate::{
+    arithmetic::FieldExt,
+    circuit,
+    plonk::{Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Fixed, Selector},
+};
+
+/// An event recorded while synthesizing a circuit with [`MockProver::run_recorded`].
+///
+/// An ordered list of these events is a trace of the circuit's witness generation, which
+/// can be replayed into a fresh `MockProver` with [`replay_trace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssignmentEvent<F> {
+    /// A region with the given name was entered.
+    EnterRegion {
+        /// The name of the region.
+        name: String,
+    },
+    /// The current region was exited.
+    ExitRegion,
+    /// A selector was enabled at a row.
+    EnableSelector {
+        /// The enabled selector.
+        selector: Selector,
+        /// The row at which it was enabled.
+        row: usize,
+    },
+    /// An advice cell was assigned a value.
+    AssignAdvice {
+        /// The column containing the cell.
+        column: Column<Advice>,
+        /// The row of the cell.
+        row: usize,
+        /// The value assigned to the cell.
+        value: F,
+    },
+    /// A fixed cell was assigned a value.
+    AssignFixed {
+        /// The column containing the cell.
+        column: Column<Fixed>,
+        /// The row of the cell.
+        row: usize,
+        /// The value assigned to the cell.
+        value: F,
+    },
+    /// Two cells were constrained to be equal.
+    Copy {
+        /// The column containing the left cell.
+        left_column: Column<Any>,
+        /// The row of the left cell.
+        left_row: usize,
+        /// The column containing the right cell.
+        right_column: Column<Any>,
+        /// The row of the right cell.
+        right_row: usize,
+    },
+    /// A cell was marked as an output of the circuit.
+    MarkOutput {
+        /// The column containing the cell.
+        column: Column<Any>,
+        /// The row of the cell.
+        row: usize,
+    },
+}
+
+impl<F: FieldExt> MockProver<F> {
+    /// Behaves like [`MockProver::run`], but also returns the ordered list of
+    /// assignment events made while synthesizing the circuit.
+    ///
+    /// The events can be replayed with [`replay_trace`] to rebuild the same state
+    /// without the circuit, which allows reproducing a bug from a trace alone.
+    pub fn run_recorded<ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        circuit: &ConcreteCircuit,
+        instance: Vec<Vec<F>>,
+    ) -> Result<(Self, Vec<AssignmentEvent<F>>), MockProverError> {
+        let options = RunOptions {
+            record_events: true,
+            ..RunOptions::default()
+        };
+        let mut prover = Self::run_with_options(k, circuit, instance, options)
+            .and_then(Self::fully_synthesized)?;
+
+        let events = prover.events.take().unwrap();
+        Ok((prover, events))
+    }
+}
+
+/// Rebuilds a [`MockProver`] for the constraint system `cs` by applying the assignment
+/// events in `trace`, such as those recorded by [`MockProver::run_recorded`], in order.
+///
+/// This doesn't run `Circuit::synthesize`, so a bug can be reproduced from a trace when
+/// the circuit itself can't be shared. `cs` must be the constraint system that the
+/// trace was recorded from, and `instance` its instance columns.
+///
+/// No circuit config is taken: the events refer to columns and selectors directly, so
+/// the config returned by `Circuit::configure` is not needed, and `cs` can be built by
+/// calling it on an empty `ConstraintSystem`. Challenges are not part of the trace, and
+/// the returned `MockProver` uses the default ones.
+///
+/// Returns an error if an event fails to apply, and panics if the regions in the trace
+/// are not properly nested.
+pub fn replay_trace<F: FieldExt>(
+    k: u32,
+    cs: &ConstraintSystem<F>,
+    instance: Vec<Vec<F>>,
+    trace: &[AssignmentEvent<F>],
+) -> Result<MockProver<F>, MockProverError> {
+    let mut prover = MockProver::empty(k, cs.clone(), instance)?;
+
+    for event in trace {
+        let result = match event {
+            AssignmentEvent::EnterRegion { name } => {
+                prover.enter_region(|| name.clone());
+                Ok(())
+            }
+            AssignmentEvent::ExitRegion => {
+                prover.exit_region();
+                Ok(())
+            }
+            AssignmentEvent::EnableSelector { selector, row } => {
+                prover.enable_selector(|| "", selector, *row)
+            }
+            AssignmentEvent::AssignAdvice { column, row, value } => {
+                prover.assign_advice(|| "", *column, *row, || circuit::Value::known(*value))
+            }
+            AssignmentEvent::AssignFixed { column, row, value } => {
+                prover.assign_fixed(|| "", *column, *row, || circuit::Value::known(*value))
+            }
+            AssignmentEvent::Copy {
+                left_column,
+                left_row,
+                right_column,
+                right_row,
+            } => prover.copy(*left_column, *left_row, *right_column, *right_row),
+            AssignmentEvent::MarkOutput { column, row } => prover.mark_output(*column, *row),
+        };
+        result.map_err(|error| prover.detailed_error(error))?;
+    }
+
+    Ok(prover.compress_selectors())
+}
@@ -0,0 +1,429 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use super::{CellValue, MockProver, Region};
+use crate::{
+    arithmetic::FieldExt,
+    helpers::{self, SerdePrimeField},
+    plonk::{permutation, Any, Circuit, Column, ConstraintSystem, Selector},
+};
+
+/// Identifies a serialized `MockProver`.
+const MAGIC: &[u8; 8] = b"halo2mp\0";
+
+/// The version of the serialization format. This is bumped on every incompatible change.
+const VERSION: u8 = 1;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u8<W: io::Write>(writer: &mut W, value: u8) -> io::Result<()> {
+    writer.write_all(&[value])
+}
+
+fn read_u8<R: io::Read>(reader: &mut R) -> io::Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn write_u32<W: io::Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    let value = u32::try_from(value).map_err(|_| invalid_data("value does not fit in 32 bits"))?;
+    writer.write_all(&value.to_be_bytes())
+}
+
+fn read_u32<R: io::Read>(reader: &mut R) -> io::Result<usize> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes) as usize)
+}
+
+/// Reads a length-prefixed count, checking that it matches what the circuit expects.
+fn read_count<R: io::Read>(reader: &mut R, expected: usize, what: &str) -> io::Result<()> {
+    if read_u32(reader)? == expected {
+        Ok(())
+    } else {
+        Err(invalid_data(&format!(
+            "the number of {} doesn't match the circuit",
+            what
+        )))
+    }
+}
+
+fn write_cell<W: io::Write, F: FieldExt>(writer: &mut W, cell: &CellValue<F>) -> io::Result<()> {
+    match cell {
+        CellValue::Unassigned => write_u8(writer, 0),
+        CellValue::Assigned(value) => {
+            write_u8(writer, 1)?;
+            value.write(writer)
+        }
+        CellValue::Poison(row) => {
+            write_u8(writer, 2)?;
+            write_u32(writer, *row)
+        }
+    }
+}
+
+fn read_cell<R: io::Read, F: FieldExt>(reader: &mut R) -> io::Result<CellValue<F>> {
+    match read_u8(reader)? {
+        0 => Ok(CellValue::Unassigned),
+        1 => Ok(CellValue::Assigned(F::read(reader)?)),
+        2 => Ok(CellValue::Poison(read_u32(reader)?)),
+        _ => Err(invalid_data("invalid cell tag")),
+    }
+}
+
+fn write_column<W: io::Write>(writer: &mut W, column: &Column<Any>) -> io::Result<()> {
+    let tag = match column.column_type() {
+        Any::Advice(_) => 0,
+        Any::Fixed => 1,
+        Any::Instance => 2,
+    };
+    write_u8(writer, tag)?;
+    write_u32(writer, column.index())
+}
+
+fn write_selector<W: io::Write>(writer: &mut W, selector: &Selector) -> io::Result<()> {
+    write_u32(writer, selector.0)?;
+    write_u8(writer, selector.is_simple() as u8)
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "dev-serde")))]
+impl<F: FieldExt> MockProver<F> {
+    /// Reads a column of this `MockProver`'s circuit. Advice columns take their phase
+    /// from the constraint system.
+    fn read_column<R: io::Read>(&self, reader: &mut R) -> io::Result<Column<Any>> {
+        let tag = read_u8(reader)?;
+        let index = read_u32(reader)?;
+        match tag {
+            0 if index < self.advice.len() => Ok(self.advice_column(index)),
+            1 if index < self.fixed.len() => Ok(Column::new(index, Any::Fixed)),
+            2 if index < self.instance.len() => Ok(Column::new(index, Any::Instance)),
+            _ => Err(invalid_data("invalid column")),
+        }
+    }
+
+    /// Reads a selector of this `MockProver`'s circuit.
+    fn read_selector<R: io::Read>(&self, reader: &mut R) -> io::Result<Selector> {
+        let index = read_u32(reader)?;
+        let simple = read_u8(reader)? != 0;
+        if index < self.selectors.len() {
+            Ok(Selector(index, simple))
+        } else {
+            Err(invalid_data("invalid selector"))
+        }
+    }
+
+    fn write_region<W: io::Write>(writer: &mut W, region: &Region) -> io::Result<()> {
+        write_u32(writer, region.name.len())?;
+        writer.write_all(region.name.as_bytes())?;
+
+        write_u32(writer, region.columns.len())?;
+        for column in &region.columns {
+            write_column(writer, column)?;
+        }
+
+        match region.rows {
+            Some((start, end)) => {
+                write_u8(writer, 1)?;
+                write_u32(writer, start)?;
+                write_u32(writer, end)?;
+            }
+            None => write_u8(writer, 0)?,
+        }
+
+        write_u32(writer, region.enabled_selectors.len())?;
+        for (selector, rows) in &region.enabled_selectors {
+            write_selector(writer, selector)?;
+            write_u32(writer, rows.len())?;
+            for row in rows {
+                write_u32(writer, *row)?;
+            }
+        }
+
+        write_u32(writer, region.cells.len())?;
+        for ((column, row), count) in &region.cells {
+            write_column(writer, column)?;
+            write_u32(writer, *row)?;
+            write_u32(writer, *count)?;
+        }
+        Ok(())
+    }
+
+    fn read_region<R: io::Read>(&self, reader: &mut R) -> io::Result<Region> {
+        let mut name = vec![0u8; read_u32(reader)?];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| invalid_data("invalid region name"))?;
+
+        let columns = (0..read_u32(reader)?)
+            .map(|_| self.read_column(reader))
+            .collect::<io::Result<HashSet<_>>>()?;
+
+        let rows = match read_u8(reader)? {
+            0 => None,
+            _ => Some((read_u32(reader)?, read_u32(reader)?)),
+        };
+
+        let enabled_selectors = (0..read_u32(reader)?)
+            .map(|_| {
+                let selector = self.read_selector(reader)?;
+                let rows = (0..read_u32(reader)?)
+                    .map(|_| read_u32(reader))
+                    .collect::<io::Result<Vec<_>>>()?;
+                Ok::<_, io::Error>((selector, rows))
+            })
+            .collect::<io::Result<HashMap<_, _>>>()?;
+
+        let cells = (0..read_u32(reader)?)
+            .map(|_| {
+                let column = self.read_column(reader)?;
+                let row = read_u32(reader)?;
+                let count = read_u32(reader)?;
+                Ok::<_, io::Error>(((column, row), count))
+            })
+            .collect::<io::Result<HashMap<_, _>>>()?;
+
+        Ok(Region {
+            name,
+            columns,
+            rows,
+            enabled_selectors,
+            cells,
+        })
+    }
+
+    /// Writes the state of this `MockProver` to a buffer, so that it can be verified
+    /// later without synthesizing the circuit again.
+    ///
+    /// This writes the assigned cells, regions, selectors, challenges and copy
+    /// constraints, along with the settings made by [`MockProver::set_strict`],
+    /// [`MockProver::set_report_instance_mismatches`] and
+    /// [`MockProver::set_unassigned_default`]. Field elements are written in their
+    /// canonical representation. The constraint system is not written; it is recreated
+    /// from the circuit by [`MockProver::read`]. A custom
+    /// [`FieldFormatter`](super::FieldFormatter) is not written either, and neither is
+    /// a region that was never exited (see [`MockProver::run_partial`]).
+    ///
+    /// The output starts with a magic header and a format version, and is only
+    /// guaranteed to be readable by the same version of this crate.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        write_u8(writer, VERSION)?;
+        write_u32(writer, self.k as usize)?;
+
+        write_u32(writer, self.instance.len())?;
+        for column in &self.instance {
+            write_u32(writer, column.len())?;
+            for value in column {
+                value.write(writer)?;
+            }
+        }
+
+        // The columns that selectors were compressed into are recreated from the
+        // selectors when reading.
+        let selector_columns: HashSet<usize> =
+            self.cs.selector_map.iter().map(|c| c.index()).collect();
+        write_u32(writer, self.fixed.len() - selector_columns.len())?;
+        for (_, column) in self
+            .fixed
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !selector_columns.contains(index))
+        {
+            for cell in column {
+                write_cell(writer, cell)?;
+            }
+        }
+
+        write_u32(writer, self.advice.len())?;
+        for column in &self.advice {
+            for cell in column {
+                write_cell(writer, cell)?;
+            }
+        }
+
+        write_u32(writer, self.selectors.len())?;
+        for selector in &self.selectors {
+            // Selectors are filled with `bool`s, so we pack them 8 at a time into bytes.
+            for bits in selector.chunks(8) {
+                write_u8(writer, helpers::pack(bits))?;
+            }
+        }
+
+        write_u32(writer, self.challenges.len())?;
+        for challenge in &self.challenges {
+            challenge.write(writer)?;
+        }
+        match &self.challenge_seed {
+            Some(seed) => {
+                write_u8(writer, 1)?;
+                write_u32(writer, seed.len())?;
+                writer.write_all(seed)?;
+            }
+            None => write_u8(writer, 0)?,
+        }
+
+        self.permutation.write(writer)?;
+
+        write_u32(writer, self.regions.len())?;
+        for region in &self.regions {
+            Self::write_region(writer, region)?;
+        }
+
+        write_u32(writer, self.floating_selectors.len())?;
+        for (selector, row) in &self.floating_selectors {
+            write_selector(writer, selector)?;
+            write_u32(writer, *row)?;
+        }
+
+        self.unassigned_default.write(writer)?;
+        write_u8(writer, self.strict as u8)?;
+        write_u8(writer, self.check_copies as u8)?;
+        write_u8(writer, self.report_instance_mismatches as u8)?;
+
+        write_u32(writer, self.outputs.len())?;
+        for (column, row) in &self.outputs {
+            write_column(writer, column)?;
+            write_u32(writer, *row)?;
+        }
+
+        write_u32(writer, self.instance_lens.len())?;
+        for len in &self.instance_lens {
+            write_u32(writer, *len)?;
+        }
+
+        write_u32(writer, self.conflicting_assignments.len())?;
+        for (column, row, earlier, later) in &self.conflicting_assignments {
+            write_column(writer, column)?;
+            write_u32(writer, *row)?;
+            write_u32(writer, *earlier)?;
+            write_u32(writer, *later)?;
+        }
+
+        // Sort the shared cells, so that the output is deterministic.
+        let mut shared_cells: Vec<_> = self.shared_cells.iter().collect();
+        shared_cells.sort_unstable();
+        write_u32(writer, shared_cells.len())?;
+        for (column, row) in shared_cells {
+            write_column(writer, column)?;
+            write_u32(writer, *row)?;
+        }
+        write_u8(writer, self.instance_queried.get() as u8)?;
+
+        Ok(())
+    }
+
+    /// Reads the state of a `MockProver` for `ConcreteCircuit` that was written by
+    /// [`MockProver::write`].
+    ///
+    /// The constraint system is recreated with `ConcreteCircuit::configure`, so this
+    /// fails if the circuit's columns or selectors have changed since the state was
+    /// written.
+    pub fn read<R: io::Read, ConcreteCircuit: Circuit<F>>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a serialized MockProver"));
+        }
+        if read_u8(reader)? != VERSION {
+            return Err(invalid_data("unsupported MockProver serialization version"));
+        }
+        let k = read_u32(reader)? as u32;
+        if k >= usize::BITS {
+            return Err(invalid_data("k is too large"));
+        }
+        let n = 1usize << k;
+
+        let mut cs = ConstraintSystem::default();
+        ConcreteCircuit::configure(&mut cs);
+
+        read_count(reader, cs.num_instance_columns, "instance columns")?;
+        let instance = (0..cs.num_instance_columns)
+            .map(|_| {
+                (0..read_u32(reader)?)
+                    .map(|_| F::read(reader))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let num_instance_columns = cs.num_instance_columns;
+        let mut prover = Self::empty(k, cs, vec![vec![]; num_instance_columns])
+            .map_err(|e| invalid_data(&e.to_string()))?;
+        prover.instance = instance;
+
+        let mut read_columns = |columns: &mut Vec<Vec<CellValue<F>>>, what| {
+            read_count(reader, columns.len(), what)?;
+            for cell in columns.iter_mut().flatten() {
+                *cell = read_cell(reader)?;
+            }
+            Ok::<_, io::Error>(())
+        };
+        read_columns(&mut prover.fixed, "fixed columns")?;
+        read_columns(&mut prover.advice, "advice columns")?;
+
+        read_count(reader, prover.selectors.len(), "selectors")?;
+        for selector in prover.selectors.iter_mut() {
+            let mut bytes = vec![0u8; (n + 7) / 8];
+            reader.read_exact(&mut bytes)?;
+            for (bits, byte) in selector.chunks_mut(8).zip(bytes) {
+                helpers::unpack(byte, bits);
+            }
+        }
+
+        read_count(reader, prover.challenges.len(), "challenges")?;
+        for challenge in prover.challenges.iter_mut() {
+            *challenge = F::read(reader)?;
+        }
+        prover.challenge_seed = match read_u8(reader)? {
+            0 => None,
+            _ => {
+                let mut seed = vec![0u8; read_u32(reader)?];
+                reader.read_exact(&mut seed)?;
+                Some(seed)
+            }
+        };
+
+        prover.permutation =
+            permutation::keygen::Assembly::read(reader, n, &prover.cs.permutation)?;
+
+        prover.regions = (0..read_u32(reader)?)
+            .map(|_| prover.read_region(reader))
+            .collect::<io::Result<_>>()?;
+
+        prover.floating_selectors = (0..read_u32(reader)?)
+            .map(|_| Ok::<_, io::Error>((prover.read_selector(reader)?, read_u32(reader)?)))
+            .collect::<io::Result<_>>()?;
+
+        prover.unassigned_default = F::read(reader)?;
+        prover.strict = read_u8(reader)? != 0;
+        prover.check_copies = read_u8(reader)? != 0;
+        prover.report_instance_mismatches = read_u8(reader)? != 0;
+
+        prover.outputs = (0..read_u32(reader)?)
+            .map(|_| Ok::<_, io::Error>((prover.read_column(reader)?, read_u32(reader)?)))
+            .collect::<io::Result<_>>()?;
+
+        read_count(reader, prover.instance.len(), "instance columns")?;
+        for len in prover.instance_lens.iter_mut() {
+            *len = read_u32(reader)?;
+        }
+
+        prover.conflicting_assignments = (0..read_u32(reader)?)
+            .map(|_| {
+                Ok::<_, io::Error>((
+                    prover.read_column(reader)?,
+                    read_u32(reader)?,
+                    read_u32(reader)?,
+                    read_u32(reader)?,
+                ))
+            })
+            .collect::<io::Result<_>>()?;
+        prover.shared_cells = (0..read_u32(reader)?)
+            .map(|_| Ok::<_, io::Error>((prover.read_column(reader)?, read_u32(reader)?)))
+            .collect::<io::Result<_>>()?;
+        prover.instance_queried.set(read_u8(reader)? != 0);
+
+        Ok(prover.compress_selectors())
+    }
+}
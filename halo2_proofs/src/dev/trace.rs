@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use super::{metadata, CellValue, MockProver};
+use crate::{
+    arithmetic::FieldExt,
+    plonk::{Any, Column},
+};
+
+/// A cell whose value differs between a [`MockProver`] and a reference trace, as found
+/// by [`MockProver::check_against_trace`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct CellDiff {
+    /// The column containing the cell.
+    pub column: metadata::Column,
+    /// The row of the cell.
+    pub row: usize,
+    /// The value of the cell in the reference trace, or `None` if it was unassigned.
+    pub expected: Option<String>,
+    /// The value of the cell in the `MockProver`, or `None` if it is unassigned.
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for CellDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "unassigned".into());
+        write!(
+            f,
+            "Cell ({}, row {}) was {} but is now {}",
+            self.column,
+            self.row,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// A cell in a trace, keyed by whether it is an advice cell, its column index, and its
+/// row.
+type TraceKey = (bool, usize, usize);
+
+impl<F: FieldExt> MockProver<F> {
+    /// Writes the assigned advice and fixed cells of this `MockProver` to `w`, as CSV
+    /// with the header `column,row,value`.
+    ///
+    /// Columns are labelled `A{index}` for advice and `F{index}` for fixed columns.
+    /// Unassigned cells, and the fixed columns that selectors were compressed into, are
+    /// omitted. The output can later be compared against with
+    /// [`MockProver::check_against_trace`].
+    pub fn export_trace<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "column,row,value")?;
+        for ((advice, index, row), value) in self.trace() {
+            let prefix = if advice { 'A' } else { 'F' };
+            writeln!(w, "{}{},{},{}", prefix, index, row, value)?;
+        }
+        Ok(())
+    }
+
+    /// Compares the assigned advice and fixed cells of this `MockProver` against a
+    /// trace previously written by [`MockProver::export_trace`], returning every cell
+    /// whose value differs.
+    ///
+    /// This is intended for regression tests that check a chip's witness does not
+    /// change unintentionally: export the trace once, and check against it thereafter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trace can't be read or is malformed.
+    pub fn check_against_trace<R: Read>(&self, r: R) -> Result<(), Vec<CellDiff>> {
+        let mut expected = BTreeMap::new();
+        for (i, line) in BufReader::new(r).lines().enumerate() {
+            let line = line.expect("failed to read trace");
+            if i == 0 || line.is_empty() {
+                // Skip the header.
+                continue;
+            }
+
+            let (key, value) = parse_trace_line(&line)
+                .unwrap_or_else(|| panic!("malformed trace line {}: {}", i + 1, line));
+            expected.insert(key, value);
+        }
+
+        let mut actual = self.trace();
+        let keys: BTreeSet<_> = expected.keys().chain(actual.keys()).cloned().collect();
+
+        let mut diffs: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let expected = expected.remove(&key);
+                let actual = actual.remove(&key);
+                if expected == actual {
+                    return None;
+                }
+                let (advice, index, row) = key;
+                let column = if !advice {
+                    Column::new(index, Any::Fixed)
+                } else if index < self.advice.len() {
+                    self.advice_column(index)
+                } else {
+                    // The trace has more advice columns than this circuit.
+                    Column::new(index, Any::advice())
+                };
+                Some(CellDiff {
+                    column: column.into(),
+                    row,
+                    expected,
+                    actual,
+                })
+            })
+            .collect();
+
+        if diffs.is_empty() {
+            Ok(())
+        } else {
+            diffs.sort_unstable_by_key(|diff| (diff.column, diff.row));
+            Err(diffs)
+        }
+    }
+
+    /// Returns the values of the assigned advice and fixed cells, excluding the fixed
+    /// columns that selectors were compressed into.
+    fn trace(&self) -> BTreeMap<TraceKey, String> {
+        let selector_columns: HashSet<usize> =
+            self.cs.selector_map.iter().map(|c| c.index()).collect();
+
+        let advice = self
+            .advice
+            .iter()
+            .enumerate()
+            .map(|(index, column)| (true, index, column));
+        let fixed = self
+            .fixed
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !selector_columns.contains(index))
+            .map(|(index, column)| (false, index, column));
+
+        advice
+            .chain(fixed)
+            .flat_map(|(advice, index, column)| {
+                column
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(row, cell)| match cell {
+                        CellValue::Assigned(value) => {
+                            Some(((advice, index, row), format!("{:?}", value)))
+                        }
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Parses a line of a trace written by [`MockProver::export_trace`].
+fn parse_trace_line(line: &str) -> Option<(TraceKey, String)> {
+    let mut parts = line.splitn(3, ',');
+    let column = parts.next()?;
+    let row = parts.next()?.parse().ok()?;
+    let value = parts.next()?;
+
+    let advice = match column.chars().next()? {
+        'A' => true,
+        'F' => false,
+        _ => return None,
+    };
+    let index = column.get(1..)?.parse().ok()?;
+
+    Some(((advice, index, row), value.to_string()))
+}
@@ -3,7 +3,7 @@ use std::collections::BTreeMap;
 use group::ff::Field;
 use halo2curves::FieldExt;
 
-use super::{metadata, CellValue, Value};
+use super::{metadata, CellValue, FieldFormatter, Value};
 use crate::{
     plonk::{
         Advice, AdviceQuery, Any, Column, ColumnType, Expression, FixedQuery, Gate, InstanceQuery,
@@ -78,11 +78,12 @@ pub(super) fn load<'a, F: FieldExt, T: ColumnType, Q: Into<AnyQuery> + Copy>(
     row: i32,
     queries: &'a [(Column<T>, Rotation)],
     cells: &'a [Vec<CellValue<F>>],
+    unassigned: F,
 ) -> impl Fn(Q) -> Value<F> + 'a {
     move |query| {
         let (column, at) = &queries[query.into().index];
-        let resolved_row = (row + at.0) % n;
-        cells[column.index()][resolved_row as usize].into()
+        let resolved_row = (row + n + at.0) % n;
+        cells[column.index()][resolved_row as usize].with_default(unassigned)
     }
 }
 
@@ -94,14 +95,21 @@ pub(super) fn load_instance<'a, F: FieldExt, T: ColumnType, Q: Into<AnyQuery> +
 ) -> impl Fn(Q) -> Value<F> + 'a {
     move |query| {
         let (column, at) = &queries[query.into().index];
-        let resolved_row = (row + at.0) % n;
-        Value::Real(cells[column.index()][resolved_row as usize])
+        let resolved_row = (row + n + at.0) % n;
+        // Instance columns that are shorter than `n` are implicitly padded with zeroes.
+        Value::Real(
+            cells[column.index()]
+                .get(resolved_row as usize)
+                .copied()
+                .unwrap_or_else(F::zero),
+        )
     }
 }
 
 fn cell_value<'a, F: FieldExt, Q: Into<AnyQuery> + Copy>(
     virtual_cells: &'a [VirtualCell],
     load: impl Fn(Q) -> Value<F> + 'a,
+    format: FieldFormatter<F>,
 ) -> impl Fn(Q) -> BTreeMap<metadata::VirtualCell, String> + 'a {
     move |query| {
         let AnyQuery {
@@ -122,7 +130,7 @@ fn cell_value<'a, F: FieldExt, Q: Into<AnyQuery> + Copy>(
                 (
                     cell.clone().into(),
                     match load(query) {
-                        Value::Real(v) => format_value(v),
+                        Value::Real(v) => format(v),
                         Value::Poison => unreachable!(),
                     },
                 )
@@ -138,14 +146,16 @@ pub(super) fn cell_values<'a, F: FieldExt>(
     load_fixed: impl Fn(FixedQuery) -> Value<F> + 'a,
     load_advice: impl Fn(AdviceQuery) -> Value<F> + 'a,
     load_instance: impl Fn(InstanceQuery) -> Value<F> + 'a,
+    format: FieldFormatter<F>,
 ) -> Vec<(metadata::VirtualCell, String)> {
     let virtual_cells = gate.queried_cells();
     let cell_values = poly.evaluate(
         &|_| BTreeMap::default(),
-        &|_| panic!("virtual selectors are removed during optimization"),
-        &cell_value(virtual_cells, load_fixed),
-        &cell_value(virtual_cells, load_advice),
-        &cell_value(virtual_cells, load_instance),
+        // Selectors are only left in expressions before they are compressed.
+        &|_| BTreeMap::default(),
+        &cell_value(virtual_cells, load_fixed, format),
+        &cell_value(virtual_cells, load_advice, format),
+        &cell_value(virtual_cells, load_instance, format),
         &|_| BTreeMap::default(),
         &|a| a,
         &|mut a, mut b| {
@@ -29,7 +29,6 @@ pub struct Column<C: ColumnType> {
 }
 
 impl<C: ColumnType> Column<C> {
-    #[cfg(test)]
     pub(crate) fn new(index: usize, column_type: C) -> Self {
         Column { index, column_type }
     }
@@ -371,7 +370,7 @@ impl TryFrom<Column<Any>> for Column<Instance> {
 /// }
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct Selector(pub(crate) usize, bool);
+pub struct Selector(pub(crate) usize, pub(crate) bool);
 
 impl Selector {
     /// Enable this selector at the given offset within the given region.
@@ -586,6 +585,20 @@ pub trait Assignment<F: Field> {
         right_row: usize,
     ) -> Result<(), Error>;
 
+    /// Marks the cell at the given absolute row of `column` as an output of the
+    /// circuit.
+    ///
+    /// This doesn't constrain anything; it only records which cells a chip intends to
+    /// expose, so that tools such as [`MockProver`](crate::dev::MockProver) can report
+    /// them. The default implementation ignores the mark.
+    ///
+    /// Not intended for downstream consumption; use [`Region::mark_output`] instead.
+    ///
+    /// [`Region::mark_output`]: crate::circuit::Region#method.mark_output
+    fn mark_output(&mut self, _column: Column<Any>, _row: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Fills a fixed `column` starting from the given `row` with value `to`.
     fn fill_from_row(
         &mut self,
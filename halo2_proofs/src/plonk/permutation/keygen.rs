@@ -1,5 +1,7 @@
 use ff::Field;
 use group::Curve;
+#[cfg(feature = "dev-serde")]
+use std::io;
 
 use super::{Argument, ProvingKey, VerifyingKey};
 use crate::{
@@ -11,7 +13,7 @@ use crate::{
     },
 };
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(crate) struct Assembly {
     columns: Vec<Column<Any>>,
     pub(crate) mapping: Vec<Vec<(usize, usize)>>,
@@ -97,6 +99,105 @@ impl Assembly {
         Ok(())
     }
 
+    /// Writes the cycles of this assembly to a buffer.
+    #[cfg(feature = "dev-serde")]
+    pub(crate) fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for cells in [&self.mapping, &self.aux] {
+            for (column, row) in cells.iter().flatten() {
+                writer.write_all(&(*column as u32).to_be_bytes())?;
+                writer.write_all(&(*row as u32).to_be_bytes())?;
+            }
+        }
+        for size in self.sizes.iter().flatten() {
+            writer.write_all(&(*size as u32).to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads an assembly with `n` rows for the given permutation argument from a buffer.
+    #[cfg(feature = "dev-serde")]
+    pub(crate) fn read<R: io::Read>(reader: &mut R, n: usize, p: &Argument) -> io::Result<Self> {
+        let mut read_u32 = || -> io::Result<usize> {
+            let mut bytes = [0u8; 4];
+            reader.read_exact(&mut bytes)?;
+            Ok(u32::from_be_bytes(bytes) as usize)
+        };
+
+        let mut assembly = Self::new(n, p);
+        for cells in [&mut assembly.mapping, &mut assembly.aux] {
+            for cell in cells.iter_mut().flatten() {
+                let column = read_u32()?;
+                let row = read_u32()?;
+                if column >= p.columns.len() || row >= n {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "permutation cell is out of bounds",
+                    ));
+                }
+                *cell = (column, row);
+            }
+        }
+        for size in assembly.sizes.iter_mut().flatten() {
+            *size = read_u32()?;
+        }
+        assembly
+            .check_cycles()
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))?;
+        Ok(assembly)
+    }
+
+    /// Checks that `mapping` is a permutation of the cells, and that `aux` and `sizes`
+    /// describe its cycles. `copy` walks the cycles of `mapping`, so it would loop
+    /// forever on a mapping that isn't a bijection.
+    #[cfg(feature = "dev-serde")]
+    fn check_cycles(&self) -> Result<(), &'static str> {
+        let mut visited: Vec<_> = self
+            .mapping
+            .iter()
+            .map(|cells| vec![false; cells.len()])
+            .collect();
+        for (column, cells) in self.mapping.iter().enumerate() {
+            for row in 0..cells.len() {
+                if visited[column][row] {
+                    continue;
+                }
+
+                // Walk the cycle starting at this cell. Every cell of a bijection is on
+                // exactly one cycle, so reaching a visited cell before getting back to
+                // the start means that two cells are mapped to the same one.
+                let start = (column, row);
+                let distinguished = self.aux[column][row];
+                let mut has_distinguished = false;
+                let mut size = 0;
+                let mut cell = start;
+                loop {
+                    if visited[cell.0][cell.1] {
+                        return Err("permutation mapping is not a bijection");
+                    }
+                    visited[cell.0][cell.1] = true;
+                    if self.aux[cell.0][cell.1] != distinguished {
+                        return Err("permutation cycle has inconsistent distinguished cells");
+                    }
+                    has_distinguished |= cell == distinguished;
+                    size += 1;
+
+                    cell = self.mapping[cell.0][cell.1];
+                    if cell == start {
+                        break;
+                    }
+                }
+
+                if !has_distinguished {
+                    return Err("permutation cycle has inconsistent distinguished cells");
+                }
+                if self.sizes[distinguished.0][distinguished.1] != size {
+                    return Err("permutation cycle has the wrong size");
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn build_vk<'params, C: CurveAffine, P: Params<'params, C>>(
         self,
         params: &P,
@@ -208,3 +309,56 @@ impl Assembly {
         }
     }
 }
+
+#[cfg(all(test, feature = "dev-serde"))]
+mod tests {
+    use super::{Argument, Assembly};
+    use crate::plonk::{Advice, Column};
+
+    #[test]
+    fn read_rejects_corrupt_cycles() {
+        const N: usize = 4;
+
+        let mut argument = Argument::new();
+        argument.add_column(Column::new(0, Advice::default()).into());
+        argument.add_column(Column::new(1, Advice::default()).into());
+
+        let mut assembly = Assembly::new(N, &argument);
+        assembly
+            .copy(argument.columns[0], 1, argument.columns[1], 2)
+            .unwrap();
+
+        let roundtrip = |assembly: &Assembly| {
+            let mut bytes = vec![];
+            assembly.write(&mut bytes).unwrap();
+            Assembly::read(&mut &bytes[..], N, &argument)
+        };
+        let read = roundtrip(&assembly).unwrap();
+        assert_eq!(read.mapping, assembly.mapping);
+
+        // Two cells mapped to the same cell.
+        let mut corrupt = assembly.clone();
+        corrupt.mapping[0][0] = corrupt.mapping[0][3];
+        assert_eq!(
+            roundtrip(&corrupt).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        // A cell whose distinguished cell is on another cycle.
+        let mut corrupt = assembly.clone();
+        corrupt.aux[0][0] = (1, 0);
+        assert_eq!(
+            roundtrip(&corrupt).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        // A cycle whose recorded size is wrong.
+        let mut corrupt = assembly;
+        let (column, row) = corrupt.aux[0][1];
+        corrupt.sizes[column][row] = 1;
+        assert_eq!(
+            roundtrip(&corrupt).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+}